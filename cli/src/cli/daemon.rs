@@ -1,11 +1,14 @@
 use crate::cli::get_lock_file_path;
 use crate::cli::get_socket_path;
+use crate::cli::output::{self, OutputFormat};
 use anyhow::{anyhow, Context, Result};
+use chrono::DateTime;
 use colored::*;
 use gavel_core::rpc::{
-    message::{DaemonAction, Message},
+    message::{DaemonAction, DaemonStatus, Message},
     request_reply,
 }; // Import RPC functions and messages
+use gavel_core::utils::time::{format_duration_secs, now_secs};
 use std::{env, fs, path::Path, process::Command};
 use structopt::StructOpt; // Import colored
 
@@ -34,11 +37,11 @@ pub enum DaemonCommand {
 }
 
 impl DaemonCommand {
-    pub fn execute(self) -> Result<()> {
+    pub fn execute(self, output: OutputFormat) -> Result<()> {
         match self {
             Self::Start { ref config } => Self::handle_start(config.as_deref()),
             Self::Stop { ref config } => Self::handle_stop(config.as_deref()), // Pass config
-            Self::Status { ref config } => Self::handle_status(config.as_deref()), // Pass config
+            Self::Status { ref config } => Self::handle_status(config.as_deref(), output),
         }
     }
 
@@ -52,7 +55,7 @@ impl DaemonCommand {
                 "[INFO]".blue(),
                 lock_file_path.display()
             );
-            match Self::handle_status(config) {
+            match Self::handle_status(config, OutputFormat::Table) {
                 Ok(_) => {
                     println!(
                         "{} Daemon appears to be running (RPC status check successful).",
@@ -145,7 +148,7 @@ impl DaemonCommand {
         // Optional: Short delay and then check status via RPC to confirm startup
         std::thread::sleep(std::time::Duration::from_millis(500));
         println!("{} Verifying daemon status via RPC...", "[INFO]".blue());
-        if let Err(e) = Self::handle_status(config) {
+        if let Err(e) = Self::handle_status(config, OutputFormat::Table) {
             println!(
                 "{} Daemon process started, but initial status check failed: {}",
                 "[WARN]".yellow(),
@@ -193,9 +196,8 @@ impl DaemonCommand {
                 }
                 Ok(())
             }
-            Ok(Message::Error(err_msg)) => {
-                Err(anyhow!("{} Daemon reported error during stop: {}", "[ERROR]".red(), err_msg))
-            }
+            Ok(Message::Error(err)) => Err(anyhow::Error::new(err)
+                .context(format!("{} Daemon reported error during stop", "[ERROR]".red()))),
             Ok(other) => Err(anyhow!(
                 "{} Received unexpected reply from daemon during stop: {:?}",
                 "[ERROR]".red(),
@@ -227,27 +229,32 @@ impl DaemonCommand {
         }
     }
 
-    fn handle_status(config: Option<&str>) -> Result<()> {
+    fn handle_status(config: Option<&str>, output: OutputFormat) -> Result<()> {
         let sock_path = get_socket_path(config)?; // Get socket path from config
 
-        println!(
-            "{} Checking daemon status via RPC (socket: {})...",
-            "[INFO]".blue(),
-            sock_path.cyan()
-        ); // Added color
+        if output.is_table() {
+            println!(
+                "{} Checking daemon status via RPC (socket: {})...",
+                "[INFO]".blue(),
+                sock_path.cyan()
+            ); // Added color
+        }
 
         let request = Message::DaemonCommand(DaemonAction::Status);
 
         match request_reply(&sock_path, &request) {
-            Ok(Message::Ack(status_msg)) => {
-                println!("{} Daemon status: {}", "[INFO]".blue(), status_msg.green().bold()); // Added format
+            Ok(Message::DaemonStatus(status)) if !output.is_table() => {
+                output::print_item(output, &status)
+            }
+            Ok(Message::DaemonStatus(status)) => {
+                Self::print_status(&status);
                 Ok(())
             }
             Ok(Message::Error(err_msg)) => {
                 println!(
                     "{} Daemon reported an error status: {}",
                     "[WARN]".yellow(),
-                    err_msg.italic()
+                    err_msg.to_string().italic()
                 ); // Added format
                 Ok(())
             }
@@ -257,13 +264,72 @@ impl DaemonCommand {
                 other
             )),
             Err(e) => {
-                println!(
-                    "{} Daemon is likely not running or unresponsive (RPC failed).",
-                    "[WARN]".yellow()
-                );
+                if output.is_table() {
+                    println!(
+                        "{} Daemon is likely not running or unresponsive (RPC failed).",
+                        "[WARN]".yellow()
+                    );
+                }
                 Err(anyhow!("{} Failed to communicate with daemon for status.", "[ERROR]".red())
                     .context(e))
             }
         }
     }
+
+    fn print_status(status: &DaemonStatus) {
+        let started = DateTime::from_timestamp(status.started_at as i64, 0)
+            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| "Invalid Time".to_string());
+        println!(
+            "{} Daemon is running: gavel {}, PID {}, up {} (since {})",
+            "[INFO]".blue(),
+            status.version,
+            status.pid,
+            format_duration_secs(now_secs().saturating_sub(status.started_at)),
+            started
+        );
+
+        let tasks = &status.tasks;
+        println!(
+            "{:<8} {} total: {} waiting, {} running, {} finished, {} failed, {} cancelled, \
+             {} timed out, {} retrying",
+            "Tasks:".bold(),
+            tasks.total,
+            tasks.waiting.to_string().cyan(),
+            tasks.running.to_string().green(),
+            tasks.finished.to_string().blue(),
+            tasks.failed.to_string().red(),
+            tasks.cancelled,
+            tasks.timed_out,
+            tasks.retrying
+        );
+
+        println!("{:<8} {}", "Queues:".bold(), status.queues.len());
+        for queue in &status.queues {
+            println!(
+                "  - {} (priority {}): {} waiting, {} running, GPUs {:?}",
+                queue.name.cyan(),
+                queue.priority.to_string().yellow(),
+                queue.waiting,
+                queue.running,
+                queue.allocated_gpus
+            );
+        }
+
+        let gpus = &status.gpus;
+        let unhealthy = if gpus.unhealthy > 0 {
+            format!("{} unhealthy", gpus.unhealthy).red()
+        } else {
+            "0 unhealthy".normal()
+        };
+        println!(
+            "{:<8} {} total: {} allocated, {} available, {} ignored, {}",
+            "GPUs:".bold(),
+            gpus.total,
+            gpus.allocated,
+            gpus.available.to_string().green(),
+            gpus.ignored,
+            unhealthy
+        );
+    }
 }
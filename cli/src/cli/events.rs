@@ -0,0 +1,82 @@
+use crate::cli::get_socket_path;
+use anyhow::{Context, Result};
+use chrono::DateTime;
+use colored::*;
+use gavel_core::rpc::message::{Event, EventFilter, EventKind};
+use gavel_core::rpc::BlockingGavelClient;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+pub struct EventsCommand {
+    /// Only show events of this queue (repeatable)
+    #[structopt(long = "queue")]
+    queues: Vec<String>,
+
+    /// Only show events of this task (repeatable)
+    #[structopt(long = "task")]
+    task_ids: Vec<u64>,
+
+    #[structopt(long)]
+    config: Option<String>,
+}
+
+impl EventsCommand {
+    pub fn execute(self) -> Result<()> {
+        let socket_path = get_socket_path(self.config.as_deref())?;
+        let filter = EventFilter { queues: self.queues, task_ids: self.task_ids };
+        let events = BlockingGavelClient::new(socket_path)
+            .subscribe(filter)
+            .context("Failed to subscribe to daemon events")?;
+        for event in events {
+            println!("{}", format_event(&event.context("Event stream broke")?));
+        }
+        println!("{} Daemon closed the event stream.", "[INFO]".blue());
+        Ok(())
+    }
+}
+
+fn format_event(event: &Event) -> String {
+    let time = DateTime::from_timestamp(event.time as i64, 0)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "Invalid Time".to_string());
+    let text = match &event.kind {
+        EventKind::TaskSubmitted { task_id, name, queue } => {
+            format!("Task {} '{}' submitted to queue '{}'", task_id, name, queue)
+        }
+        EventKind::TaskStateChanged { task_id, name, queue, from, to, reason } => {
+            let mut text =
+                format!("Task {} '{}' ({}): {:?} -> {:?}", task_id, name, queue, from, to);
+            if let Some(reason) = reason {
+                text.push_str(&format!(" ({})", reason));
+            }
+            text
+        }
+        EventKind::GpuAllocationChanged { gpu_id, from, to } => format!(
+            "GPU {} moved from {} to {}",
+            gpu_id,
+            from.as_deref().map_or("unallocated".to_string(), |q| format!("queue '{}'", q)),
+            to.as_deref().map_or("unallocated".to_string(), |q| format!("queue '{}'", q))
+        ),
+        EventKind::GpuHealthChanged { gpu_id, healthy: true, .. } => {
+            format!("GPU {} is healthy again", gpu_id).green().to_string()
+        }
+        EventKind::GpuHealthChanged { gpu_id, healthy: false, queue, reason } => format!(
+            "GPU {}{} is unhealthy: {}",
+            gpu_id,
+            queue.as_deref().map_or(String::new(), |q| format!(" (queue '{}')", q)),
+            reason.as_deref().unwrap_or("unknown reason")
+        )
+        .red()
+        .to_string(),
+        EventKind::QueueCreated { name, priority } => {
+            format!("Queue '{}' created with priority {}", name, priority)
+        }
+        EventKind::QueuesMerged { source, dest, moved_task_ids } => {
+            format!("Queue '{}' merged into '{}', moved tasks: {:?}", source, dest, moved_task_ids)
+        }
+        EventKind::Lagged { missed } => {
+            format!("Missed {} events, the subscriber fell behind", missed).yellow().to_string()
+        }
+    };
+    format!("[{}] {}", time.dimmed(), text)
+}
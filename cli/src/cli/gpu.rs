@@ -2,8 +2,11 @@ use anyhow::{anyhow, Result}; // Removed Context
 use gavel_core::rpc::message::{GPUAction, Message};
 use structopt::StructOpt;
 // Use the actual GpuStats struct from monitor
+use crate::cli::daemon_error;
 use crate::cli::get_socket_path;
+use crate::cli::output::{self, OutputFormat};
 use colored::*;
+use gavel_core::gpu::monitor::GpuStats;
 use gavel_core::rpc::request_reply; // Import colored
 
 #[derive(StructOpt, Debug)]
@@ -73,7 +76,7 @@ pub enum GpuCommand {
 }
 
 impl GpuCommand {
-    pub fn execute(self) -> Result<()> {
+    pub fn execute(self, output: OutputFormat) -> Result<()> {
         // Extract config path first
         let config_path: Option<String> = match &self {
             Self::List { config } => config.clone(),
@@ -86,8 +89,8 @@ impl GpuCommand {
         let socket_path = get_socket_path(config_path.as_deref())?;
 
         match self {
-            Self::List { .. } => Self::handle_list(&socket_path),
-            Self::Info { gpu_id, .. } => Self::handle_info(&socket_path, gpu_id),
+            Self::List { .. } => Self::handle_list(&socket_path, output),
+            Self::Info { gpu_id, .. } => Self::handle_info(&socket_path, gpu_id, output),
             Self::Allocate { gpu_ids, queue_name, .. } => {
                 Self::handle_allocate(&socket_path, gpu_ids, queue_name)
             }
@@ -97,11 +100,21 @@ impl GpuCommand {
         }
     }
 
-    fn handle_list(socket_path: &str) -> Result<()> {
-        println!("{} Listing all GPU statuses via RPC...", "[INFO]".blue());
+    fn handle_list(socket_path: &str, output: OutputFormat) -> Result<()> {
+        if output.is_table() {
+            println!("{} Listing all GPU statuses via RPC...", "[INFO]".blue());
+        }
         let request = Message::GPUCommand(GPUAction::List);
 
         match request_reply(socket_path, &request) {
+            Ok(Message::GPUStatus(mut gpus)) if !output.is_table() => {
+                gpus.sort_by_key(|gpu| gpu.gpu_index);
+                output::print_list(output, &gpus)
+            }
+            // No GPUs detected
+            Ok(Message::Ack(_)) if !output.is_table() => {
+                output::print_list::<GpuStats>(output, &[])
+            }
             // Correct match arm for the Message enum variant
             Ok(Message::GPUStatus(mut gpus)) => { // Add mut here
                 if gpus.is_empty() {
@@ -115,16 +128,17 @@ impl GpuCommand {
                     println!(
                         "{}",
                         format!(
-                            "{:<5} {:<12} {:<15} {:<15} {:<15}",
+                            "{:<5} {:<12} {:<15} {:<15} {:<15} {}",
                             "ID".bold(),
                             "Temp.".bold(),
                             "Core Usage".bold(),
                             "Mem Usage".bold(),
-                            "Pwr Usage".bold()
+                            "Pwr Usage".bold(),
+                            "Model".bold()
                         )
                         .underline()
                     );
-                    println!("{}", "-".repeat(70)); // Separator line
+                    println!("{}", "-".repeat(90)); // Separator line
                                                     // Use gpu_index from GpuStats directly
                     for gpu in gpus.iter() {
                         // Convert bytes to MB for memory
@@ -155,12 +169,13 @@ impl GpuCommand {
                         let power_usage_colored = format!("{}W", gpu.power_usage / 1000).normal(); // Assuming power_usage is in mW
 
                         println!(
-                            "{:<5} {:<12} {:<15} {:<15} {:<15}",
+                            "{:<5} {:<12} {:<15} {:<15} {:<15} {}",
                             gpu.gpu_index.to_string().yellow().bold(), // Use gpu_index
                             temp_colored,
                             core_usage_colored,
                             mem_usage_colored,
-                            power_usage_colored
+                            power_usage_colored,
+                            gpu.name
                         );
                     }
                 }
@@ -171,9 +186,7 @@ impl GpuCommand {
                 println!("{} Daemon reply: {}", "[INFO]".blue(), msg.italic()); // Format Ack
                 Ok(())
             }
-            Ok(Message::Error(err_msg)) => {
-                Err(anyhow!("{} Daemon returned error: {}", "[ERROR]".red(), err_msg))
-            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
             Ok(other) => {
                 Err(anyhow!("{} Received unexpected reply type: {:?}", "[ERROR]".red(), other))
             }
@@ -184,16 +197,28 @@ impl GpuCommand {
         }
     }
 
-    fn handle_info(socket_path: &str, gpu_id: u8) -> Result<()> {
-        println!(
-            "{} Getting info for GPU ID {} via RPC...",
-            "[INFO]".blue(),
-            gpu_id.to_string().yellow()
-        ); // Color GPU ID
-           // Correctly wrap gpu_id in Some for the message
+    fn handle_info(socket_path: &str, gpu_id: u8, output: OutputFormat) -> Result<()> {
+        if output.is_table() {
+            println!(
+                "{} Getting info for GPU ID {} via RPC...",
+                "[INFO]".blue(),
+                gpu_id.to_string().yellow()
+            ); // Color GPU ID
+        }
+        // Correctly wrap gpu_id in Some for the message
         let request = Message::GPUCommand(GPUAction::Info { gpu_id: Some(gpu_id) });
 
         match request_reply(socket_path, &request) {
+            Ok(Message::GPUStatus(gpus)) if !output.is_table() => {
+                match gpus.iter().find(|g| g.gpu_index == gpu_id as u32) {
+                    Some(gpu) => output::print_item(output, gpu),
+                    None => Err(anyhow!(
+                        "{} GPU with ID {} not found or no stats returned by daemon.",
+                        "[ERROR]".red(),
+                        gpu_id
+                    )),
+                }
+            }
             // Correct match arm and use actual GpuStats fields
             Ok(Message::GPUStatus(gpus)) => {
                 // The daemon returns a Vec<GpuStats>, even for a single ID request.
@@ -230,6 +255,7 @@ impl GpuCommand {
                     let power_usage_colored = format!("{}W", gpu.power_usage / 1000).normal(); // Assuming power_usage is in mW
 
                     println!("  {:<15} {}", "GPU Index:".green(), gpu.gpu_index.to_string().yellow());
+                    println!("  {:<15} {}", "Model:".green(), gpu.name);
                     println!("  {:<15} {}", "Temperature:".green(), temp_colored);
                     println!("  {:<15} {}", "Core Usage:".green(), core_usage_colored);
                     println!("  {:<15} {}", "Memory Usage:".green(), mem_usage_colored);
@@ -251,9 +277,13 @@ impl GpuCommand {
                 }
                 Ok(())
             }
-            Ok(Message::Error(err_msg)) => {
+            Ok(Message::Error(err)) => {
                 // Handle specific error message from daemon (e.g., GPU not found)
-                Err(anyhow!("{} Daemon error for GPU {}: {}", "[ERROR]".red(), gpu_id, err_msg))
+                Err(anyhow::Error::new(err).context(format!(
+                    "{} Daemon error for GPU {}",
+                    "[ERROR]".red(),
+                    gpu_id
+                )))
             }
             Ok(other) => Err(anyhow!(
                 "{} Received unexpected reply type for GPU {}: {:?}",
@@ -309,9 +339,7 @@ impl GpuCommand {
                 println!("{} Daemon reply: {}", "[SUCCESS]".green(), msg.italic()); // Format Ack
                 Ok(())
             }
-            Ok(Message::Error(err_msg)) => {
-                Err(anyhow!("{} Daemon returned error: {}", "[ERROR]".red(), err_msg))
-            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
             Ok(other) => {
                 Err(anyhow!("{} Received unexpected reply type: {:?}", "[ERROR]".red(), other))
             }
@@ -338,9 +366,7 @@ impl GpuCommand {
                 println!("{} Daemon reply: {}", "[SUCCESS]".green(), msg.italic()); // Format Ack
                 Ok(())
             }
-            Ok(Message::Error(err_msg)) => {
-                Err(anyhow!("{} Daemon returned error: {}", "[ERROR]".red(), err_msg))
-            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
             Ok(other) => {
                 Err(anyhow!("{} Received unexpected reply type: {:?}", "[ERROR]".red(), other))
             }
@@ -366,9 +392,7 @@ impl GpuCommand {
                 println!("{} Daemon reply: {}", "[SUCCESS]".green(), msg.italic()); // Format Ack
                 Ok(())
             }
-            Ok(Message::Error(err_msg)) => {
-                Err(anyhow!("{} Daemon returned error: {}", "[ERROR]".red(), err_msg))
-            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
             Ok(other) => {
                 Err(anyhow!("{} Received unexpected reply type: {:?}", "[ERROR]".red(), other))
             }
@@ -392,9 +416,7 @@ impl GpuCommand {
                 println!("{} Daemon reply: {}", "[SUCCESS]".green(), msg.italic()); // Format Ack
                 Ok(())
             }
-            Ok(Message::Error(err_msg)) => {
-                Err(anyhow!("{} Daemon returned error: {}", "[ERROR]".red(), err_msg))
-            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
             Ok(other) => {
                 Err(anyhow!("{} Received unexpected reply type: {:?}", "[ERROR]".red(), other))
             }
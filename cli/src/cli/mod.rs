@@ -1,28 +1,59 @@
 mod daemon;
+mod events;
 mod gpu;
+mod output;
 mod queue;
 mod submit;
 mod task;
+mod top;
 use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
 use std::{
     env, fs,
     path::{Path, PathBuf},
 };
 const LOCK_FILE_NAME: &str = "gavelrs.lock";
+use gavel_core::rpc::message::RpcError;
+use gavel_core::rpc::GavelError;
 use serde::Deserialize; // For reading config
 
 use structopt::{clap::AppSettings, StructOpt};
 // Aggregate all subcommand types
 use self::{
-    daemon::DaemonCommand, gpu::GpuCommand, queue::QueueCommand, submit::SubmitCommand,
-    task::TaskCommand,
+    daemon::DaemonCommand, events::EventsCommand, gpu::GpuCommand, queue::QueueCommand,
+    submit::SubmitCommand, task::TaskCommand, top::TopCommand,
 };
 
+pub use self::output::OutputFormat;
+
 #[derive(StructOpt, Debug)]
 #[structopt(
     name = "gavelrs",
     global_settings = &[AppSettings::DisableHelpSubcommand]
 )]
+pub struct App {
+    /// Output format of listing commands
+    #[structopt(
+        long,
+        short,
+        global = true,
+        default_value = "table",
+        possible_values = OutputFormat::NAMES
+    )]
+    output: OutputFormat,
+
+    #[structopt(subcommand)]
+    command: AppCommand,
+}
+
+impl App {
+    pub fn execute(self) -> Result<()> {
+        self.command.execute(self.output)
+    }
+}
+
+#[allow(clippy::large_enum_variant)] // Parsed once per invocation
+#[derive(StructOpt, Debug)]
 pub enum AppCommand {
     /// Daemon process management
     #[structopt(name = "daemon")]
@@ -43,20 +74,75 @@ pub enum AppCommand {
     /// Queue scheduling management
     #[structopt(name = "queue")]
     Queue(QueueCommand),
+
+    /// Follow task, GPU and queue events as they happen
+    #[structopt(name = "events")]
+    Events(EventsCommand),
+
+    /// Full-screen dashboard of GPUs, queues and tasks
+    #[structopt(name = "top")]
+    Top(TopCommand),
 }
 
 impl AppCommand {
-    pub fn execute(self) -> Result<()> {
+    pub fn execute(self, output: OutputFormat) -> Result<()> {
         match self {
-            AppCommand::Daemon(cmd) => cmd.execute(),
+            AppCommand::Daemon(cmd) => cmd.execute(output),
             AppCommand::Submit(cmd) => cmd.execute(),
-            AppCommand::Task(cmd) => cmd.execute(),
-            AppCommand::Gpu(cmd) => cmd.execute(),
-            AppCommand::Queue(cmd) => cmd.execute(),
+            AppCommand::Task(cmd) => cmd.execute(output),
+            AppCommand::Gpu(cmd) => cmd.execute(output),
+            AppCommand::Queue(cmd) => cmd.execute(output),
+            AppCommand::Events(cmd) => cmd.execute(),
+            AppCommand::Top(cmd) => cmd.execute(),
         }
     }
 }
 
+// Process exit codes, so that scripts can tell failures apart
+pub const EXIT_FAILURE: i32 = 1; // Any other error, e.g. invalid command line or config
+pub const EXIT_DAEMON_UNREACHABLE: i32 = 2; // No daemon on the socket, or a broken reply
+pub const EXIT_NOT_FOUND: i32 = 3;
+pub const EXIT_INVALID_ARGUMENT: i32 = 4;
+pub const EXIT_CONFLICT: i32 = 5;
+pub const EXIT_PERMISSION_DENIED: i32 = 6;
+pub const EXIT_INTERNAL: i32 = 7; // The daemon failed while handling the request
+pub const EXIT_INCOMPATIBLE: i32 = 8; // The daemon speaks another protocol version
+
+/// Error for an Error reply of the daemon. The `RpcError` stays in the chain for `exit_code`.
+fn daemon_error(err: RpcError) -> anyhow::Error {
+    anyhow::Error::new(err).context(format!("{} Daemon returned error", "[ERROR]".red()))
+}
+
+/// Exit code for a failed command
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    if let Some(rpc_error) = err.downcast_ref::<RpcError>() {
+        return rpc_exit_code(rpc_error);
+    }
+    match err.downcast_ref::<GavelError>() {
+        Some(GavelError::Daemon(rpc_error)) => return rpc_exit_code(rpc_error),
+        Some(GavelError::Incompatible { .. } | GavelError::Handshake(_)) => {
+            return EXIT_INCOMPATIBLE
+        }
+        Some(_) => return EXIT_DAEMON_UNREACHABLE,
+        None => {}
+    }
+    // Some commands attach the RPC failure as the context of their own error
+    match err.downcast_ref::<anyhow::Error>() {
+        Some(inner) => exit_code(inner),
+        None => EXIT_FAILURE,
+    }
+}
+
+fn rpc_exit_code(err: &RpcError) -> i32 {
+    match err {
+        RpcError::NotFound { .. } => EXIT_NOT_FOUND,
+        RpcError::InvalidArgument(_) => EXIT_INVALID_ARGUMENT,
+        RpcError::Conflict(_) => EXIT_CONFLICT,
+        RpcError::PermissionDenied(_) => EXIT_PERMISSION_DENIED,
+        RpcError::Internal(_) => EXIT_INTERNAL,
+    }
+}
+
 // Minimal config structure to read sock-path
 #[derive(Debug, Deserialize)]
 struct CliConfig {
@@ -104,3 +190,79 @@ fn get_lock_file_path() -> Result<PathBuf> {
         .map(|p| p.join(LOCK_FILE_NAME))
         .context("Failed to get current directory for lock file")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gavel_core::rpc::message::ResourceKind;
+
+    fn rpc_errors() -> Vec<(RpcError, i32)> {
+        vec![
+            (RpcError::NotFound { kind: ResourceKind::Task, id: "7".to_string() }, EXIT_NOT_FOUND),
+            (RpcError::InvalidArgument("bad".to_string()), EXIT_INVALID_ARGUMENT),
+            (RpcError::Conflict("running".to_string()), EXIT_CONFLICT),
+            (RpcError::PermissionDenied("root".to_string()), EXIT_PERMISSION_DENIED),
+            (RpcError::Internal("oops".to_string()), EXIT_INTERNAL),
+        ]
+    }
+
+    // A failed request as returned by `request_reply`, bare and wrapped the way the submit
+    // and task commands wrap it
+    fn request_failures(err: impl Fn() -> GavelError) -> Vec<anyhow::Error> {
+        vec![
+            anyhow::Error::new(err()),
+            anyhow!("[ERROR] Failed to send command task to daemon")
+                .context(anyhow::Error::new(err())),
+            anyhow::Error::new(err()).context("Failed to list tasks"),
+        ]
+    }
+
+    #[test]
+    fn daemon_errors_map_to_their_exit_code() {
+        for (rpc_error, code) in rpc_errors() {
+            // Error replies, see `daemon_error` and `task info`
+            assert_eq!(exit_code(&daemon_error(rpc_error.clone())), code);
+            assert_eq!(exit_code(&anyhow!(rpc_error.clone())), code);
+            for err in request_failures(|| GavelError::Daemon(rpc_error.clone())) {
+                assert_eq!(exit_code(&err), code, "{:#}", err);
+            }
+        }
+    }
+
+    #[test]
+    fn incompatible_daemons_exit_with_8() {
+        let incompatible = || GavelError::Incompatible {
+            client: 2,
+            client_version: "0.2.0".to_string(),
+            daemon: 1,
+            daemon_version: "0.1.0".to_string(),
+        };
+        for err in request_failures(incompatible) {
+            assert_eq!(exit_code(&err), EXIT_INCOMPATIBLE, "{:#}", err);
+        }
+        for err in request_failures(|| GavelError::Handshake("no reply".to_string())) {
+            assert_eq!(exit_code(&err), EXIT_INCOMPATIBLE, "{:#}", err);
+        }
+    }
+
+    #[test]
+    fn unreachable_daemons_exit_with_2() {
+        let refused = || GavelError::Connect {
+            socket: "/tmp/gavel.sock".to_string(),
+            source: std::io::Error::from(std::io::ErrorKind::ConnectionRefused),
+        };
+        for err in request_failures(refused) {
+            assert_eq!(exit_code(&err), EXIT_DAEMON_UNREACHABLE, "{:#}", err);
+        }
+        for err in request_failures(|| GavelError::UnexpectedReply("Ack")) {
+            assert_eq!(exit_code(&err), EXIT_DAEMON_UNREACHABLE, "{:#}", err);
+        }
+    }
+
+    #[test]
+    fn other_errors_exit_with_1() {
+        assert_eq!(exit_code(&anyhow!("Config file not found")), EXIT_FAILURE);
+        let err = anyhow!("[ERROR] --gpu-util requires --gpu-mem").context("Invalid options");
+        assert_eq!(exit_code(&err), EXIT_FAILURE);
+    }
+}
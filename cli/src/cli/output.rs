@@ -0,0 +1,88 @@
+// Machine-readable output of the listing commands, selected with the global --output option
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::io;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Table, // Colored tables for humans, printed by each command
+    Json,
+    Yaml,
+    Csv,
+}
+
+impl OutputFormat {
+    pub const NAMES: &'static [&'static str] = &["table", "json", "yaml", "csv"];
+
+    pub fn is_table(self) -> bool {
+        self == OutputFormat::Table
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "yaml" => Ok(OutputFormat::Yaml),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(anyhow!("Unknown output format '{}', expected table, json, yaml or csv", s)),
+        }
+    }
+}
+
+/// Prints a list of records, e.g. the tasks of `task list`
+pub fn print_list<T: Serialize>(format: OutputFormat, records: &[T]) -> Result<()> {
+    print_value(format, serde_json::to_value(records)?)
+}
+
+/// Prints a single record, e.g. the task of `task info`. CSV gets a header and one row.
+pub fn print_item<T: Serialize>(format: OutputFormat, record: &T) -> Result<()> {
+    print_value(format, serde_json::to_value(record)?)
+}
+
+fn print_value(format: OutputFormat, value: Value) -> Result<()> {
+    match format {
+        OutputFormat::Table => unreachable!("tables are printed by the commands themselves"),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&value)?),
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(&value)?),
+        OutputFormat::Csv => {
+            let rows = match value {
+                Value::Array(rows) => rows,
+                row => vec![row],
+            };
+            print_csv(&rows).context("Failed to write CSV")?;
+        }
+    }
+    Ok(())
+}
+
+// One column per top-level field, in field order. Nested values (lists, structs) are written
+// as JSON, and missing optional values as empty cells.
+fn print_csv(rows: &[Value]) -> Result<()> {
+    let mut columns: Vec<&str> = Vec::new();
+    for column in rows.iter().filter_map(Value::as_object).flat_map(|row| row.keys()) {
+        if !columns.contains(&column.as_str()) {
+            columns.push(column);
+        }
+    }
+    let mut writer = csv::Writer::from_writer(io::stdout());
+    if !columns.is_empty() {
+        writer.write_record(&columns)?;
+    }
+    for row in rows {
+        let cells = columns.iter().map(|column| match row.get(column) {
+            None | Some(Value::Null) => String::new(),
+            Some(Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+        });
+        writer.write_record(cells)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
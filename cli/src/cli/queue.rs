@@ -1,9 +1,18 @@
+use crate::cli::daemon_error;
 use crate::cli::get_socket_path; // Import socket path helper
+use crate::cli::output::{self, OutputFormat};
+use crate::cli::submit::{describe_retry_policy, RetryArgs};
+use crate::cli::task::TaskTarget;
 use anyhow::{anyhow, Context, Result}; // Added anyhow imports
 use colored::*; // Import colored
 use gavel_core::rpc::message::{Message, QueueAction}; // Import RPC messages
 use gavel_core::rpc::request_reply; // Import RPC function
-use gavel_core::utils::models::{MemoryRequirementType, ResourceLimit};
+use gavel_core::utils::fair_share::{self, ShareStatus};
+use gavel_core::utils::models::{
+    GpuMode, MemoryRequirementType, PlacementStrategy, QueueMeta, ResourceLimit,
+};
+use gavel_core::utils::time::{format_duration_secs, parse_duration_secs};
+use std::collections::HashMap;
 use structopt::StructOpt; // Import ResourceLimit and MemoryRequirementType
 
 #[derive(StructOpt, Debug)]
@@ -67,7 +76,7 @@ pub enum QueueCommand {
     /// Set task priority
     #[structopt(name = "priority")]
     Priority {
-        /// Task ID
+        /// Task ID, or ARRAY_ID: / ARRAY_ID:SLICE for tasks of an array
         task_id: String,
         /// Priority level (0-9)
         level: u8, // Use u8 directly, structopt can parse it
@@ -99,10 +108,99 @@ pub enum QueueCommand {
         #[structopt(long)]
         config: Option<String>,
     },
+
+    /// Set the default max runtime of tasks in a queue
+    #[structopt(name = "set-max-runtime")]
+    SetMaxRuntime {
+        /// Name of the queue to modify
+        queue_name: String,
+
+        /// Max runtime, e.g. "90s", "30m", "2h30m", or "none" to remove the limit
+        max_runtime: String,
+
+        /// Optional path to config file
+        #[structopt(long)]
+        config: Option<String>,
+    },
+
+    /// Set the default retry policy of tasks in a queue (no --max-attempts removes it)
+    #[structopt(name = "set-retry")]
+    SetRetry {
+        /// Name of the queue to modify
+        queue_name: String,
+
+        #[structopt(flatten)]
+        retry: RetryArgs,
+
+        /// Optional path to config file
+        #[structopt(long)]
+        config: Option<String>,
+    },
+
+    /// Set whether tasks of a queue get whole GPUs or share them by declared memory
+    #[structopt(name = "set-gpu-mode")]
+    SetGpuMode {
+        /// Name of the queue to modify
+        queue_name: String,
+
+        /// "exclusive": one task per GPU. "shared": tasks submitted with --gpu-mem are packed
+        /// onto GPUs while their declared memory fits
+        #[structopt(possible_values = GpuMode::NAMES)]
+        mode: GpuMode,
+
+        /// Optional path to config file
+        #[structopt(long)]
+        config: Option<String>,
+    },
+
+    /// Set which of the suitable GPUs tasks of a queue are placed on
+    #[structopt(name = "set-placement")]
+    SetPlacement {
+        /// Name of the queue to modify
+        queue_name: String,
+
+        /// "best-fit": the GPUs with the least free memory that is enough, keeping large free
+        /// GPUs for large tasks. "most-free": the GPUs with the most free memory
+        #[structopt(possible_values = PlacementStrategy::NAMES)]
+        placement: PlacementStrategy,
+
+        /// Optional path to config file
+        #[structopt(long)]
+        config: Option<String>,
+    },
+
+    /// Set the fair-share weight of a queue. With fair share enabled in the daemon, queues get
+    /// GPU time in proportion to their weights
+    #[structopt(name = "set-share")]
+    SetShare {
+        /// Name of the queue to modify
+        queue_name: String,
+
+        /// Weight, at least 1 (default 1)
+        weight: u32,
+
+        /// Optional path to config file
+        #[structopt(long)]
+        config: Option<String>,
+    },
+
+    /// Set how many tasks of a queue may run at the same time
+    #[structopt(name = "set-max-concurrent")]
+    SetMaxConcurrent {
+        /// Name of the queue to modify
+        queue_name: String,
+
+        /// Number of tasks, at least 1
+        max_concurrent: u8,
+
+        /// Optional path to config file
+        #[structopt(long)]
+        config: Option<String>,
+    },
 }
 
 impl QueueCommand {
-    pub fn execute(self) -> Result<()> {
+    pub fn execute(self, output: OutputFormat) -> Result<()> {
         // Extract config path first
         let config_path: Option<String> = match &self {
             Self::List { config } => config.clone(),
@@ -112,12 +210,20 @@ impl QueueCommand {
             Self::Move { config, .. } => config.clone(),
             Self::Priority { config, .. } => config.clone(),
             Self::SetLimit { config, .. } => config.clone(),
+            Self::SetMaxRuntime { config, .. } => config.clone(),
+            Self::SetRetry { config, .. } => config.clone(),
+            Self::SetGpuMode { config, .. } => config.clone(),
+            Self::SetPlacement { config, .. } => config.clone(),
+            Self::SetShare { config, .. } => config.clone(),
+            Self::SetMaxConcurrent { config, .. } => config.clone(),
         };
         let socket_path = get_socket_path(config_path.as_deref())?;
 
         match self {
-            Self::List { .. } => Self::handle_list(&socket_path),
-            Self::Status { queue_name, .. } => Self::handle_status(&socket_path, queue_name),
+            Self::List { .. } => Self::handle_list(&socket_path, output),
+            Self::Status { queue_name, .. } => {
+                Self::handle_status(&socket_path, queue_name, output)
+            }
             Self::Merge { source, dest, .. } => Self::handle_merge(&socket_path, source, dest),
             Self::Create { queue_name, priority, .. } => {
                 Self::handle_create(&socket_path, queue_name, priority)
@@ -131,36 +237,68 @@ impl QueueCommand {
             Self::SetLimit { queue_name, mem_type, mem_value, max_util, .. } => {
                 Self::handle_set_limit(&socket_path, queue_name, mem_type, mem_value, max_util)
             }
+            Self::SetMaxRuntime { queue_name, max_runtime, .. } => {
+                Self::handle_set_max_runtime(&socket_path, queue_name, max_runtime)
+            }
+            Self::SetRetry { queue_name, retry, .. } => {
+                Self::handle_set_retry(&socket_path, queue_name, retry)
+            }
+            Self::SetGpuMode { queue_name, mode, .. } => {
+                Self::handle_set_gpu_mode(&socket_path, queue_name, mode)
+            }
+            Self::SetPlacement { queue_name, placement, .. } => {
+                Self::handle_set_placement(&socket_path, queue_name, placement)
+            }
+            Self::SetShare { queue_name, weight, .. } => {
+                Self::handle_set_share(&socket_path, queue_name, weight)
+            }
+            Self::SetMaxConcurrent { queue_name, max_concurrent, .. } => {
+                Self::handle_set_max_concurrent(&socket_path, queue_name, max_concurrent)
+            }
         }
     }
 
-    fn handle_list(socket_path: &str) -> Result<()> {
-        println!("{} Listing all queues via RPC...", "[INFO]".blue());
+    fn handle_list(socket_path: &str, output: OutputFormat) -> Result<()> {
+        if output.is_table() {
+            println!("{} Listing all queues via RPC...", "[INFO]".blue());
+        }
         let request = Message::QueueCommand(QueueAction::List);
 
         match request_reply(socket_path, &request) {
+            Ok(Message::QueueStatus(queues)) if !output.is_table() => {
+                output::print_list(output, &queues)
+            }
+            // No queues
+            Ok(Message::Ack(_)) if !output.is_table() => {
+                output::print_list::<QueueMeta>(output, &[])
+            }
             Ok(Message::QueueStatus(queues)) => {
                 if queues.is_empty() {
                     println!("{} No queues found.", "[INFO]".blue());
                 } else {
                     // Pretty print queue list with colors
+                    let shares = fair_share::share_status(&queues);
                     println!(
-                        "{}",
-                        format!(
-                            "{:<15} {:<10} {:<10} {:<10} {:<15}",
-                            "Name".bold().underline(),
-                            "Priority".bold().underline(),
-                            "Waiting".bold().underline(),
-                            "Running".bold().underline(),
-                            "Allocated GPUs".bold().underline()
-                        )
+                        "{:<15} {:<10} {:<10} {:<13} {:<10} {:<10} {:<15}",
+                        "Name".bold().underline(),
+                        "Priority".bold().underline(),
+                        "GPU Mode".bold().underline(),
+                        "Usage/Share".bold().underline(),
+                        "Waiting".bold().underline(),
+                        "Running".bold().underline(),
+                        "Allocated GPUs".bold().underline()
                     );
-                    println!("{}", "-".repeat(65)); // Separator line
+                    println!("{}", "-".repeat(90)); // Separator line
                     for queue in queues {
+                        let usage_share = shares.get(&queue.name).map_or("-".to_string(), |s| {
+                            format!("{:.0}%/{:.0}%", s.usage * 100.0, s.share * 100.0)
+                        });
                         println!(
-                            "{:<15} {:<10} {:<10} {:<10} {:<15}",
+                            "{:<15} {:<10} {:<10} {:<13} {:<10} {:<10} {:<15}",
                             queue.name.cyan(),                   // Color queue name
                             queue.priority.to_string().yellow(), // Color priority
+                            queue.gpu_mode.as_str(),
+                            usage_share,
                             queue.waiting_task_ids.len(),
                             queue.running_task_ids.len(),
                             format!("{:?}", queue.allocated_gpus).magenta() // Color GPU list
@@ -174,9 +312,7 @@ impl QueueCommand {
                 println!("{} Daemon reply: {}", "[INFO]".blue(), msg.italic()); // Format Ack
                 Ok(())
             }
-            Ok(Message::Error(err_msg)) => {
-                Err(anyhow!("{} Daemon returned error: {}", "[ERROR]".red(), err_msg))
-            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
             Ok(other) => Err(anyhow!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other)),
             Err(e) => {
                 Err(anyhow!("{} Failed to send list command to daemon", "[ERROR]".red()).context(e))
@@ -184,11 +320,20 @@ impl QueueCommand {
         }
     }
 
-    fn handle_status(socket_path: &str, queue_name: String) -> Result<()> {
-        println!("{} Getting status for queue '{}' via RPC...", "[INFO]".blue(), queue_name.cyan()); // Color queue name
+    fn handle_status(socket_path: &str, queue_name: String, output: OutputFormat) -> Result<()> {
+        if output.is_table() {
+            println!(
+                "{} Getting status for queue '{}' via RPC...",
+                "[INFO]".blue(),
+                queue_name.cyan()
+            ); // Color queue name
+        }
         let request = Message::QueueCommand(QueueAction::Status { queue_name: queue_name.clone() });
 
         match request_reply(socket_path, &request) {
+            Ok(Message::QueueStatus(queues)) if !output.is_table() && !queues.is_empty() => {
+                output::print_item(output, &queues[0])
+            }
             Ok(Message::QueueStatus(queues)) => {
                 if let Some(queue) = queues.first() {
                     // Pretty print queue details with colors
@@ -199,6 +344,27 @@ impl QueueCommand {
                         queue.priority.to_string().yellow()
                     );
                     println!("  {:<20} {}", "Max Concurrent:".green(), queue.max_concurrent); // Assuming QueueMeta has this
+                    println!("  {:<20} {}", "GPU Mode:".green(), queue.gpu_mode.as_str());
+                    println!("  {:<20} {}", "Placement:".green(), queue.placement.as_str());
+                    let share = fetch_share_status(socket_path).remove(&queue.name);
+                    println!(
+                        "  {:<20} weight {}{}",
+                        "Fair Share:".green(),
+                        queue.fair_share,
+                        share.map_or(String::new(), |s| format!(
+                            " ({:.1}% of all weights)",
+                            s.share * 100.0
+                        ))
+                    );
+                    println!(
+                        "  {:<20} {} of GPU time{}",
+                        "Recent GPU Usage:".green(),
+                        format_duration_secs(queue.usage.gpu_secs.round() as u64),
+                        share.map_or(String::new(), |s| format!(
+                            " ({:.1}% of all usage)",
+                            s.usage * 100.0
+                        ))
+                    );
                     println!(
                         "  {:<20} {} ({:?})", // Adjusted spacing
                         "Waiting Tasks:".green(),
@@ -248,6 +414,17 @@ impl QueueCommand {
                             "Ignored".to_string()
                         };
                     println!("    {:<28} {}", "Max GPU Utilization:".blue(), max_gpu_util_str); // Sub-label and value
+
+                    let max_runtime_str = queue
+                        .default_max_runtime
+                        .map_or("Unlimited".to_string(), format_duration_secs);
+                    println!("    {:<28} {}", "Default Max Runtime:".blue(), max_runtime_str);
+
+                    let retry_str = queue
+                        .default_retry_policy
+                        .as_ref()
+                        .map_or("None".to_string(), describe_retry_policy);
+                    println!("    {:<28} {}", "Default Retry Policy:".blue(), retry_str);
                 } else {
                     // Should not happen if daemon returns QueueStatus, but handle defensively
                     println!(
@@ -258,9 +435,7 @@ impl QueueCommand {
                 }
                 Ok(())
             }
-            Ok(Message::Error(err_msg)) => {
-                Err(anyhow!("{} Daemon returned error: {}", "[ERROR]".red(), err_msg))
-            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
             Ok(other) => Err(anyhow!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other)),
             Err(e) => Err(anyhow!(
                 "{} Failed to send status command for queue {} to daemon",
@@ -288,9 +463,7 @@ impl QueueCommand {
                 println!("{} Daemon reply: {}", "[SUCCESS]".green(), msg.italic()); // Format Ack
                 Ok(())
             }
-            Ok(Message::Error(err_msg)) => {
-                Err(anyhow!("{} Daemon returned error: {}", "[ERROR]".red(), err_msg))
-            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
             Ok(other) => Err(anyhow!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other)),
             Err(e) => Err(anyhow!(
                 "{} Failed to send merge command ({} -> {}) to daemon",
@@ -317,9 +490,7 @@ impl QueueCommand {
                 println!("{} Daemon reply: {}", "[SUCCESS]".green(), msg.italic()); // Format Ack
                 Ok(())
             }
-            Ok(Message::Error(err_msg)) => {
-                Err(anyhow!("{} Daemon returned error: {}", "[ERROR]".red(), err_msg))
-            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
             Ok(other) => Err(anyhow!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other)),
             Err(e) => Err(anyhow!(
                 "{} Failed to send create command for queue {} to daemon",
@@ -347,9 +518,7 @@ impl QueueCommand {
                 println!("{} Daemon reply: {}", "[SUCCESS]".green(), msg.italic()); // Format Ack
                 Ok(())
             }
-            Ok(Message::Error(err_msg)) => {
-                Err(anyhow!("{} Daemon returned error: {}", "[ERROR]".red(), err_msg))
-            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
             Ok(other) => Err(anyhow!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other)),
             Err(e) => Err(anyhow!(
                 "{} Failed to send move command (task {} -> queue {}) to daemon",
@@ -362,8 +531,7 @@ impl QueueCommand {
     }
 
     fn handle_priority(socket_path: &str, task_id_str: String, level: u8) -> Result<()> {
-        let task_id =
-            task_id_str.parse::<u64>().context("Invalid Task ID format, must be a number")?;
+        let target = task_id_str.parse::<TaskTarget>()?;
         if level > 9 {
             // Add validation consistent with handler
             return Err(anyhow!(
@@ -373,26 +541,27 @@ impl QueueCommand {
             )); // Color error
         }
         println!(
-            "{} Requesting to set priority of task {} to {} via RPC...",
+            "{} Requesting to set priority of {} to {} via RPC...",
             "[INFO]".blue(),
-            task_id.to_string().yellow(),
+            target.to_string().yellow(),
             level.to_string().yellow()
         ); // Color task ID and level
-        let request = Message::QueueCommand(QueueAction::SetPriority { task_id, level });
+        let request = Message::QueueCommand(match target.clone() {
+            TaskTarget::Task(task_id) => QueueAction::SetPriority { task_id, level },
+            TaskTarget::Array(array) => QueueAction::SetArrayPriority { array, level },
+        });
 
         match request_reply(socket_path, &request) {
             Ok(Message::Ack(msg)) => {
                 println!("{} Daemon reply: {}", "[SUCCESS]".green(), msg.italic()); // Format Ack
                 Ok(())
             }
-            Ok(Message::Error(err_msg)) => {
-                Err(anyhow!("{} Daemon returned error: {}", "[ERROR]".red(), err_msg))
-            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
             Ok(other) => Err(anyhow!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other)),
             Err(e) => Err(anyhow!(
-                "{} Failed to send priority command (task {} -> level {}) to daemon",
+                "{} Failed to send priority command ({} -> level {}) to daemon",
                 "[ERROR]".red(),
-                task_id,
+                target,
                 level
             )
             .context(e)),
@@ -451,9 +620,7 @@ impl QueueCommand {
                 println!("{} Daemon reply: {}", "[SUCCESS]".green(), msg.italic());
                 Ok(())
             }
-            Ok(Message::Error(err_msg)) => {
-                Err(anyhow!(format!("{} Daemon returned error: {}", "[ERROR]".red(), err_msg)))
-            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
             Ok(other) => {
                 Err(anyhow!(format!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other)))
             }
@@ -465,4 +632,204 @@ impl QueueCommand {
             .context(e)),
         }
     }
+
+    fn handle_set_max_runtime(
+        socket_path: &str,
+        queue_name: String,
+        max_runtime_str: String,
+    ) -> Result<()> {
+        let max_runtime = if max_runtime_str.eq_ignore_ascii_case("none") {
+            None
+        } else {
+            Some(parse_duration_secs(&max_runtime_str).map_err(|e| {
+                anyhow!("{} Invalid max runtime '{}': {}", "[ERROR]".red(), max_runtime_str, e)
+            })?)
+        };
+        println!(
+            "{} Setting default max runtime of queue '{}' to {} via RPC...",
+            "[INFO]".blue(),
+            queue_name.cyan(),
+            max_runtime.map_or("none".to_string(), format_duration_secs).yellow()
+        );
+
+        let request = Message::QueueCommand(QueueAction::SetMaxRuntime {
+            queue_name: queue_name.clone(),
+            max_runtime,
+        });
+
+        match request_reply(socket_path, &request) {
+            Ok(Message::Ack(msg)) => {
+                println!("{} Daemon reply: {}", "[SUCCESS]".green(), msg.italic());
+                Ok(())
+            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
+            Ok(other) => Err(anyhow!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other)),
+            Err(e) => Err(anyhow!(
+                "{} Failed to send set-max-runtime command for queue {} to daemon",
+                "[ERROR]".red(),
+                queue_name
+            )
+            .context(e)),
+        }
+    }
+
+    fn handle_set_retry(socket_path: &str, queue_name: String, retry: RetryArgs) -> Result<()> {
+        let policy = retry.into_policy()?;
+        println!(
+            "{} Setting default retry policy of queue '{}' to {} via RPC...",
+            "[INFO]".blue(),
+            queue_name.cyan(),
+            policy.as_ref().map_or("none".to_string(), describe_retry_policy).yellow()
+        );
+
+        let request = Message::QueueCommand(QueueAction::SetRetryPolicy {
+            queue_name: queue_name.clone(),
+            policy,
+        });
+
+        match request_reply(socket_path, &request) {
+            Ok(Message::Ack(msg)) => {
+                println!("{} Daemon reply: {}", "[SUCCESS]".green(), msg.italic());
+                Ok(())
+            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
+            Ok(other) => Err(anyhow!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other)),
+            Err(e) => Err(anyhow!(
+                "{} Failed to send set-retry command for queue {} to daemon",
+                "[ERROR]".red(),
+                queue_name
+            )
+            .context(e)),
+        }
+    }
+
+    fn handle_set_gpu_mode(socket_path: &str, queue_name: String, mode: GpuMode) -> Result<()> {
+        println!(
+            "{} Setting GPU mode of queue '{}' to {} via RPC...",
+            "[INFO]".blue(),
+            queue_name.cyan(),
+            mode.as_str().yellow()
+        );
+
+        let request =
+            Message::QueueCommand(QueueAction::SetGpuMode { queue_name: queue_name.clone(), mode });
+
+        match request_reply(socket_path, &request) {
+            Ok(Message::Ack(msg)) => {
+                println!("{} Daemon reply: {}", "[SUCCESS]".green(), msg.italic());
+                Ok(())
+            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
+            Ok(other) => Err(anyhow!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other)),
+            Err(e) => Err(anyhow!(
+                "{} Failed to send set-gpu-mode command for queue {} to daemon",
+                "[ERROR]".red(),
+                queue_name
+            )
+            .context(e)),
+        }
+    }
+
+    fn handle_set_placement(
+        socket_path: &str,
+        queue_name: String,
+        placement: PlacementStrategy,
+    ) -> Result<()> {
+        println!(
+            "{} Setting placement of queue '{}' to {} via RPC...",
+            "[INFO]".blue(),
+            queue_name.cyan(),
+            placement.as_str().yellow()
+        );
+
+        let request = Message::QueueCommand(QueueAction::SetPlacement {
+            queue_name: queue_name.clone(),
+            placement,
+        });
+
+        match request_reply(socket_path, &request) {
+            Ok(Message::Ack(msg)) => {
+                println!("{} Daemon reply: {}", "[SUCCESS]".green(), msg.italic());
+                Ok(())
+            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
+            Ok(other) => Err(anyhow!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other)),
+            Err(e) => Err(anyhow!(
+                "{} Failed to send set-placement command for queue {} to daemon",
+                "[ERROR]".red(),
+                queue_name
+            )
+            .context(e)),
+        }
+    }
+
+    fn handle_set_share(socket_path: &str, queue_name: String, weight: u32) -> Result<()> {
+        println!(
+            "{} Setting fair-share weight of queue '{}' to {} via RPC...",
+            "[INFO]".blue(),
+            queue_name.cyan(),
+            weight.to_string().yellow()
+        );
+
+        let request = Message::QueueCommand(QueueAction::SetFairShare {
+            queue_name: queue_name.clone(),
+            weight,
+        });
+
+        match request_reply(socket_path, &request) {
+            Ok(Message::Ack(msg)) => {
+                println!("{} Daemon reply: {}", "[SUCCESS]".green(), msg.italic());
+                Ok(())
+            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
+            Ok(other) => Err(anyhow!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other)),
+            Err(e) => Err(anyhow!(
+                "{} Failed to send set-share command for queue {} to daemon",
+                "[ERROR]".red(),
+                queue_name
+            )
+            .context(e)),
+        }
+    }
+
+    fn handle_set_max_concurrent(
+        socket_path: &str,
+        queue_name: String,
+        max_concurrent: u8,
+    ) -> Result<()> {
+        println!(
+            "{} Setting max concurrent tasks of queue '{}' to {} via RPC...",
+            "[INFO]".blue(),
+            queue_name.cyan(),
+            max_concurrent.to_string().yellow()
+        );
+
+        let request = Message::QueueCommand(QueueAction::SetMaxConcurrent {
+            queue_name: queue_name.clone(),
+            max_concurrent,
+        });
+
+        match request_reply(socket_path, &request) {
+            Ok(Message::Ack(msg)) => {
+                println!("{} Daemon reply: {}", "[SUCCESS]".green(), msg.italic());
+                Ok(())
+            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
+            Ok(other) => Err(anyhow!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other)),
+            Err(e) => Err(anyhow!(
+                "{} Failed to send set-max-concurrent command for queue {} to daemon",
+                "[ERROR]".red(),
+                queue_name
+            )
+            .context(e)),
+        }
+    }
+}
+
+// Usage against share of every queue, empty if the queues cannot be listed
+fn fetch_share_status(socket_path: &str) -> HashMap<String, ShareStatus> {
+    match request_reply(socket_path, &Message::QueueCommand(QueueAction::List)) {
+        Ok(Message::QueueStatus(queues)) => fair_share::share_status(&queues),
+        _ => HashMap::new(),
+    }
 }
@@ -1,12 +1,19 @@
+use crate::cli::daemon_error;
 use crate::cli::get_socket_path;
 use anyhow::{anyhow, Context, Result};
 use colored::*;
 use gavel_core::rpc::message::{Message, SubmitAction}; // Import RPC messages
 use gavel_core::rpc::request_reply; // Import RPC function
-use gavel_core::utils::models::TaskMeta; // Import TaskMeta for BatchJson
+use gavel_core::utils::array::{self, parse_param};
+use gavel_core::utils::models::{
+    ArrayParam, DependencyKind, ExecContext, GpuRequirement, GpuShare, MemoryRequirementType,
+    RetryPolicy, TaskDependency, TaskMeta,
+}; // Import TaskMeta for BatchJson
+use gavel_core::utils::time::{format_duration_secs, parse_duration_secs};
 use gavel_core::utils::DEFAULT_WAITING_QUEUE_NAME;
 use serde::Deserialize; // Added for JSON parsing and config reading
-use std::fs; // Added fs, env, Path
+use std::collections::HashMap;
+use std::{env, fs}; // Added fs, env, Path
 use structopt::StructOpt; // Import colored
 
 // Define the structure expected in the JSON file for batch submission
@@ -18,6 +25,443 @@ struct JsonTaskInput {
     queue: Option<String>, // Allow specifying queue per task
     priority: Option<u8>,  // Allow specifying priority per task
     name: Option<String>,  // Allow specifying name per task
+    #[serde(default)] // Tasks (IDs, or names in this file or of existing tasks) to wait for
+    after_ok: Vec<TaskRef>,
+    #[serde(default)]
+    after_any: Vec<TaskRef>,
+    #[serde(default)]
+    after_failed: Vec<TaskRef>,
+    max_runtime: Option<JsonDuration>, // Seconds, or a duration string such as "2h30m"
+    retry: Option<JsonRetryPolicy>,
+    cwd: Option<String>, // Relative to the directory gavelrs is run from
+    env: Option<HashMap<String, String>>, // Only these variables, instead of the whole environment
+    shell: Option<JsonShell>,
+    gpu_memory: Option<JsonMemory>, // Memory used on each GPU, to share GPUs in shared queues
+    gpu_util: Option<u8>,           // Utilization share (%) used on each GPU
+    gpu_min_free: Option<JsonMemory>, // Free memory each GPU must have, e.g. "70G" or "90%"
+    gpu_min_total: Option<JsonMemory>, // Total memory each GPU must have
+    gpu_model: Option<String>,      // Part of the GPU model name, e.g. "A100"
+}
+
+// Megabytes, or a size string such as "10G"
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum JsonMemory {
+    Mb(u64),
+    Text(String),
+}
+
+impl JsonMemory {
+    fn mb(&self) -> Result<u64> {
+        match self {
+            JsonMemory::Mb(mb) => Ok(*mb),
+            JsonMemory::Text(text) => parse_memory_mb(text),
+        }
+    }
+
+    fn free_memory(&self) -> Result<FreeMemory> {
+        match self {
+            JsonMemory::Mb(mb) => Ok((MemoryRequirementType::AbsoluteMb, *mb)),
+            JsonMemory::Text(text) => parse_free_memory(text),
+        }
+    }
+}
+
+// `true` for /bin/sh, or the path of a shell
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum JsonShell {
+    Enabled(bool),
+    Path(String),
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct JsonRetryPolicy {
+    max_attempts: u32,
+    backoff: Option<JsonDuration>,
+    #[serde(default)]
+    exit_codes: Vec<i32>,
+    #[serde(default)]
+    signals: Vec<JsonSignal>,
+}
+
+// A signal given by number or by name, e.g. 9 or "SIGKILL"
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum JsonSignal {
+    Number(i32),
+    Name(String),
+}
+
+impl JsonRetryPolicy {
+    fn into_policy(self) -> Result<RetryPolicy> {
+        Ok(RetryPolicy {
+            max_attempts: self.max_attempts,
+            backoff_secs: self.backoff.as_ref().map(JsonDuration::secs).transpose()?.unwrap_or(0),
+            exit_codes: self.exit_codes,
+            signals: self
+                .signals
+                .into_iter()
+                .map(|sig| match sig {
+                    JsonSignal::Number(n) => Ok(n),
+                    JsonSignal::Name(name) => parse_signal(&name),
+                })
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum JsonDuration {
+    Secs(u64),
+    Text(String),
+}
+
+impl JsonDuration {
+    fn secs(&self) -> Result<u64> {
+        match self {
+            JsonDuration::Secs(secs) => Ok(*secs),
+            JsonDuration::Text(text) => parse_duration_secs(text),
+        }
+    }
+}
+
+// A task referred to by ID or by name
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum TaskRef {
+    Id(u64),
+    Name(String),
+}
+
+impl TaskRef {
+    // Command-line values: numbers are task IDs, anything else is a task name
+    fn parse(value: &str) -> Self {
+        value.parse().map(TaskRef::Id).unwrap_or_else(|_| TaskRef::Name(value.to_string()))
+    }
+}
+
+// Builds the dependency list sent to the daemon, which resolves names into IDs
+fn build_dependencies(
+    after_ok: Vec<TaskRef>,
+    after_any: Vec<TaskRef>,
+    after_failed: Vec<TaskRef>,
+) -> Vec<TaskDependency> {
+    let groups = [
+        (DependencyKind::AfterOk, after_ok),
+        (DependencyKind::AfterAny, after_any),
+        (DependencyKind::AfterFailed, after_failed),
+    ];
+    groups
+        .into_iter()
+        .flat_map(|(kind, refs)| {
+            refs.into_iter().map(move |task| match task {
+                TaskRef::Id(task_id) => TaskDependency { kind, task_id, task_name: None },
+                TaskRef::Name(name) => TaskDependency { kind, task_id: 0, task_name: Some(name) },
+            })
+        })
+        .collect()
+}
+
+fn parse_refs(values: Vec<String>) -> Vec<TaskRef> {
+    values.iter().map(|v| TaskRef::parse(v)).collect()
+}
+
+// Signal numbers are the Linux ones
+const SIGNAL_NAMES: &[(&str, i32)] = &[
+    ("HUP", 1),
+    ("INT", 2),
+    ("QUIT", 3),
+    ("ILL", 4),
+    ("ABRT", 6),
+    ("BUS", 7),
+    ("FPE", 8),
+    ("KILL", 9),
+    ("USR1", 10),
+    ("SEGV", 11),
+    ("USR2", 12),
+    ("PIPE", 13),
+    ("ALRM", 14),
+    ("TERM", 15),
+];
+
+/// Parses a signal number or name, with or without the "SIG" prefix
+pub fn parse_signal(value: &str) -> Result<i32> {
+    if let Ok(number) = value.parse::<i32>() {
+        return Ok(number);
+    }
+    let upper = value.to_uppercase();
+    let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+    SIGNAL_NAMES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, number)| *number)
+        .ok_or_else(|| anyhow!("Unknown signal '{}'", value))
+}
+
+// Retry options of `submit command`, `submit script` and `queue set-retry`
+#[derive(StructOpt, Debug)]
+pub struct RetryArgs {
+    /// Run a failed task up to this many times in total (including the first run)
+    #[structopt(long)]
+    max_attempts: Option<u32>,
+
+    /// Delay before a failed task is queued again, e.g. "30s", "5m"
+    #[structopt(long, parse(try_from_str = parse_duration_secs))]
+    retry_backoff: Option<u64>,
+
+    /// Retry only on these exit codes (comma-separated)
+    #[structopt(long, use_delimiter = true)]
+    retry_on_exit: Vec<i32>,
+
+    /// Retry only when the task is killed by these signals, e.g. "KILL" or "9" (comma-separated)
+    #[structopt(long, use_delimiter = true, parse(try_from_str = parse_signal))]
+    retry_on_signal: Vec<i32>,
+}
+
+impl RetryArgs {
+    /// The policy described by the options, None if --max-attempts was not given
+    pub fn into_policy(self) -> Result<Option<RetryPolicy>> {
+        let Some(max_attempts) = self.max_attempts else {
+            if self.retry_backoff.is_some()
+                || !self.retry_on_exit.is_empty()
+                || !self.retry_on_signal.is_empty()
+            {
+                return Err(anyhow!("{} Retry options require --max-attempts", "[ERROR]".red()));
+            }
+            return Ok(None);
+        };
+        Ok(Some(RetryPolicy {
+            max_attempts,
+            backoff_secs: self.retry_backoff.unwrap_or(0),
+            exit_codes: self.retry_on_exit,
+            signals: self.retry_on_signal,
+        }))
+    }
+}
+
+// Parses a memory size in MB: "512", "512M", "10G" or "10GB" (case-insensitive)
+fn parse_memory_mb(value: &str) -> Result<u64> {
+    let upper = value.trim().to_uppercase();
+    let number = upper.strip_suffix('B').unwrap_or(&upper);
+    let (number, factor) = match number.strip_suffix('G') {
+        Some(number) => (number, 1024),
+        None => (number.strip_suffix('M').unwrap_or(number), 1),
+    };
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|mb| mb.checked_mul(factor))
+        .filter(|mb| *mb > 0)
+        .ok_or_else(|| anyhow!("Invalid memory size '{}': expected e.g. 512M or 10G", value))
+}
+
+// Utilization share in percent, 1 to 100, e.g. "50" or "50%"
+fn parse_util_percent(value: &str) -> Result<u8> {
+    value
+        .trim()
+        .trim_end_matches('%')
+        .parse::<u8>()
+        .ok()
+        .filter(|util| (1..=100).contains(util))
+        .ok_or_else(|| anyhow!("Invalid utilization share '{}': expected 1 to 100", value))
+}
+
+// The share of each GPU a task declared, None if it uses whole GPUs
+fn gpu_share(memory_mb: Option<u64>, util_percent: Option<u8>) -> Result<Option<GpuShare>> {
+    if let Some(util) = util_percent.filter(|util| !(1..=100).contains(util)) {
+        return Err(anyhow!("Invalid utilization share {}: expected 1 to 100", util));
+    }
+    match (memory_mb, util_percent) {
+        (Some(memory_mb), util_percent) => Ok(Some(GpuShare { memory_mb, util_percent })),
+        (None, Some(_)) => Err(anyhow!("{} --gpu-util requires --gpu-mem", "[ERROR]".red())),
+        (None, None) => Ok(None),
+    }
+}
+
+// A free memory requirement: its type, and MB or percentage
+type FreeMemory = (MemoryRequirementType, u64);
+
+// Parses a free memory requirement: a size as for --gpu-mem, or a percentage such as "90%"
+fn parse_free_memory(value: &str) -> Result<FreeMemory> {
+    match value.trim().strip_suffix('%') {
+        Some(percent) => percent
+            .trim()
+            .parse::<u64>()
+            .ok()
+            .filter(|percent| (1..=100).contains(percent))
+            .map(|percent| (MemoryRequirementType::Percentage, percent))
+            .ok_or_else(|| anyhow!("Invalid memory percentage '{}': expected 1% to 100%", value)),
+        None => Ok((MemoryRequirementType::AbsoluteMb, parse_memory_mb(value)?)),
+    }
+}
+
+// What each GPU of a task must offer, None if any GPU will do
+fn gpu_requirement(
+    min_free: Option<FreeMemory>,
+    min_total_memory_mb: Option<u64>,
+    model: Option<String>,
+) -> Option<GpuRequirement> {
+    if min_free.is_none() && min_total_memory_mb.is_none() && model.is_none() {
+        return None;
+    }
+    let (memory_requirement_type, memory_requirement_value) =
+        min_free.unwrap_or((MemoryRequirementType::Ignore, 0));
+    Some(GpuRequirement {
+        memory_requirement_type,
+        memory_requirement_value,
+        min_total_memory_mb,
+        model,
+    })
+}
+
+// GPU selection options of `submit command`, `submit script` and `submit array`
+#[derive(StructOpt, Debug)]
+pub struct GpuRequirementArgs {
+    /// Only use GPUs with at least this much free memory, e.g. "70G", "512M" or "90%"
+    #[structopt(long, parse(try_from_str = parse_free_memory))]
+    gpu_min_free: Option<FreeMemory>,
+
+    /// Only use GPUs with at least this much memory in total, e.g. "80G"
+    #[structopt(long, parse(try_from_str = parse_memory_mb))]
+    gpu_min_total: Option<u64>,
+
+    /// Only use GPUs whose model name contains this, e.g. "A100" (case-insensitive)
+    #[structopt(long)]
+    gpu_model: Option<String>,
+}
+
+impl GpuRequirementArgs {
+    pub fn into_requirement(self) -> Option<GpuRequirement> {
+        gpu_requirement(self.gpu_min_free, self.gpu_min_total, self.gpu_model)
+    }
+}
+
+// GPU sharing options of `submit command`, `submit script` and `submit array`
+#[derive(StructOpt, Debug)]
+pub struct GpuShareArgs {
+    /// Memory used on each GPU, e.g. "4G" or "512M" (plain numbers are MB). In queues in
+    /// shared GPU mode, the task may then run next to other tasks on the same GPUs.
+    #[structopt(long, parse(try_from_str = parse_memory_mb))]
+    gpu_mem: Option<u64>,
+
+    /// Largest utilization share (%) used on each GPU; shared GPUs are never given more than
+    /// 100% in total
+    #[structopt(long, parse(try_from_str = parse_util_percent))]
+    gpu_util: Option<u8>,
+}
+
+impl GpuShareArgs {
+    pub fn into_share(self) -> Result<Option<GpuShare>> {
+        gpu_share(self.gpu_mem, self.gpu_util)
+    }
+}
+
+const DEFAULT_SHELL: &str = "/bin/sh";
+
+// Execution options of `submit command` and `submit script`
+#[derive(StructOpt, Debug)]
+pub struct ExecArgs {
+    /// Run the command through a shell (default /bin/sh), so pipes and `&&` work.
+    /// For scripts: the interpreter to run the script with
+    #[structopt(long)]
+    shell: Option<Option<String>>,
+
+    /// Working directory of the task (default: the current directory)
+    #[structopt(long)]
+    cwd: Option<String>,
+
+    /// Pass only these environment variables (NAME or NAME=VALUE) on top of the daemon's
+    /// environment. By default the task gets the whole environment of this shell.
+    #[structopt(long, number_of_values = 1, conflicts_with = "no-env")]
+    env: Vec<String>,
+
+    /// Do not pass this shell's environment; the task inherits the daemon's
+    #[structopt(long)]
+    no_env: bool,
+}
+
+impl ExecArgs {
+    /// Captures the working directory and environment the task is submitted from
+    pub fn capture(self) -> Result<ExecContext> {
+        let (env, full_env) = if self.no_env {
+            (Vec::new(), false)
+        } else if !self.env.is_empty() {
+            (self.env.iter().map(|var| env_var(var)).collect::<Result<_>>()?, false)
+        } else {
+            (env_snapshot(), true)
+        };
+        Ok(ExecContext {
+            cwd: Some(resolve_cwd(self.cwd.as_deref())?),
+            env,
+            full_env,
+            shell: self.shell.map(|shell| shell.unwrap_or_else(|| DEFAULT_SHELL.to_string())),
+        })
+    }
+}
+
+// Absolute path of the task's working directory; relative paths are taken from the current one
+fn resolve_cwd(cwd: Option<&str>) -> Result<String> {
+    let current = env::current_dir().context("Failed to get current directory")?;
+    let dir = match cwd {
+        Some(dir) => current.join(dir),
+        None => current,
+    };
+    let dir = dir
+        .canonicalize()
+        .with_context(|| format!("Invalid working directory: {}", dir.display()))?;
+    Ok(dir.to_string_lossy().to_string())
+}
+
+// The whole environment of this process (variables that are not valid UTF-8 are skipped)
+fn env_snapshot() -> Vec<(String, String)> {
+    env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .collect()
+}
+
+// NAME=VALUE, or NAME to pass the variable's current value
+fn env_var(var: &str) -> Result<(String, String)> {
+    match var.split_once('=') {
+        Some((name, value)) => Ok((name.to_string(), value.to_string())),
+        None => env::var(var)
+            .map(|value| (var.to_string(), value))
+            .map_err(|_| anyhow!("Environment variable {} is not set", var)),
+    }
+}
+
+impl JsonTaskInput {
+    // Execution context of a JSON task; tasks without `env` get the whole environment
+    fn exec_context(&self) -> Result<ExecContext> {
+        let (env, full_env) = match &self.env {
+            Some(vars) => (vars.iter().map(|(k, v)| (k.clone(), v.clone())).collect(), false),
+            None => (env_snapshot(), true),
+        };
+        let shell = match &self.shell {
+            None | Some(JsonShell::Enabled(false)) => None,
+            Some(JsonShell::Enabled(true)) => Some(DEFAULT_SHELL.to_string()),
+            Some(JsonShell::Path(path)) => Some(path.clone()),
+        };
+        Ok(ExecContext { cwd: Some(resolve_cwd(self.cwd.as_deref())?), env, full_env, shell })
+    }
+}
+
+/// Human readable summary of a retry policy
+pub fn describe_retry_policy(policy: &RetryPolicy) -> String {
+    let mut text = format!(
+        "up to {} attempts, backoff {}",
+        policy.max_attempts,
+        format_duration_secs(policy.backoff_secs)
+    );
+    let mut filters: Vec<String> =
+        policy.exit_codes.iter().map(|code| format!("exit {}", code)).collect();
+    filters.extend(policy.signals.iter().map(|sig| format!("signal {}", sig)));
+    if !filters.is_empty() {
+        text.push_str(&format!(", only on {}", filters.join(" or ")));
+    }
+    text
 }
 
 #[derive(StructOpt, Debug)]
@@ -33,6 +477,12 @@ pub enum SubmitCommand {
         #[structopt(long)]
         gpu_num: u8,
 
+        #[structopt(flatten)]
+        share: GpuShareArgs,
+
+        #[structopt(flatten)]
+        requirement: GpuRequirementArgs,
+
         /// Optional queue name
         #[structopt(long)]
         queue: Option<String>,
@@ -41,6 +491,28 @@ pub enum SubmitCommand {
         #[structopt(long)]
         name: Option<String>,
 
+        /// Start only after these tasks (IDs or names, comma-separated) have finished successfully
+        #[structopt(long, use_delimiter = true)]
+        after_ok: Vec<String>,
+
+        /// Start only after these tasks have ended, whatever the outcome
+        #[structopt(long, use_delimiter = true)]
+        after_any: Vec<String>,
+
+        /// Start only after these tasks have failed
+        #[structopt(long, use_delimiter = true)]
+        after_failed: Vec<String>,
+
+        /// Maximum runtime, e.g. "90s", "30m", "2h30m" (defaults to the queue's limit)
+        #[structopt(long, parse(try_from_str = parse_duration_secs))]
+        max_runtime: Option<u64>,
+
+        #[structopt(flatten)]
+        retry: RetryArgs,
+
+        #[structopt(flatten)]
+        exec: ExecArgs,
+
         /// Optional path to config file
         #[structopt(long)]
         config: Option<String>,
@@ -57,6 +529,12 @@ pub enum SubmitCommand {
         #[structopt(long)]
         gpu_num: u8,
 
+        #[structopt(flatten)]
+        share: GpuShareArgs,
+
+        #[structopt(flatten)]
+        requirement: GpuRequirementArgs,
+
         /// Optional queue name
         #[structopt(long)]
         queue: Option<String>,
@@ -65,6 +543,90 @@ pub enum SubmitCommand {
         #[structopt(long)]
         name: Option<String>,
 
+        /// Start only after these tasks (IDs or names, comma-separated) have finished successfully
+        #[structopt(long, use_delimiter = true)]
+        after_ok: Vec<String>,
+
+        /// Start only after these tasks have ended, whatever the outcome
+        #[structopt(long, use_delimiter = true)]
+        after_any: Vec<String>,
+
+        /// Start only after these tasks have failed
+        #[structopt(long, use_delimiter = true)]
+        after_failed: Vec<String>,
+
+        /// Maximum runtime, e.g. "90s", "30m", "2h30m" (defaults to the queue's limit)
+        #[structopt(long, parse(try_from_str = parse_duration_secs))]
+        max_runtime: Option<u64>,
+
+        #[structopt(flatten)]
+        retry: RetryArgs,
+
+        #[structopt(flatten)]
+        exec: ExecArgs,
+
+        /// Optional path to config file
+        #[structopt(long)]
+        config: Option<String>,
+    },
+
+    /// Submit an array of tasks, one per combination of parameter values (parameter sweep)
+    #[structopt(name = "array")]
+    Array {
+        /// Command template; {NAME} is replaced by the value of parameter NAME and {index} by
+        /// the index of the task in the array
+        #[structopt(long)]
+        cmd: String,
+
+        /// Parameter and its values, e.g. "lr in [1e-3,1e-4]", "seed in 0..8" or
+        /// "epoch in 1..=3". Repeat for a grid over several parameters.
+        #[structopt(long, required = true, number_of_values = 1, parse(try_from_str = parse_param))]
+        param: Vec<ArrayParam>,
+
+        /// Number of GPUs required by each task
+        #[structopt(long)]
+        gpu_num: u8,
+
+        #[structopt(flatten)]
+        share: GpuShareArgs,
+
+        #[structopt(flatten)]
+        requirement: GpuRequirementArgs,
+
+        /// Optional queue name
+        #[structopt(long)]
+        queue: Option<String>,
+
+        /// Optional array name; tasks are named "<name>[<index>]"
+        #[structopt(long)]
+        name: Option<String>,
+
+        /// Start only after these tasks (IDs or names, comma-separated) have finished successfully
+        #[structopt(long, use_delimiter = true)]
+        after_ok: Vec<String>,
+
+        /// Start only after these tasks have ended, whatever the outcome
+        #[structopt(long, use_delimiter = true)]
+        after_any: Vec<String>,
+
+        /// Start only after these tasks have failed
+        #[structopt(long, use_delimiter = true)]
+        after_failed: Vec<String>,
+
+        /// Maximum runtime of each task, e.g. "90s", "30m", "2h30m"
+        #[structopt(long, parse(try_from_str = parse_duration_secs))]
+        max_runtime: Option<u64>,
+
+        #[structopt(flatten)]
+        retry: RetryArgs,
+
+        #[structopt(flatten)]
+        exec: ExecArgs,
+
+        /// Print the commands of the tasks without submitting them
+        #[structopt(long)]
+        dry_run: bool,
+
         /// Optional path to config file
         #[structopt(long)]
         config: Option<String>,
@@ -93,12 +655,31 @@ impl SubmitCommand {
         let config_path: Option<String> = match &self {
             Self::Command { config, .. } => config.clone(),
             Self::Script { config, .. } => config.clone(),
+            Self::Array { config, .. } => config.clone(),
             Self::Json { config, .. } => config.clone(),
         };
         let socket_path = get_socket_path(config_path.as_deref())?; // Get socket path once
 
         match self {
-            Self::Command { cmd, gpu_num, mut queue, name, .. } => {
+            Self::Command {
+                cmd,
+                gpu_num,
+                share,
+                requirement,
+                mut queue,
+                name,
+                after_ok,
+                after_any,
+                after_failed,
+                max_runtime,
+                retry,
+                exec,
+                ..
+            } => {
+                let retry_policy = retry.into_policy()?;
+                let gpu_share = share.into_share()?;
+                let gpu_requirement = requirement.into_requirement();
+                let exec = exec.capture()?;
                 // If queue is None, set it to default_waiting_queue
                 if queue.is_none() {
                     queue = Some(DEFAULT_WAITING_QUEUE_NAME.to_string());
@@ -114,17 +695,25 @@ impl SubmitCommand {
                 let request = Message::SubmitCommand(SubmitAction::Command {
                     command: cmd,
                     gpu_num_required: gpu_num,
+                    gpu_share,
+                    gpu_requirement,
                     queue_name: queue,
                     name, // Pass name
+                    dependencies: build_dependencies(
+                        parse_refs(after_ok),
+                        parse_refs(after_any),
+                        parse_refs(after_failed),
+                    ),
+                    max_runtime,
+                    retry_policy,
+                    exec,
                 });
                 match request_reply(&socket_path, &request) {
                     Ok(Message::Ack(msg)) => {
                         println!("{} {}", "[SUCCESS]".green(), msg);
                         Ok(())
                     }
-                    Ok(Message::Error(err_msg)) => {
-                        Err(anyhow!("{} Daemon returned error: {}", "[ERROR]".red(), err_msg))
-                    }
+                    Ok(Message::Error(err)) => Err(daemon_error(err)),
                     Ok(other) => {
                         Err(anyhow!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other))
                     }
@@ -134,7 +723,30 @@ impl SubmitCommand {
                     }
                 }
             }
-            Self::Script { file, gpu_num, mut queue, name, .. } => {
+            Self::Script {
+                file,
+                gpu_num,
+                share,
+                requirement,
+                mut queue,
+                name,
+                after_ok,
+                after_any,
+                after_failed,
+                max_runtime,
+                retry,
+                exec,
+                ..
+            } => {
+                let retry_policy = retry.into_policy()?;
+                let gpu_share = share.into_share()?;
+                let gpu_requirement = requirement.into_requirement();
+                let exec = exec.capture()?;
+                // The daemon does not run in this directory, so send an absolute path
+                let file = fs::canonicalize(&file)
+                    .with_context(|| format!("Script file not found: {}", file))?
+                    .to_string_lossy()
+                    .to_string();
                 // If queue is None, set it to default_waiting_queue
                 if queue.is_none() {
                     queue = Some(DEFAULT_WAITING_QUEUE_NAME.to_string());
@@ -150,17 +762,25 @@ impl SubmitCommand {
                 let request = Message::SubmitCommand(SubmitAction::Script {
                     script_path: file,
                     gpu_num_required: gpu_num,
+                    gpu_share,
+                    gpu_requirement,
                     queue_name: queue,
                     name, // Pass name
+                    dependencies: build_dependencies(
+                        parse_refs(after_ok),
+                        parse_refs(after_any),
+                        parse_refs(after_failed),
+                    ),
+                    max_runtime,
+                    retry_policy,
+                    exec,
                 });
                 match request_reply(&socket_path, &request) {
                     Ok(Message::Ack(msg)) => {
                         println!("{} {}", "[SUCCESS]".green(), msg);
                         Ok(())
                     }
-                    Ok(Message::Error(err_msg)) => {
-                        Err(anyhow!("{} Daemon returned error: {}", "[ERROR]".red(), err_msg))
-                    }
+                    Ok(Message::Error(err)) => Err(daemon_error(err)),
                     Ok(other) => {
                         Err(anyhow!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other))
                     }
@@ -170,6 +790,80 @@ impl SubmitCommand {
                     }
                 }
             }
+            Self::Array {
+                cmd,
+                param,
+                gpu_num,
+                share,
+                requirement,
+                queue,
+                name,
+                after_ok,
+                after_any,
+                after_failed,
+                max_runtime,
+                retry,
+                exec,
+                dry_run,
+                ..
+            } => {
+                // Same expansion as the daemon, so mistakes show up before submitting
+                let expanded = array::expand_array(&cmd, &param)
+                    .with_context(|| format!("{} Invalid array", "[ERROR]".red()))?;
+                if dry_run {
+                    for (index, (command, _)) in expanded.iter().enumerate() {
+                        println!("{:>5}  {}", index.to_string().yellow(), command);
+                    }
+                    let count = expanded.len();
+                    println!("{} {} tasks, nothing submitted (--dry-run)", "[INFO]".blue(), count);
+                    return Ok(());
+                }
+                let retry_policy = retry.into_policy()?;
+                let gpu_share = share.into_share()?;
+                let gpu_requirement = requirement.into_requirement();
+                let exec = exec.capture()?;
+                let queue = queue.unwrap_or_else(|| DEFAULT_WAITING_QUEUE_NAME.to_string());
+                println!(
+                    "{} Submitting {} array tasks '{}' (Name: {}, GPUs: {}, Queue: {}) via RPC...",
+                    "[INFO]".blue(),
+                    expanded.len().to_string().yellow(),
+                    cmd.bold(),
+                    name.as_deref().unwrap_or("Default").cyan(),
+                    gpu_num.to_string().yellow(),
+                    queue.magenta()
+                );
+                let request = Message::SubmitCommand(SubmitAction::Array {
+                    command: cmd,
+                    params: param,
+                    gpu_num_required: gpu_num,
+                    gpu_share,
+                    gpu_requirement,
+                    queue_name: Some(queue),
+                    name,
+                    dependencies: build_dependencies(
+                        parse_refs(after_ok),
+                        parse_refs(after_any),
+                        parse_refs(after_failed),
+                    ),
+                    max_runtime,
+                    retry_policy,
+                    exec,
+                });
+                match request_reply(&socket_path, &request) {
+                    Ok(Message::Ack(msg)) => {
+                        println!("{} {}", "[SUCCESS]".green(), msg);
+                        Ok(())
+                    }
+                    Ok(Message::Error(err)) => Err(daemon_error(err)),
+                    Ok(other) => {
+                        Err(anyhow!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other))
+                    }
+                    Err(e) => {
+                        Err(anyhow!("{} Failed to send array to daemon", "[ERROR]".red())
+                            .context(e))
+                    }
+                }
+            }
             Self::Json { file, mut queue, .. } => {
                 // If default_queue_name is None for JSON, set it to default_waiting_queue
                 if queue.is_none() {
@@ -191,8 +885,22 @@ impl SubmitCommand {
                 let tasks: Vec<TaskMeta> = inputs
                     .into_iter()
                     .map(|input| {
+                        let max_runtime =
+                            input.max_runtime.as_ref().map(JsonDuration::secs).transpose()?;
+                        let exec = input.exec_context()?;
+                        let retry_policy =
+                            input.retry.map(JsonRetryPolicy::into_policy).transpose()?;
+                        let gpu_share = gpu_share(
+                            input.gpu_memory.as_ref().map(JsonMemory::mb).transpose()?,
+                            input.gpu_util,
+                        )?;
+                        let gpu_requirement = gpu_requirement(
+                            input.gpu_min_free.as_ref().map(JsonMemory::free_memory).transpose()?,
+                            input.gpu_min_total.as_ref().map(JsonMemory::mb).transpose()?,
+                            input.gpu_model,
+                        );
                         // Create a TaskMeta with default values for fields not in JsonTaskInput
-                        TaskMeta {
+                        Ok(TaskMeta {
                             pid: None,
                             id: 0,                                // Daemon will generate ID
                             name: input.name.unwrap_or_default(), // Use provided name or empty string (daemon will default)
@@ -209,10 +917,22 @@ impl SubmitCommand {
                             }),
                             create_time: 0, // Daemon will set time
                             gpu_ids: Vec::new(),
-                            failure_reason: None
-                        }
+                            failure_reason: None,
+                            dependencies: build_dependencies(
+                                input.after_ok,
+                                input.after_any,
+                                input.after_failed,
+                            ),
+                            max_runtime,
+                            retry_policy,
+                            exec,
+                            gpu_share,
+                            gpu_requirement,
+                            ..Default::default()
+                        })
                     })
-                    .collect();
+                    .collect::<Result<_>>()
+                    .with_context(|| format!("Invalid task in JSON file: {}", file))?;
 
                 let request = Message::SubmitCommand(SubmitAction::BatchJson {
                     tasks,
@@ -225,9 +945,7 @@ impl SubmitCommand {
                         println!("{} {}", "[SUCCESS]".green(), msg);
                         Ok(())
                     }
-                    Ok(Message::Error(err_msg)) => {
-                        Err(anyhow!("{} Daemon returned error: {}", "[ERROR]".red(), err_msg))
-                    }
+                    Ok(Message::Error(err)) => Err(daemon_error(err)),
                     Ok(other) => {
                         Err(anyhow!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other))
                     }
@@ -1,12 +1,69 @@
+use crate::cli::daemon_error;
 use crate::cli::get_socket_path; // Import socket path helper
+use crate::cli::output::{self, OutputFormat};
+use crate::cli::submit::describe_retry_policy;
 use anyhow::{anyhow, Context, Result}; // Added anyhow imports
-use colored::*; // Import colored
-use gavel_core::rpc::message::{Message, TaskAction, TaskFilter}; // Import RPC messages
-use gavel_core::rpc::request_reply; // Import RPC function
-use gavel_core::utils::models::TaskState;
 use chrono::DateTime; // Import chrono for time formatting
+use colored::*; // Import colored
+use gavel_core::rpc::message::{
+    ArraySelector, LogRequest, Message, ResourceKind, RpcError, TaskAction, TaskFilter,
+}; // Import RPC messages
+use gavel_core::rpc::{request_reply, BlockingGavelClient}; // Import RPC function
+use gavel_core::utils::array::{format_indices, parse_indices};
+use gavel_core::utils::models::{TaskMeta, TaskState};
+use gavel_core::utils::time::format_duration_secs;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
 use structopt::StructOpt; // Import TaskState for coloring
 
+/// A task, or tasks of an array: "ARRAY_ID:" for all of them, "ARRAY_ID:0-3,7" for a slice
+#[derive(Debug, Clone)]
+pub enum TaskTarget {
+    Task(u64),
+    Array(ArraySelector),
+}
+
+impl FromStr for TaskTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let Some((array_id, slice)) = value.split_once(':') else {
+            let task_id = value.parse().context("Invalid Task ID format, must be a number")?;
+            return Ok(TaskTarget::Task(task_id));
+        };
+        let array_id = array_id.parse().context("Invalid array ID format, must be a number")?;
+        let indices = match slice.trim() {
+            "" | "*" => None,
+            slice => Some(parse_indices(slice)?),
+        };
+        Ok(TaskTarget::Array(ArraySelector { array_id, indices }))
+    }
+}
+
+impl fmt::Display for TaskTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskTarget::Task(task_id) => write!(f, "task {}", task_id),
+            TaskTarget::Array(array) => write!(f, "{}", array),
+        }
+    }
+}
+
+// Colored state of a task in tables
+fn state_label(task: &TaskMeta) -> ColoredString {
+    match task.state {
+        TaskState::Waiting if task.preempted => "Preempted".magenta(),
+        TaskState::Waiting => "Waiting".cyan(),
+        TaskState::Running => "Running".green(),
+        TaskState::Finished => "Finished".blue(),
+        TaskState::Failed => "Failed".red(), // New: Red for Failed
+        TaskState::Cancelled => "Cancelled".yellow(),
+        TaskState::TimedOut => "TimedOut".red(),
+        TaskState::Retrying => "Retrying".yellow(),
+    }
+}
+
 #[derive(StructOpt, Debug)]
 pub enum TaskCommand {
     /// List tasks (default: pending tasks)
@@ -33,9 +90,19 @@ pub enum TaskCommand {
         config: Option<String>,
     },
 
-    /// View task details
+    /// View task details, or the status of an array
     #[structopt(name = "info")]
     Info {
+        /// Task ID, or ARRAY_ID: / ARRAY_ID:SLICE (e.g. 4213700:0-3,7) for tasks of an array
+        task_id: String,
+        /// Optional path to config file
+        #[structopt(long)]
+        config: Option<String>,
+    },
+
+    /// Explain why a waiting task has not started, GPU by GPU
+    #[structopt(name = "explain")]
+    Explain {
         /// Task ID
         task_id: String,
         /// Optional path to config file
@@ -53,10 +120,10 @@ pub enum TaskCommand {
         config: Option<String>,
     },
 
-    /// Terminate task
+    /// Terminate task; waiting tasks of an array are cancelled
     #[structopt(name = "kill")]
     Kill {
-        /// Task ID
+        /// Task ID, or ARRAY_ID: / ARRAY_ID:SLICE for tasks of an array
         task_id: String,
         /// Optional path to config file
         #[structopt(long)]
@@ -66,7 +133,7 @@ pub enum TaskCommand {
     /// Remove a finished or waiting task
     #[structopt(name = "remove")]
     Remove {
-        /// Task ID
+        /// Task ID, or ARRAY_ID: / ARRAY_ID:SLICE for tasks of an array
         task_id: String,
         /// Optional path to config file
         #[structopt(long)]
@@ -79,9 +146,17 @@ pub enum TaskCommand {
         /// Task ID
         task_id: String,
 
-        /// Show only log tail
-        #[structopt(long)]
+        /// Show only the last 10 lines (same as --lines 10)
+        #[structopt(long, conflicts_with = "lines")]
         tail: bool,
+
+        /// Show only the last N lines
+        #[structopt(long, short = "n")]
+        lines: Option<u32>,
+
+        /// Keep printing new output until the task has finished
+        #[structopt(long, short = "f")]
+        follow: bool,
         /// Optional path to config file
         #[structopt(long)]
         config: Option<String>,
@@ -89,11 +164,12 @@ pub enum TaskCommand {
 }
 
 impl TaskCommand {
-    pub fn execute(self) -> Result<()> {
+    pub fn execute(self, output: OutputFormat) -> Result<()> {
         // Extract config path first
         let config_path: Option<String> = match &self {
             Self::List { config, .. } => config.clone(),
             Self::Info { config, .. } => config.clone(),
+            Self::Explain { config, .. } => config.clone(),
             Self::Run { config, .. } => config.clone(),
             Self::Kill { config, .. } => config.clone(),
             Self::Remove { config, .. } => config.clone(), // Added Remove
@@ -103,13 +179,17 @@ impl TaskCommand {
 
         match self {
             Self::List { all, running, finished, queue, .. } => {
-                Self::handle_list(&socket_path, all, running, finished, queue)
+                Self::handle_list(&socket_path, all, running, finished, queue, output)
             }
-            Self::Info { task_id, .. } => Self::handle_info(&socket_path, task_id),
+            Self::Info { task_id, .. } => Self::handle_info(&socket_path, task_id, output),
+            Self::Explain { task_id, .. } => Self::handle_explain(&socket_path, task_id, output),
             Self::Run { task_id, .. } => Self::handle_run(&socket_path, task_id),
             Self::Kill { task_id, .. } => Self::handle_kill(&socket_path, task_id),
             Self::Remove { task_id, .. } => Self::handle_remove(&socket_path, task_id), // Added Remove
-            Self::Logs { task_id, tail, .. } => Self::handle_logs(&socket_path, task_id, tail),
+            Self::Logs { task_id, tail, lines, follow, .. } => {
+                let lines = if tail { Some(10) } else { lines };
+                Self::handle_logs(&socket_path, task_id, lines, follow)
+            }
         }
     }
 
@@ -119,6 +199,7 @@ impl TaskCommand {
         running: bool,
         finished: bool,
         queue: Option<String>,
+        output: OutputFormat,
     ) -> Result<()> {
         let filter = if all {
             TaskFilter::All
@@ -132,11 +213,20 @@ impl TaskCommand {
             // Default to waiting tasks if no specific filter is given
             TaskFilter::ByQueue(gavel_core::utils::DEFAULT_WAITING_QUEUE_NAME.to_string())
         };
-        println!("{} Listing tasks with filter: {:?} via RPC...", "[INFO]".blue(), filter);
+        if output.is_table() {
+            println!("{} Listing tasks with filter: {:?} via RPC...", "[INFO]".blue(), filter);
+        }
 
         let request = Message::TaskCommand(TaskAction::List { filter });
 
         match request_reply(socket_path, &request) {
+            Ok(Message::TaskStatus(tasks)) if !output.is_table() => {
+                output::print_list(output, &tasks)
+            }
+            // No matching tasks
+            Ok(Message::Ack(_)) if !output.is_table() => {
+                output::print_list::<TaskMeta>(output, &[])
+            }
             Ok(Message::TaskStatus(tasks)) => {
                 if tasks.is_empty() {
                     println!("{}", "No tasks found matching your criteria.".yellow());
@@ -144,19 +234,14 @@ impl TaskCommand {
                     println!(
                         "{}",
                         format!(
-                            "{:<5} | {:<20} | {:<10} | {:<10} | {:<8} | {:<15} | {:<6} | GPU IDs",
-                            "ID", "Name", "State", "Queue", "Prio", "Submit Time", "PID"
+                            "{:<5} | {:<20} | {:<10} | {:<10} | {:<8} | {:<15} | {:<6} | {:<7} | Pending Reason",
+                            "ID", "Name", "State", "Queue", "Prio", "Submit Time", "PID", "GPU IDs"
                         )
                         .bold()
                     );
                     println!("{}", "-".repeat(100)); // Separator line
                     for task in tasks {
-                        let state_str = match task.state {
-                            TaskState::Waiting => "Waiting".cyan(),
-                            TaskState::Running => "Running".green(),
-                            TaskState::Finished => "Finished".blue(),
-                            TaskState::Failed => "Failed".red(), // New: Red for Failed
-                        };
+                        let state_str = state_label(&task);
                         let pid_str = task.pid.map_or("N/A".to_string(), |p| p.to_string());
                         let gpu_ids_str = if task.gpu_ids.is_empty() {
                             "CPU".to_string()
@@ -172,8 +257,11 @@ impl TaskCommand {
                             .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
                             .unwrap_or_else(|| "Invalid Time".to_string());
 
+                        let pending_str =
+                            task.pending_reason.as_ref().map_or(String::new(), |r| r.describe());
+
                         println!(
-                            "{:<5} | {:<20} | {:<10} | {:<10} | {:<8} | {:<19} | {:<6} | {}", // Adjusted width for time
+                            "{:<5} | {:<20} | {:<10} | {:<10} | {:<8} | {:<19} | {:<6} | {:<7} | {}", // Adjusted width for time
                             task.id.to_string().yellow(), // Color ID
                             task.name,
                             state_str,
@@ -181,7 +269,8 @@ impl TaskCommand {
                             task.priority,
                             create_time_str, // Use formatted time string
                             pid_str,
-                            gpu_ids_str
+                            gpu_ids_str,
+                            pending_str
                         );
                     }
                 }
@@ -196,40 +285,115 @@ impl TaskCommand {
         }
     }
 
-    fn handle_info(socket_path: &str, task_id_str: String) -> Result<()> {
-        let task_id =
-            task_id_str.parse::<u64>().context("Invalid Task ID format, must be a number")?;
-        println!(
-            "{} Fetching info for task ID: {} via RPC...",
-            "[INFO]".blue(),
-            task_id.to_string().yellow()
-        ); // Color task ID
+    fn handle_info(socket_path: &str, task_id_str: String, output: OutputFormat) -> Result<()> {
+        let task_id = match task_id_str.parse::<TaskTarget>()? {
+            TaskTarget::Task(task_id) => task_id,
+            TaskTarget::Array(array) => return Self::handle_array_info(socket_path, array, output),
+        };
+        if output.is_table() {
+            println!(
+                "{} Fetching info for task ID: {} via RPC...",
+                "[INFO]".blue(),
+                task_id.to_string().yellow()
+            ); // Color task ID
+        }
 
         let request = Message::TaskCommand(TaskAction::Info { task_id });
 
         match request_reply(socket_path, &request) {
+            Ok(Message::TaskStatus(tasks)) if !output.is_table() && !tasks.is_empty() => {
+                output::print_item(output, &tasks[0])
+            }
             Ok(Message::TaskStatus(tasks)) => {
                 if let Some(task) = tasks.first() {
                     println!("{}", "---------------- Task Info ----------------".bold());
                     println!("{:<20}: {}", "ID", task.id.to_string().yellow());
                     println!("{:<20}: {}", "Name", task.name);
                     let state_str = match task.state {
+                        TaskState::Waiting if task.preempted => "Waiting (Preempted)".magenta(),
                         TaskState::Waiting => "Waiting".cyan(),
+                        TaskState::Running if task.preempted => "Running (Preempting)".yellow(),
                         TaskState::Running => "Running".green(),
                         TaskState::Finished => "Finished".blue(),
                         TaskState::Failed => "Failed".red(), // New: Red for Failed
+                        TaskState::Cancelled => "Cancelled".yellow(),
+                        TaskState::TimedOut => "Timed Out".red(),
+                        TaskState::Retrying => "Retrying".yellow(),
                     };
                     println!("{:<20}: {}", "State", state_str);
-                    if task.state == TaskState::Failed {
+                    if let Some(reason) = task.pending_reason.as_ref() {
+                        println!("{:<20}: {}", "Pending Reason", reason.describe().cyan());
+                    }
+                    if matches!(
+                        task.state,
+                        TaskState::Failed
+                            | TaskState::Cancelled
+                            | TaskState::TimedOut
+                            | TaskState::Retrying
+                    ) {
                         if let Some(reason) = &task.failure_reason {
                             println!("{:<20}: {}", "Failure Reason", reason.red()); // New: Display reason in red
                         }
                     }
+                    if let Some(member) = &task.array {
+                        println!(
+                            "{:<20}: {} (index {} of {})",
+                            "Array",
+                            member.array_id.to_string().yellow(),
+                            member.index,
+                            member.size
+                        );
+                        let values: Vec<String> = member
+                            .params
+                            .iter()
+                            .map(|(name, value)| format!("{}={}", name, value))
+                            .collect();
+                        println!("{:<20}: {}", "Parameters", values.join(" "));
+                    }
                     println!("{:<20}: {}", "Queue", task.queue);
                     println!("{:<20}: {}", "Priority", task.priority);
                     println!("{:<20}: {}", "Command", task.cmd);
+                    if let Some(shell) = &task.exec.shell {
+                        println!("{:<20}: {}", "Shell", shell);
+                    }
+                    if let Some(cwd) = &task.exec.cwd {
+                        println!("{:<20}: {}", "Working Dir", cwd);
+                    }
+                    let env_str = if task.exec.full_env {
+                        format!("{} variables captured at submission", task.exec.env.len())
+                    } else if task.exec.env.is_empty() {
+                        "Inherited from the daemon".to_string()
+                    } else {
+                        let names: Vec<&str> =
+                            task.exec.env.iter().map(|(name, _)| name.as_str()).collect();
+                        format!("Daemon environment + {}", names.join(", "))
+                    };
+                    println!("{:<20}: {}", "Environment", env_str);
                     println!("{:<20}: {}", "Log Path", task.log_path);
+                    if !task.dependencies.is_empty() {
+                        let deps_str = task
+                            .dependencies
+                            .iter()
+                            .map(|d| match &d.task_name {
+                                Some(name) => {
+                                    format!("{} {} ({})", d.kind.as_str(), d.task_id, name)
+                                }
+                                None => format!("{} {}", d.kind.as_str(), d.task_id),
+                            })
+                            .collect::<Vec<String>>()
+                            .join(", ");
+                        println!("{:<20}: {}", "Dependencies", deps_str);
+                    }
                     println!("{:<20}: {}", "GPUs Required", task.gpu_require);
+                    if let Some(share) = &task.gpu_share {
+                        println!("{:<20}: {}", "GPU Share", share.describe());
+                    }
+                    if let Some(requirement) = &task.gpu_requirement {
+                        println!("{:<20}: {}", "GPU Requirement", requirement.describe());
+                    }
+                    let max_runtime_str =
+                        task.max_runtime.map_or("Queue default".to_string(), format_duration_secs);
+                    println!("{:<20}: {}", "Max Runtime", max_runtime_str);
                     let gpu_ids_str = if task.gpu_ids.is_empty() {
                         "CPU (None assigned)".to_string()
                     } else {
@@ -245,11 +409,55 @@ impl TaskCommand {
                         "PID",
                         task.pid.map_or("N/A".to_string(), |p| p.to_string())
                     );
+                    if let Some(exit_info) = &task.exit_info {
+                        let status_str = match (exit_info.exit_code, exit_info.signal) {
+                            (Some(code), _) => format!("code {}", code),
+                            (None, Some(sig)) => format!("signal {}", sig),
+                            (None, None) => "unknown".to_string(),
+                        };
+                        println!("{:<20}: {}", "Exit Status", status_str);
+                        println!(
+                            "{:<20}: user {:.2}s, system {:.2}s, max RSS {} KB",
+                            "Resource Usage",
+                            exit_info.user_time_ms as f64 / 1000.0,
+                            exit_info.system_time_ms as f64 / 1000.0,
+                            exit_info.max_rss_kb
+                        );
+                    }
                     // Format create_time from timestamp to human-readable string
                     let create_time_str = DateTime::from_timestamp(task.create_time as i64, 0)
                         .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
                         .unwrap_or_else(|| "Invalid Time".to_string());
                     println!("{:<20}: {}", "Create Time", create_time_str); // Use formatted time
+                    if let Some(policy) = &task.retry_policy {
+                        println!("{:<20}: {}", "Retry Policy", describe_retry_policy(policy));
+                    }
+                    if let Some(retry_at) =
+                        task.retry_at.filter(|_| task.state == TaskState::Retrying)
+                    {
+                        let retry_at_str = DateTime::from_timestamp(retry_at as i64, 0)
+                            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+                            .unwrap_or_else(|| "Invalid Time".to_string());
+                        println!("{:<20}: {}", "Next Attempt At", retry_at_str);
+                    }
+                    if !task.attempts.is_empty() {
+                        println!("{:<20}:", "Attempts");
+                        for attempt in &task.attempts {
+                            println!(
+                                "  #{:<3} {:<10} {} (log: {})",
+                                attempt.attempt,
+                                format!("{:?}", attempt.state),
+                                attempt.reason.as_deref().unwrap_or("-"),
+                                attempt.log_path
+                            );
+                        }
+                    }
+                    if let Some(start_time) = task.start_time {
+                        let start_time_str = DateTime::from_timestamp(start_time as i64, 0)
+                            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+                            .unwrap_or_else(|| "Invalid Time".to_string());
+                        println!("{:<20}: {}", "Start Time", start_time_str);
+                    }
                     println!("{}", "-----------------------------------------".bold());
                 } else {
                     println!("{}", "Task not found.".red());
@@ -265,6 +473,137 @@ impl TaskCommand {
         }
     }
 
+    fn handle_explain(socket_path: &str, task_id_str: String, output: OutputFormat) -> Result<()> {
+        let task_id =
+            task_id_str.parse::<u64>().context("Invalid Task ID format, must be a number")?;
+        if output.is_table() {
+            println!(
+                "{} Explaining task ID: {} via RPC...",
+                "[INFO]".blue(),
+                task_id.to_string().yellow()
+            );
+        }
+
+        let request = Message::TaskCommand(TaskAction::Explain { task_id });
+
+        let explanation = match request_reply(socket_path, &request) {
+            Ok(Message::TaskExplanation(explanation)) => explanation,
+            Ok(Message::Error(err)) => return Err(daemon_error(err)),
+            Ok(other) => return Err(anyhow!("Unexpected response from daemon: {:?}", other)),
+            Err(e) => return Err(e.context(format!("Failed to explain task {}", task_id))),
+        };
+        if !output.is_table() {
+            return output::print_item(output, &explanation);
+        }
+
+        if explanation.state != TaskState::Waiting {
+            println!("Task {} is not waiting, it is {:?}.", task_id, explanation.state);
+            return Ok(());
+        }
+        match &explanation.reason {
+            Some(reason) => println!("{:<20}: {}", "Pending Reason", reason.describe().cyan()),
+            None => println!(
+                "{:<20}: {}",
+                "Pending Reason",
+                "None yet, the scheduler has not looked at the task".yellow()
+            ),
+        }
+        if explanation.gpus.is_empty() {
+            return Ok(());
+        }
+        println!();
+        println!("{}", format!("{:<5} | {:<26} | Checks", "GPU", "Model").bold());
+        println!("{}", "-".repeat(80));
+        for gpu in &explanation.gpus {
+            let checks = if gpu.problems.is_empty() {
+                "OK, can take the task".green()
+            } else {
+                let problems: Vec<String> = gpu.problems.iter().map(|p| p.to_string()).collect();
+                problems.join("; ").red()
+            };
+            println!("{:<5} | {:<26} | {}", gpu.gpu_id, gpu.name, checks);
+        }
+        Ok(())
+    }
+
+    // Status of an array: task counts by state, then one line per task
+    fn handle_array_info(
+        socket_path: &str,
+        array: ArraySelector,
+        output: OutputFormat,
+    ) -> Result<()> {
+        if output.is_table() {
+            println!("{} Fetching status of {} via RPC...", "[INFO]".blue(), array);
+        }
+        let request =
+            Message::TaskCommand(TaskAction::List { filter: TaskFilter::ByArray(array.clone()) });
+        let mut tasks = match request_reply(socket_path, &request) {
+            Ok(Message::TaskStatus(tasks)) => tasks,
+            Ok(Message::Ack(_)) => Vec::new(), // No matching tasks
+            Ok(Message::Error(err)) => return Err(daemon_error(err)),
+            Ok(other) => return Err(anyhow!("Unexpected response from daemon: {:?}", other)),
+            Err(e) => return Err(e.context(format!("Failed to get status of {}", array))),
+        };
+        tasks.sort_by_key(|t| t.array.as_ref().map(|a| a.index));
+        let Some(member) = tasks.first().and_then(|t| t.array.clone()) else {
+            return Err(daemon_error(RpcError::not_found(ResourceKind::Array, array.array_id)));
+        };
+        if !output.is_table() {
+            return output::print_list(output, &tasks);
+        }
+
+        let indices: Vec<u32> =
+            tasks.iter().filter_map(|t| t.array.as_ref().map(|a| a.index)).collect();
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for task in &tasks {
+            let state = state_label(task).to_string();
+            match counts.iter_mut().find(|(s, _)| *s == state) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((state, 1)),
+            }
+        }
+        let params: Vec<&str> = member.params.iter().map(|(name, _)| name.as_str()).collect();
+
+        println!("{}", "---------------- Array Info ----------------".bold());
+        println!("{:<20}: {}", "Array ID", member.array_id.to_string().yellow());
+        println!(
+            "{:<20}: {} of {} (indices {})",
+            "Tasks",
+            tasks.len(),
+            member.size,
+            format_indices(&indices)
+        );
+        println!("{:<20}: {}", "Parameters", params.join(", "));
+        let counts_str: Vec<String> =
+            counts.iter().map(|(state, count)| format!("{} {}", count, state)).collect();
+        println!("{:<20}: {}", "States", counts_str.join(", "));
+        println!("{}", "--------------------------------------------".bold());
+        println!(
+            "{}",
+            format!(
+                "{:<5} | {:<10} | {:<10} | {:<10} | {:<4} | Parameters",
+                "Index", "ID", "State", "Queue", "Prio"
+            )
+            .bold()
+        );
+        println!("{}", "-".repeat(80));
+        for task in &tasks {
+            let Some(member) = &task.array else { continue };
+            let values: Vec<String> =
+                member.params.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+            println!(
+                "{:<5} | {:<10} | {:<10} | {:<10} | {:<4} | {}",
+                member.index,
+                task.id.to_string().yellow(),
+                state_label(task),
+                task.queue,
+                task.priority,
+                values.join(" ")
+            );
+        }
+        Ok(())
+    }
+
     fn handle_run(socket_path: &str, task_id_str: String) -> Result<()> {
         let task_id =
             task_id_str.parse::<u64>().context("Invalid Task ID format, must be a number")?;
@@ -281,9 +620,7 @@ impl TaskCommand {
                 println!("{} Daemon reply: {}", "[SUCCESS]".green(), msg.italic()); // Format Ack
                 Ok(())
             }
-            Ok(Message::Error(err_msg)) => {
-                Err(anyhow!("{} Daemon returned error: {}", "[ERROR]".red(), err_msg))
-            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
             Ok(other) => Err(anyhow!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other)),
             Err(e) => Err(anyhow!(
                 "{} Failed to send run command for task {} to daemon",
@@ -295,29 +632,29 @@ impl TaskCommand {
     }
 
     fn handle_kill(socket_path: &str, task_id_str: String) -> Result<()> {
-        let task_id =
-            task_id_str.parse::<u64>().context("Invalid Task ID format, must be a number")?;
+        let target = task_id_str.parse::<TaskTarget>()?;
         println!(
-            "{} Requesting to kill task {} via RPC...",
+            "{} Requesting to kill {} via RPC...",
             "[INFO]".blue(),
-            task_id.to_string().yellow()
+            target.to_string().yellow()
         ); // Color task ID
 
-        let request = Message::TaskCommand(TaskAction::Kill { task_id });
+        let request = Message::TaskCommand(match target.clone() {
+            TaskTarget::Task(task_id) => TaskAction::Kill { task_id },
+            TaskTarget::Array(array) => TaskAction::KillArray { array },
+        });
 
         match request_reply(socket_path, &request) {
             Ok(Message::Ack(msg)) => {
                 println!("{} Daemon reply: {}", "[SUCCESS]".green(), msg.italic()); // Format Ack
                 Ok(())
             }
-            Ok(Message::Error(err_msg)) => {
-                Err(anyhow!("{} Daemon returned error: {}", "[ERROR]".red(), err_msg))
-            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
             Ok(other) => Err(anyhow!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other)),
             Err(e) => Err(anyhow!(
-                "{} Failed to send kill command for task {} to daemon",
+                "{} Failed to send kill command for {} to daemon",
                 "[ERROR]".red(),
-                task_id
+                target
             )
             .context(e)),
         }
@@ -325,63 +662,79 @@ impl TaskCommand {
 
     // Added handle_remove
     fn handle_remove(socket_path: &str, task_id_str: String) -> Result<()> {
-        let task_id =
-            task_id_str.parse::<u64>().context("Invalid Task ID format, must be a number")?;
+        let target = task_id_str.parse::<TaskTarget>()?;
         println!(
-            "{} Requesting to remove task {} via RPC...",
+            "{} Requesting to remove {} via RPC...",
             "[INFO]".blue(),
-            task_id.to_string().yellow()
+            target.to_string().yellow()
         ); // Color task ID
 
-        let request = Message::TaskCommand(TaskAction::Remove { task_id });
+        let request = Message::TaskCommand(match target.clone() {
+            TaskTarget::Task(task_id) => TaskAction::Remove { task_id },
+            TaskTarget::Array(array) => TaskAction::RemoveArray { array },
+        });
 
         match request_reply(socket_path, &request) {
             Ok(Message::Ack(msg)) => {
                 println!("{} Daemon reply: {}", "[SUCCESS]".green(), msg.italic()); // Format Ack
                 Ok(())
             }
-            Ok(Message::Error(err_msg)) => {
-                Err(anyhow!("{} Daemon returned error: {}", "[ERROR]".red(), err_msg))
-            }
+            Ok(Message::Error(err)) => Err(daemon_error(err)),
             Ok(other) => Err(anyhow!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other)),
             Err(e) => Err(anyhow!(
-                "{} Failed to send remove command for task {} to daemon",
+                "{} Failed to send remove command for {} to daemon",
                 "[ERROR]".red(),
-                task_id
+                target
             )
             .context(e)),
         }
     }
 
-    fn handle_logs(socket_path: &str, task_id_str: String, tail: bool) -> Result<()> {
+    fn handle_logs(
+        socket_path: &str,
+        task_id_str: String,
+        lines: Option<u32>,
+        follow: bool,
+    ) -> Result<()> {
         let task_id =
             task_id_str.parse::<u64>().context("Invalid Task ID format, must be a number")?;
+        let part = match lines {
+            Some(n) => format!("last {} lines of", n),
+            None => "full".to_string(),
+        };
         println!(
-            "{} Fetching {} logs for task {} via RPC...",
+            "{} Fetching {} logs for task {} via RPC{}...",
             "[INFO]".blue(),
-            if tail { "tail of".italic() } else { "full".italic() }, // Removed unnecessary parentheses
-            task_id.to_string().yellow()
-        ); // Color task ID and format tail/full
-
-        let request = Message::TaskCommand(TaskAction::Logs { task_id, tail });
+            part.italic(),
+            task_id.to_string().yellow(),
+            if follow { ", following new output" } else { "" }
+        );
+
+        let request = LogRequest { task_id, offset: None, lines, follow };
+        let failed = |e| {
+            let message = format!("{} Failed to get logs of task {}", "[ERROR]".red(), task_id);
+            anyhow::Error::new(e).context(message)
+        };
+        let mut chunks =
+            BlockingGavelClient::new(socket_path).stream_logs(request).map_err(failed)?.peekable();
+        // Report a missing task or log before printing anything
+        if let Some(Err(e)) = chunks.next_if(|chunk| chunk.is_err()) {
+            return Err(failed(e));
+        }
 
-        match request_reply(socket_path, &request) {
-            Ok(Message::Ack(log_content)) => {
-                println!("--- Logs for Task {} ---", task_id.to_string().bold());
-                println!("{}", log_content); // Keep logs as is, maybe add syntax highlighting later if needed
-                println!("--- End Logs ---");
-                Ok(())
-            }
-            Ok(Message::Error(err_msg)) => {
-                Err(anyhow!("{} Daemon returned error: {}", "[ERROR]".red(), err_msg))
-            }
-            Ok(other) => Err(anyhow!("{} Received unexpected reply: {:?}", "[ERROR]".red(), other)),
-            Err(e) => Err(anyhow!(
-                "{} Failed to send logs command for task {} to daemon",
-                "[ERROR]".red(),
-                task_id
-            )
-            .context(e)),
+        println!("--- Logs for Task {} ---", task_id.to_string().bold());
+        let mut stdout = io::stdout().lock();
+        let mut ends_with_newline = true;
+        for chunk in chunks {
+            let chunk = chunk.map_err(failed)?;
+            stdout.write_all(&chunk.data)?;
+            stdout.flush()?;
+            ends_with_newline = chunk.data.ends_with(b"\n");
+        }
+        if !ends_with_newline {
+            writeln!(stdout)?;
         }
+        writeln!(stdout, "--- End Logs ---")?;
+        Ok(())
     }
 }
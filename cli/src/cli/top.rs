@@ -0,0 +1,484 @@
+// Full-screen dashboard of GPUs, queues and tasks, refreshed from the daemon
+use crate::cli::get_socket_path;
+use anyhow::{Context, Result};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use gavel_core::gpu::monitor::GpuStats;
+use gavel_core::rpc::message::TaskFilter;
+use gavel_core::rpc::BlockingGavelClient;
+use gavel_core::utils::models::{QueueMeta, TaskMeta, TaskState};
+use gavel_core::utils::time::{format_duration_secs, now_secs};
+use std::io;
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+use tui::backend::{Backend, CrosstermBackend};
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState, Tabs};
+use tui::{Frame, Terminal};
+
+const BAR_WIDTH: usize = 10;
+
+#[derive(StructOpt, Debug)]
+pub struct TopCommand {
+    /// Seconds between refreshes
+    #[structopt(long, default_value = "1")]
+    interval: u64,
+
+    #[structopt(long)]
+    config: Option<String>,
+}
+
+impl TopCommand {
+    pub fn execute(self) -> Result<()> {
+        let socket_path = get_socket_path(self.config.as_deref())?;
+        let mut app = App::new(BlockingGavelClient::new(socket_path));
+        // Fail before taking over the terminal if the daemon is not there
+        app.snapshot = Snapshot::fetch(&app.client).context("Failed to query the daemon")?;
+        // Start on the first queue that has tasks
+        app.selected_queue = app
+            .snapshot
+            .queues
+            .iter()
+            .position(|q| !q.running_task_ids.is_empty() || !q.waiting_task_ids.is_empty())
+            .unwrap_or(0);
+        app.task_table.select(Some(0));
+
+        let _guard = TerminalGuard::enter()?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+        let interval = Duration::from_secs(self.interval.max(1));
+        let mut last_refresh = Instant::now();
+        loop {
+            terminal.draw(|f| draw(f, &mut app))?;
+
+            let timeout = interval.saturating_sub(last_refresh.elapsed());
+            if event::poll(timeout)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press && !app.handle_key(key) {
+                        return Ok(());
+                    }
+                }
+            }
+            if app.refresh_requested || last_refresh.elapsed() >= interval {
+                app.refresh();
+                last_refresh = Instant::now();
+            }
+        }
+    }
+}
+
+/// Raw mode and the alternate screen, restored on drop (also when panicking)
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> Result<Self> {
+        enable_raw_mode().context("Failed to enable raw terminal mode")?;
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+        let _ = disable_raw_mode();
+    }
+}
+
+#[derive(Default)]
+struct Snapshot {
+    gpus: Vec<GpuStats>,
+    queues: Vec<QueueMeta>,
+    tasks: Vec<TaskMeta>,
+}
+
+impl Snapshot {
+    fn fetch(client: &BlockingGavelClient) -> Result<Self> {
+        let mut gpus = client.list_gpus()?;
+        gpus.sort_by_key(|gpu| gpu.gpu_index);
+        let mut queues = client.list_queues()?;
+        queues.sort_by(|a, b| b.priority.cmp(&a.priority).then_with(|| a.name.cmp(&b.name)));
+        let tasks = client.list_tasks(TaskFilter::All)?;
+        Ok(Snapshot { gpus, queues, tasks })
+    }
+}
+
+enum Mode {
+    Normal,
+    ConfirmKill(u64),
+    Input { action: InputAction, buffer: String },
+}
+
+#[derive(Clone, Copy)]
+enum InputAction {
+    Move(u64),
+    Priority(u64),
+}
+
+struct App {
+    client: BlockingGavelClient,
+    snapshot: Snapshot,
+    selected_queue: usize,
+    task_table: TableState,
+    show_finished: bool,
+    mode: Mode,
+    status: Result<String, String>, // Outcome of the last action or refresh
+    refresh_requested: bool,
+}
+
+impl App {
+    fn new(client: BlockingGavelClient) -> Self {
+        App {
+            client,
+            snapshot: Snapshot::default(),
+            selected_queue: 0,
+            task_table: TableState::default(),
+            show_finished: false,
+            mode: Mode::Normal,
+            status: Ok(String::new()),
+            refresh_requested: false,
+        }
+    }
+
+    fn refresh(&mut self) {
+        self.refresh_requested = false;
+        match Snapshot::fetch(&self.client) {
+            Ok(snapshot) => {
+                self.snapshot = snapshot;
+                if matches!(&self.status, Err(e) if e.starts_with("Refresh failed")) {
+                    self.status = Ok(String::new());
+                }
+            }
+            Err(e) => self.status = Err(format!("Refresh failed: {}", e)),
+        }
+        self.selected_queue = self.selected_queue.min(self.snapshot.queues.len().saturating_sub(1));
+        let task_count = self.queue_tasks().len();
+        match self.task_table.selected() {
+            _ if task_count == 0 => self.task_table.select(None),
+            Some(i) => self.task_table.select(Some(i.min(task_count - 1))),
+            None => self.task_table.select(Some(0)),
+        }
+    }
+
+    /// Tasks of the selected queue: running ones first, then by priority
+    fn queue_tasks(&self) -> Vec<&TaskMeta> {
+        let Some(queue) = self.snapshot.queues.get(self.selected_queue) else {
+            return Vec::new();
+        };
+        let mut tasks: Vec<&TaskMeta> = self
+            .snapshot
+            .tasks
+            .iter()
+            .filter(|t| t.queue == queue.name && (self.show_finished || !is_final(&t.state)))
+            .collect();
+        tasks.sort_by_key(|t| (t.state != TaskState::Running, std::cmp::Reverse(t.priority), t.id));
+        tasks
+    }
+
+    fn selected_task(&self) -> Option<u64> {
+        let index = self.task_table.selected()?;
+        self.queue_tasks().get(index).map(|t| t.id)
+    }
+
+    /// Handles a key press; false quits
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return false;
+        }
+        match std::mem::replace(&mut self.mode, Mode::Normal) {
+            Mode::Normal => return self.handle_normal_key(key.code),
+            Mode::ConfirmKill(task_id) => {
+                if let KeyCode::Char('y') | KeyCode::Char('Y') = key.code {
+                    let result = self.client.kill(task_id);
+                    self.finish_action(result);
+                }
+            }
+            Mode::Input { action, mut buffer } => match key.code {
+                KeyCode::Enter => self.run_input(action, buffer.trim()),
+                KeyCode::Esc => {}
+                KeyCode::Backspace => {
+                    buffer.pop();
+                    self.mode = Mode::Input { action, buffer };
+                }
+                KeyCode::Char(c) => {
+                    buffer.push(c);
+                    self.mode = Mode::Input { action, buffer };
+                }
+                _ => self.mode = Mode::Input { action, buffer },
+            },
+        }
+        true
+    }
+
+    fn handle_normal_key(&mut self, code: KeyCode) -> bool {
+        let queue_count = self.snapshot.queues.len().max(1);
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Right | KeyCode::Tab => {
+                self.selected_queue = (self.selected_queue + 1) % queue_count;
+                self.task_table.select(Some(0));
+                self.refresh_requested = true;
+            }
+            KeyCode::Left | KeyCode::BackTab => {
+                self.selected_queue = (self.selected_queue + queue_count - 1) % queue_count;
+                self.task_table.select(Some(0));
+                self.refresh_requested = true;
+            }
+            KeyCode::Down => {
+                let last = self.queue_tasks().len().saturating_sub(1);
+                let next = self.task_table.selected().map_or(0, |i| (i + 1).min(last));
+                self.task_table.select(Some(next));
+            }
+            KeyCode::Up => {
+                let prev = self.task_table.selected().map_or(0, |i| i.saturating_sub(1));
+                self.task_table.select(Some(prev));
+            }
+            KeyCode::Char('f') => {
+                self.show_finished = !self.show_finished;
+                self.refresh_requested = true;
+            }
+            KeyCode::Char('k') => {
+                if let Some(task_id) = self.selected_task() {
+                    self.mode = Mode::ConfirmKill(task_id);
+                }
+            }
+            KeyCode::Char('r') => {
+                if let Some(task_id) = self.selected_task() {
+                    let result = self.client.run_task(task_id);
+                    self.finish_action(result);
+                }
+            }
+            KeyCode::Char('m') => {
+                if let Some(task_id) = self.selected_task() {
+                    let buffer = String::new();
+                    self.mode = Mode::Input { action: InputAction::Move(task_id), buffer };
+                }
+            }
+            KeyCode::Char('p') => {
+                if let Some(task_id) = self.selected_task() {
+                    let buffer = String::new();
+                    self.mode = Mode::Input { action: InputAction::Priority(task_id), buffer };
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn run_input(&mut self, action: InputAction, input: &str) {
+        let result = match action {
+            InputAction::Move(task_id) => self.client.move_task(task_id, input),
+            InputAction::Priority(task_id) => match input.parse::<u8>() {
+                Ok(level) => self.client.set_task_priority(task_id, level),
+                Err(_) => {
+                    self.status = Err(format!("Invalid priority '{}'", input));
+                    return;
+                }
+            },
+        };
+        self.finish_action(result);
+    }
+
+    fn finish_action(&mut self, result: gavel_core::rpc::client::Result<String>) {
+        self.status = result.map_err(|e| e.to_string());
+        self.refresh_requested = true;
+    }
+}
+
+fn is_final(state: &TaskState) -> bool {
+    matches!(
+        state,
+        TaskState::Finished | TaskState::Failed | TaskState::Cancelled | TaskState::TimedOut
+    )
+}
+
+fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let gpu_height = app.snapshot.gpus.len().max(1) as u16 + 3;
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(gpu_height),
+            Constraint::Length(3),
+            Constraint::Min(4),
+            Constraint::Length(2),
+        ])
+        .split(f.size());
+    draw_gpus(f, app, chunks[0]);
+    draw_queue_tabs(f, app, chunks[1]);
+    draw_tasks(f, app, chunks[2]);
+    draw_footer(f, app, chunks[3]);
+}
+
+/// Text gauge such as "██████░░░░  60%", colored by how full it is
+fn bar(ratio: f64, label: String) -> Cell<'static> {
+    let ratio = ratio.clamp(0.0, 1.0);
+    let filled = (ratio * BAR_WIDTH as f64).round() as usize;
+    let text = format!("{}{} {}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled), label);
+    let color = if ratio > 0.8 {
+        Color::Red
+    } else if ratio > 0.5 {
+        Color::Yellow
+    } else {
+        Color::Green
+    };
+    Cell::from(Span::styled(text, Style::default().fg(color)))
+}
+
+fn draw_gpus<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let header = Row::new(["GPU", "Queue", "Task", "Temp", "Util", "Memory", "Power"])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+    let rows = app.snapshot.gpus.iter().map(|gpu| {
+        let index = gpu.gpu_index;
+        let owners: Vec<&str> = app
+            .snapshot
+            .queues
+            .iter()
+            .filter(|q| q.allocated_gpus.iter().any(|&id| id as u32 == index))
+            .map(|q| q.name.as_str())
+            .collect();
+        let tasks: Vec<String> = app
+            .snapshot
+            .tasks
+            .iter()
+            .filter(|t| t.state == TaskState::Running)
+            .filter(|t| t.gpu_ids.iter().any(|&id| id as u32 == index))
+            .map(|t| format!("{} {}", t.id, t.name))
+            .collect();
+        let mem = &gpu.memory_usage;
+        let mem_ratio = if mem.total > 0 { mem.used as f64 / mem.total as f64 } else { 0.0 };
+        let power = if gpu.power_limit > 0 {
+            let label = format!("{}/{}W", gpu.power_usage / 1000, gpu.power_limit / 1000);
+            bar(gpu.power_usage as f64 / gpu.power_limit as f64, label)
+        } else {
+            Cell::from(format!("{}W", gpu.power_usage / 1000))
+        };
+        Row::new(vec![
+            Cell::from(index.to_string()),
+            Cell::from(if owners.is_empty() { "-".to_string() } else { owners.join(",") }),
+            Cell::from(if tasks.is_empty() { "-".to_string() } else { tasks.join(", ") }),
+            bar(gpu.temperature as f64 / 100.0, format!("{}C", gpu.temperature)),
+            bar(gpu.core_usage as f64 / 100.0, format!("{}%", gpu.core_usage)),
+            bar(mem_ratio, format!("{}/{}MB", mem.used / (1024 * 1024), mem.total / (1024 * 1024))),
+            power,
+        ])
+    });
+    let widths = [
+        Constraint::Length(4),
+        Constraint::Length(14),
+        Constraint::Min(16),
+        Constraint::Length(16),
+        Constraint::Length(16),
+        Constraint::Length(26),
+        Constraint::Length(20),
+    ];
+    let table = Table::new(rows)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(" GPUs "))
+        .widths(&widths);
+    f.render_widget(table, area);
+}
+
+fn draw_queue_tabs<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let titles = app
+        .snapshot
+        .queues
+        .iter()
+        .map(|q| {
+            let running = q.running_task_ids.len();
+            let waiting = q.waiting_task_ids.len();
+            Spans::from(format!("{} (P{}, {}R/{}W)", q.name, q.priority, running, waiting))
+        })
+        .collect();
+    let tabs = Tabs::new(titles)
+        .select(app.selected_queue)
+        .block(Block::default().borders(Borders::ALL).title(" Queues "))
+        .highlight_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD));
+    f.render_widget(tabs, area);
+}
+
+fn draw_tasks<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
+    let now = now_secs();
+    let header = Row::new(["ID", "Name", "State", "Prio", "GPUs", "Time"])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+    let rows: Vec<Row> = app
+        .queue_tasks()
+        .into_iter()
+        .map(|task| {
+            let (state, color) = match task.state {
+                TaskState::Waiting if task.preempted => ("Preempted", Color::Magenta),
+                TaskState::Waiting => ("Waiting", Color::Cyan),
+                TaskState::Running => ("Running", Color::Green),
+                TaskState::Finished => ("Finished", Color::Blue),
+                TaskState::Failed => ("Failed", Color::Red),
+                TaskState::Cancelled => ("Cancelled", Color::Yellow),
+                TaskState::TimedOut => ("TimedOut", Color::Red),
+                TaskState::Retrying => ("Retrying", Color::Yellow),
+            };
+            let gpus = if task.gpu_ids.is_empty() {
+                format!("{} req", task.gpu_require)
+            } else {
+                task.gpu_ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(",")
+            };
+            // Running time, otherwise time since submission
+            let since = match (&task.state, task.start_time) {
+                (TaskState::Running, Some(start)) => start,
+                _ => task.create_time,
+            };
+            Row::new(vec![
+                Cell::from(task.id.to_string()),
+                Cell::from(task.name.clone()),
+                Cell::from(Span::styled(state, Style::default().fg(color))),
+                Cell::from(task.priority.to_string()),
+                Cell::from(gpus),
+                Cell::from(format_duration_secs(now.saturating_sub(since))),
+            ])
+        })
+        .collect();
+    let title = match app.snapshot.queues.get(app.selected_queue) {
+        Some(q) if app.show_finished => format!(" Tasks of '{}' (all) ", q.name),
+        Some(q) => format!(" Tasks of '{}' ", q.name),
+        None => " Tasks ".to_string(),
+    };
+    let widths = [
+        Constraint::Length(10),
+        Constraint::Min(20),
+        Constraint::Length(10),
+        Constraint::Length(5),
+        Constraint::Length(8),
+        Constraint::Length(10),
+    ];
+    let table = Table::new(rows)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .widths(&widths)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(table, area, &mut app.task_table);
+}
+
+fn draw_footer<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let prompt = match &app.mode {
+        Mode::Normal => {
+            Span::raw("←/→ queue  ↑/↓ task  k kill  r run  m move  p priority  f finished  q quit")
+        }
+        Mode::ConfirmKill(task_id) => Span::styled(
+            format!("Kill task {}? (y/N)", task_id),
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        ),
+        Mode::Input { action: InputAction::Move(task_id), buffer } => {
+            Span::raw(format!("Move task {} to queue: {}_", task_id, buffer))
+        }
+        Mode::Input { action: InputAction::Priority(task_id), buffer } => {
+            Span::raw(format!("New priority of task {} (0-9): {}_", task_id, buffer))
+        }
+    };
+    let status = match &app.status {
+        Ok(msg) => Span::styled(msg.clone(), Style::default().fg(Color::Green)),
+        Err(msg) => Span::styled(msg.clone(), Style::default().fg(Color::Red)),
+    };
+    f.render_widget(Paragraph::new(vec![Spans::from(prompt), Spans::from(status)]), area);
+}
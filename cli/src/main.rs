@@ -1,10 +1,13 @@
 // src/main.rs
-use cli::AppCommand;
+use cli::App;
 use structopt::StructOpt;
 
 mod cli;
 
-fn main() -> anyhow::Result<()> {
-    let cmd = AppCommand::from_args();
-    cmd.execute()
+fn main() {
+    let app = App::from_args();
+    if let Err(e) = app.execute() {
+        eprintln!("Error: {:?}", e);
+        std::process::exit(cli::exit_code(&e));
+    }
 }
@@ -0,0 +1,52 @@
+// core/src/gpu/backend.rs
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::gpu::monitor::{GpuStats, NvmlBackend};
+use crate::gpu::simulated::SimulatedBackend;
+
+/// A source of GPU statistics.
+///
+/// The daemon only talks to GPUs through this trait, so it can run on top of
+/// NVML or on top of a simulated fixture on machines without an NVIDIA driver.
+pub trait GpuBackend: Send + Sync + Debug {
+    /// Short name used in logs and status output
+    fn name(&self) -> &str;
+
+    /// Number of devices currently visible to the backend
+    fn device_count(&self) -> Result<u32>;
+
+    /// Stats for a single device
+    fn get_stats(&self, index: u32) -> Result<GpuStats>;
+
+    /// Stats for all devices, one result per device index
+    fn get_all_stats(&self) -> Result<Vec<Result<GpuStats>>> {
+        let count = self.device_count()?;
+        Ok((0..count).map(|i| self.get_stats(i)).collect())
+    }
+}
+
+/// Which backend the daemon should use, as written in the daemon config (`gpu-backend`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GpuBackendKind {
+    #[default]
+    Nvml,
+    Simulated,
+}
+
+/// Builds the backend selected in the daemon config.
+/// `fixture` is required for the simulated backend and ignored for NVML.
+pub fn open_backend(kind: &GpuBackendKind, fixture: Option<&str>) -> Result<Arc<dyn GpuBackend>> {
+    match kind {
+        GpuBackendKind::Nvml => Ok(Arc::new(NvmlBackend::new())),
+        GpuBackendKind::Simulated => {
+            let path = fixture
+                .ok_or_else(|| anyhow!("The simulated GPU backend requires 'gpu-fixture'"))?;
+            Ok(Arc::new(SimulatedBackend::load(path)?))
+        }
+    }
+}
@@ -1,2 +1,4 @@
 // core/src/gpu/mod.rs
+pub mod backend;
 pub mod monitor;
+pub mod simulated;
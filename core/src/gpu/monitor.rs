@@ -1,8 +1,13 @@
 // core/src/gpu/monitor.rs
 use anyhow::{Context, Result};
 use bincode::{Decode, Encode};
+use log::warn;
+use nvml_wrapper::error::NvmlError;
 use nvml_wrapper::Nvml;
 use serde::{Deserialize, Serialize}; // 添加 serde 导入
+use std::sync::Mutex;
+
+use crate::gpu::backend::GpuBackend;
 
 #[derive(Debug, Clone, Encode, Decode, Serialize, Deserialize)] // Add Serialize, Deserialize
 pub struct MemoryInfo {
@@ -18,6 +23,10 @@ pub struct GpuStats {
     pub core_usage: u32,  // GPU core utilization percentage
     pub memory_usage: MemoryInfo,
     pub power_usage: u32, // Power usage in milliwatts
+    #[serde(default)]
+    pub power_limit: u32, // Enforced power limit in milliwatts, 0 if unknown
+    #[serde(default)]
+    pub name: String, // Model name, e.g. "NVIDIA A100-SXM4-80GB"
 }
 
 #[derive(Debug)]
@@ -48,13 +57,16 @@ impl GpuMonitor {
             gpu_index: index, // 将当前 GPU 的索引 index 赋值给新字段
             temperature: device
                 .temperature(nvml_wrapper::enum_wrappers::device::TemperatureSensor::Gpu)
-                .context("Failed to get temperature")? as u32,
+                .context("Failed to get temperature")?,
             core_usage: device.utilization_rates().context("Failed to get core utilization")?.gpu,
             memory_usage: {
                 let mem = device.memory_info().context("Failed to get memory info")?;
                 MemoryInfo { total: mem.total, used: mem.used, free: mem.free }
             },
-            power_usage: device.power_usage().context("Failed to get power usage")? as u32,
+            power_usage: device.power_usage().context("Failed to get power usage")?,
+            // Not every GPU reports a limit
+            power_limit: device.enforced_power_limit().unwrap_or(0),
+            name: device.name().unwrap_or_default(),
         })
     }
 
@@ -68,3 +80,84 @@ impl GpuMonitor {
         Ok(stats)
     }
 }
+
+/// NVML implementation of `GpuBackend`.
+/// One NVML handle is kept across samples. It is dropped on driver errors and NVML is
+/// initialised again on the next sample, so the daemon keeps working if the driver is
+/// loaded (or reloaded) after it started.
+#[derive(Debug, Default)]
+pub struct NvmlBackend {
+    monitor: Mutex<Option<GpuMonitor>>,
+}
+
+impl NvmlBackend {
+    pub fn new() -> Self {
+        NvmlBackend::default()
+    }
+
+    // Runs `sample` with the NVML handle, initialising NVML first if needed. The handle is
+    // dropped if `broken` says the result shows a driver error.
+    fn with_monitor<T>(
+        &self,
+        sample: impl FnOnce(&GpuMonitor) -> T,
+        broken: impl FnOnce(&T) -> bool,
+    ) -> Result<T> {
+        let mut guard = self.monitor.lock().unwrap_or_else(|e| e.into_inner());
+        let monitor = match guard.take() {
+            Some(monitor) => monitor,
+            None => GpuMonitor::new()?,
+        };
+        let result = sample(&monitor);
+        if broken(&result) {
+            warn!("NVML reported a driver error, it will be initialised again on the next sample");
+        } else {
+            *guard = Some(monitor);
+        }
+        Ok(result)
+    }
+}
+
+// Errors after which the NVML handle cannot be trusted anymore (driver unloaded, upgraded
+// or reset)
+fn needs_reinit(error: &anyhow::Error) -> bool {
+    matches!(
+        error.root_cause().downcast_ref::<NvmlError>(),
+        Some(
+            NvmlError::Uninitialized
+                | NvmlError::DriverNotLoaded
+                | NvmlError::LibRmVersionMismatch
+                | NvmlError::GpuLost
+                | NvmlError::ResetRequired
+                | NvmlError::Unknown
+        )
+    )
+}
+
+fn is_driver_error<T>(result: &Result<T>) -> bool {
+    result.as_ref().is_err_and(needs_reinit)
+}
+
+impl GpuBackend for NvmlBackend {
+    fn name(&self) -> &str {
+        "nvml"
+    }
+
+    fn device_count(&self) -> Result<u32> {
+        self.with_monitor(|monitor| monitor.device_count(), is_driver_error)?
+    }
+
+    fn get_stats(&self, index: u32) -> Result<GpuStats> {
+        self.with_monitor(|monitor| monitor.get_stats(index), is_driver_error)?
+    }
+
+    fn get_all_stats(&self) -> Result<Vec<Result<GpuStats>>> {
+        // A driver error on any device means the handle has to be initialised again
+        self.with_monitor(
+            |monitor| monitor.get_all_stats(),
+            |result| match result {
+                Ok(stats) => stats.iter().any(is_driver_error),
+                Err(e) => needs_reinit(e),
+            },
+        )?
+    }
+}
@@ -0,0 +1,251 @@
+// core/src/gpu/simulated.rs
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+
+use crate::gpu::backend::GpuBackend;
+use crate::gpu::monitor::{GpuStats, MemoryInfo};
+
+const MB: u64 = 1024 * 1024;
+
+fn default_memory_total_mb() -> u64 {
+    81920
+}
+
+fn default_temperature() -> u32 {
+    35
+}
+
+fn default_name() -> String {
+    "Simulated GPU".to_string()
+}
+
+/// Initial state of one simulated device
+#[derive(Debug, Clone, Deserialize)]
+pub struct SimulatedDevice {
+    #[serde(default = "default_name")]
+    pub name: String,
+    #[serde(default = "default_memory_total_mb")]
+    pub memory_total_mb: u64,
+    #[serde(default)]
+    pub memory_used_mb: u64,
+    #[serde(default)]
+    pub core_usage: u32, // Percentage
+    #[serde(default = "default_temperature")]
+    pub temperature: u32, // °C
+    #[serde(default)]
+    pub power_usage: u32, // Milliwatts, same unit as GpuStats
+    #[serde(default)]
+    pub power_limit: u32, // Milliwatts, 0 if unknown
+    #[serde(default)]
+    pub unavailable: bool, // Sampling this device fails, like a GPU that fell off the bus
+}
+
+/// One step of a scripted timeline. Once `at_secs` seconds have elapsed since the backend
+/// was loaded, every field that is set overrides the state of device `gpu_index`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TimelineStep {
+    pub at_secs: f64,
+    pub gpu_index: u32,
+    pub memory_used_mb: Option<u64>,
+    pub core_usage: Option<u32>,
+    pub temperature: Option<u32>,
+    pub power_usage: Option<u32>,
+    pub unavailable: Option<bool>,
+}
+
+/// Contents of a fixture file (JSON, or TOML if the file ends in `.toml`)
+#[derive(Debug, Clone, Deserialize)]
+pub struct SimulatedFixture {
+    pub devices: Vec<SimulatedDevice>,
+    #[serde(default)]
+    pub timeline: Vec<TimelineStep>,
+    /// Restart the timeline every `loop_secs` seconds
+    pub loop_secs: Option<f64>,
+}
+
+/// `GpuBackend` that serves stats from a fixture instead of real hardware
+#[derive(Debug)]
+pub struct SimulatedBackend {
+    fixture: SimulatedFixture,
+    started_at: Instant,
+}
+
+impl SimulatedBackend {
+    pub fn new(mut fixture: SimulatedFixture) -> Self {
+        fixture.timeline.sort_by(|a, b| a.at_secs.total_cmp(&b.at_secs));
+        Self { fixture, started_at: Instant::now() }
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read GPU fixture: {}", path))?;
+        let fixture: SimulatedFixture =
+            if Path::new(path).extension().is_some_and(|ext| ext == "toml") {
+                toml::from_str(&content)
+                    .with_context(|| format!("Failed to parse TOML GPU fixture: {}", path))?
+            } else {
+                serde_json::from_str(&content)
+                    .with_context(|| format!("Failed to parse JSON GPU fixture: {}", path))?
+            };
+        Ok(Self::new(fixture))
+    }
+
+    // Device state after applying every timeline step that is due
+    fn device_at(&self, index: u32, elapsed_secs: f64) -> Option<SimulatedDevice> {
+        let mut device = self.fixture.devices.get(index as usize)?.clone();
+        let elapsed = match self.fixture.loop_secs {
+            Some(period) if period > 0.0 => elapsed_secs % period,
+            _ => elapsed_secs,
+        };
+
+        for step in self.fixture.timeline.iter().filter(|s| s.gpu_index == index) {
+            if step.at_secs > elapsed {
+                break;
+            }
+            if let Some(v) = step.memory_used_mb {
+                device.memory_used_mb = v;
+            }
+            if let Some(v) = step.core_usage {
+                device.core_usage = v;
+            }
+            if let Some(v) = step.temperature {
+                device.temperature = v;
+            }
+            if let Some(v) = step.power_usage {
+                device.power_usage = v;
+            }
+            if let Some(v) = step.unavailable {
+                device.unavailable = v;
+            }
+        }
+        Some(device)
+    }
+}
+
+impl GpuBackend for SimulatedBackend {
+    fn name(&self) -> &str {
+        "simulated"
+    }
+
+    fn device_count(&self) -> Result<u32> {
+        Ok(self.fixture.devices.len() as u32)
+    }
+
+    fn get_stats(&self, index: u32) -> Result<GpuStats> {
+        let elapsed = self.started_at.elapsed().as_secs_f64();
+        let device = self
+            .device_at(index, elapsed)
+            .ok_or_else(|| anyhow!("Failed to access GPU device {}", index))?;
+        if device.unavailable {
+            return Err(anyhow!("Simulated GPU {} is unavailable", index));
+        }
+
+        let total = device.memory_total_mb * MB;
+        let used = device.memory_used_mb.min(device.memory_total_mb) * MB;
+        Ok(GpuStats {
+            gpu_index: index,
+            temperature: device.temperature,
+            core_usage: device.core_usage.min(100),
+            memory_usage: MemoryInfo { total, used, free: total - used },
+            power_usage: device.power_usage,
+            power_limit: device.power_limit,
+            name: device.name,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(memory_used_mb: u64) -> SimulatedDevice {
+        SimulatedDevice {
+            name: default_name(),
+            memory_total_mb: default_memory_total_mb(),
+            memory_used_mb,
+            core_usage: 0,
+            temperature: default_temperature(),
+            power_usage: 0,
+            power_limit: 0,
+            unavailable: false,
+        }
+    }
+
+    fn step(at_secs: f64, gpu_index: u32, memory_used_mb: u64) -> TimelineStep {
+        TimelineStep {
+            at_secs,
+            gpu_index,
+            memory_used_mb: Some(memory_used_mb),
+            core_usage: None,
+            temperature: None,
+            power_usage: None,
+            unavailable: None,
+        }
+    }
+
+    fn memory_at(backend: &SimulatedBackend, index: u32, elapsed_secs: f64) -> u64 {
+        backend.device_at(index, elapsed_secs).unwrap().memory_used_mb
+    }
+
+    #[test]
+    fn device_at_without_timeline_keeps_initial_state() {
+        let backend = SimulatedBackend::new(SimulatedFixture {
+            devices: vec![device(100), device(200)],
+            timeline: vec![],
+            loop_secs: None,
+        });
+        assert_eq!(memory_at(&backend, 0, 0.0), 100);
+        assert_eq!(memory_at(&backend, 1, 1e6), 200);
+        assert!(backend.device_at(2, 0.0).is_none());
+    }
+
+    #[test]
+    fn device_at_applies_due_steps_in_order() {
+        // Steps are listed out of order on purpose, `new` sorts them
+        let backend = SimulatedBackend::new(SimulatedFixture {
+            devices: vec![device(0), device(0)],
+            timeline: vec![step(20.0, 0, 2000), step(10.0, 0, 1000), step(5.0, 1, 500)],
+            loop_secs: None,
+        });
+        assert_eq!(memory_at(&backend, 0, 9.9), 0);
+        assert_eq!(memory_at(&backend, 0, 10.0), 1000);
+        assert_eq!(memory_at(&backend, 0, 19.9), 1000);
+        assert_eq!(memory_at(&backend, 0, 25.0), 2000);
+        // Steps only touch the device they name
+        assert_eq!(memory_at(&backend, 1, 4.0), 0);
+        assert_eq!(memory_at(&backend, 1, 25.0), 500);
+
+        // Unset fields keep their previous value
+        let device = backend.device_at(0, 25.0).unwrap();
+        assert_eq!(device.temperature, default_temperature());
+        assert!(!device.unavailable);
+    }
+
+    #[test]
+    fn device_at_wraps_around_when_looping() {
+        let backend = SimulatedBackend::new(SimulatedFixture {
+            devices: vec![device(0)],
+            timeline: vec![step(10.0, 0, 1000), step(20.0, 0, 2000)],
+            loop_secs: Some(30.0),
+        });
+        assert_eq!(memory_at(&backend, 0, 25.0), 2000);
+        assert_eq!(memory_at(&backend, 0, 30.0), 0);
+        assert_eq!(memory_at(&backend, 0, 35.0), 0);
+        assert_eq!(memory_at(&backend, 0, 42.0), 1000);
+        assert_eq!(memory_at(&backend, 0, 85.0), 2000);
+    }
+
+    #[test]
+    fn device_at_ignores_non_positive_loop_period() {
+        let backend = SimulatedBackend::new(SimulatedFixture {
+            devices: vec![device(0)],
+            timeline: vec![step(10.0, 0, 1000)],
+            loop_secs: Some(0.0),
+        });
+        assert_eq!(memory_at(&backend, 0, 100.0), 1000);
+    }
+}
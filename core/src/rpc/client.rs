@@ -0,0 +1,696 @@
+// core/src/rpc/client.rs
+// Typed client of the daemon, for the CLI and other tools that drive it programmatically
+use super::message::{
+    ArraySelector, DaemonAction, DaemonStatus, Event, EventFilter, GPUAction, LogChunk, LogRequest,
+    Message, QueueAction, RpcError, SubmitAction, TaskAction, TaskExplanation, TaskFilter,
+};
+use super::protocol::{
+    read_frame, read_frame_async, write_frame, write_frame_async, Hello, CAP_LOG_STREAM,
+    CAP_SUBSCRIBE,
+};
+use crate::gpu::monitor::GpuStats;
+use crate::utils::models::{
+    ExecContext, GpuMode, GpuRequirement, GpuShare, PlacementStrategy, QueueMeta, ResourceLimit,
+    RetryPolicy, TaskDependency, TaskMeta,
+};
+use std::io::ErrorKind;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum GavelError {
+    #[error("Failed to connect to socket {socket}: {source}")]
+    Connect { socket: String, source: std::io::Error },
+    #[error("Connection to the daemon failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to encode message: {0}")]
+    Encode(#[from] bincode::error::EncodeError),
+    #[error("Failed to decode message: {0}")]
+    Decode(#[from] bincode::error::DecodeError),
+    #[error("Received excessively large message length: {0}")]
+    MessageTooLarge(u32),
+    #[error("Version handshake with the daemon failed: {0}")]
+    Handshake(String),
+    #[error(
+        "Incompatible protocol versions: client v{client} (gavel {client_version}), \
+         daemon v{daemon} (gavel {daemon_version}). Use a client of the daemon's release."
+    )]
+    Incompatible { client: u32, client_version: String, daemon: u32, daemon_version: String },
+    #[error("The daemon (gavel {daemon_version}) does not support '{capability}'")]
+    Unsupported { capability: &'static str, daemon_version: String },
+    #[error("{0}")]
+    Daemon(RpcError), // Error reported by the daemon
+    #[error("Unexpected {0} reply from daemon")]
+    UnexpectedReply(&'static str),
+}
+
+pub type Result<T> = std::result::Result<T, GavelError>;
+
+/// Options of a single submitted task; the defaults match `gavelrs submit` without flags
+/// except for the execution context, which is empty (the daemon's own cwd and environment).
+#[derive(Debug, Clone, Default)]
+pub struct SubmitOptions {
+    pub gpus: u8,
+    pub gpu_share: Option<GpuShare>, // Per-GPU demand for sharing GPUs in shared queues
+    pub gpu_requirement: Option<GpuRequirement>, // What each GPU must offer, None for any GPU
+    pub queue: Option<String>,       // None uses the waiting queue
+    pub name: Option<String>,
+    pub dependencies: Vec<TaskDependency>,
+    pub max_runtime: Option<u64>, // Seconds, None uses the queue default
+    pub retry_policy: Option<RetryPolicy>, // None uses the queue default
+    pub exec: ExecContext,
+}
+
+/// Checks the daemon's answer to our Hello. Daemons from before the handshake existed fail
+/// to decode our Hello and hang up.
+fn check_daemon_hello(reply: Result<Hello>) -> Result<Hello> {
+    let daemon = match reply {
+        Ok(daemon) => daemon,
+        Err(GavelError::Io(e)) if e.kind() == ErrorKind::UnexpectedEof => {
+            return Err(GavelError::Handshake(format!(
+                "the daemon closed the connection; it is probably older than this client \
+                 (gavel {})",
+                env!("CARGO_PKG_VERSION")
+            )))
+        }
+        Err(e) => return Err(e),
+    };
+    Hello::current().check_daemon(&daemon)?;
+    Ok(daemon)
+}
+
+fn error_reply(reply: Message) -> Result<Message> {
+    match reply {
+        Message::Error(err) => Err(GavelError::Daemon(err)),
+        reply => Ok(reply),
+    }
+}
+
+fn reply_kind(reply: &Message) -> &'static str {
+    match reply {
+        Message::DaemonCommand(_) => "DaemonCommand",
+        Message::TaskCommand(_) => "TaskCommand",
+        Message::GPUCommand(_) => "GPUCommand",
+        Message::QueueCommand(_) => "QueueCommand",
+        Message::SubmitCommand(_) => "SubmitCommand",
+        Message::GPUStatus(_) => "GPUStatus",
+        Message::TaskStatus(_) => "TaskStatus",
+        Message::QueueStatus(_) => "QueueStatus",
+        Message::DaemonStatus(_) => "DaemonStatus",
+        Message::TaskExplanation(_) => "TaskExplanation",
+        Message::Ack(_) => "Ack",
+        Message::Error(_) => "Error",
+        Message::Subscribe(_) => "Subscribe",
+        Message::Event(_) => "Event",
+        Message::StreamLogs(_) => "StreamLogs",
+        Message::LogChunk(_) => "LogChunk",
+    }
+}
+
+fn require_capability(daemon: &Hello, capability: &'static str) -> Result<()> {
+    if daemon.supports(capability) {
+        Ok(())
+    } else {
+        Err(GavelError::Unsupported { capability, daemon_version: daemon.software_version.clone() })
+    }
+}
+
+/// Next event of a subscription; None once the daemon has closed it (e.g. on shutdown)
+fn into_event(frame: Result<Message>) -> Result<Option<Event>> {
+    match frame {
+        Ok(Message::Event(event)) => Ok(Some(event)),
+        Ok(other) => Err(GavelError::UnexpectedReply(reply_kind(&other))),
+        Err(GavelError::Io(e)) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Next chunk of a log stream; None once the daemon has ended it with an Ack
+fn into_log_chunk(frame: Message) -> Result<Option<LogChunk>> {
+    match error_reply(frame)? {
+        Message::LogChunk(chunk) => Ok(Some(chunk)),
+        Message::Ack(_) => Ok(None),
+        other => Err(GavelError::UnexpectedReply(reply_kind(&other))),
+    }
+}
+
+// Conversions of replies into typed results. List requests that match nothing are answered
+// with an Ack ("No tasks found ...") instead of an empty list.
+
+fn into_ack(reply: Message) -> Result<String> {
+    match reply {
+        Message::Ack(msg) => Ok(msg),
+        other => Err(GavelError::UnexpectedReply(reply_kind(&other))),
+    }
+}
+
+fn into_tasks(reply: Message) -> Result<Vec<TaskMeta>> {
+    match reply {
+        Message::TaskStatus(tasks) => Ok(tasks),
+        Message::Ack(_) => Ok(Vec::new()),
+        other => Err(GavelError::UnexpectedReply(reply_kind(&other))),
+    }
+}
+
+fn into_task(reply: Message) -> Result<TaskMeta> {
+    match reply {
+        Message::TaskStatus(tasks) if !tasks.is_empty() => Ok(tasks.into_iter().next().unwrap()),
+        other => Err(GavelError::UnexpectedReply(reply_kind(&other))),
+    }
+}
+
+fn into_gpus(reply: Message) -> Result<Vec<GpuStats>> {
+    match reply {
+        Message::GPUStatus(gpus) => Ok(gpus),
+        Message::Ack(_) => Ok(Vec::new()),
+        other => Err(GavelError::UnexpectedReply(reply_kind(&other))),
+    }
+}
+
+fn into_queues(reply: Message) -> Result<Vec<QueueMeta>> {
+    match reply {
+        Message::QueueStatus(queues) => Ok(queues),
+        Message::Ack(_) => Ok(Vec::new()),
+        other => Err(GavelError::UnexpectedReply(reply_kind(&other))),
+    }
+}
+
+fn into_daemon_status(reply: Message) -> Result<DaemonStatus> {
+    match reply {
+        Message::DaemonStatus(status) => Ok(status),
+        other => Err(GavelError::UnexpectedReply(reply_kind(&other))),
+    }
+}
+
+fn into_task_explanation(reply: Message) -> Result<TaskExplanation> {
+    match reply {
+        Message::TaskExplanation(explanation) => Ok(explanation),
+        other => Err(GavelError::UnexpectedReply(reply_kind(&other))),
+    }
+}
+
+fn into_queue(reply: Message) -> Result<QueueMeta> {
+    match reply {
+        Message::QueueStatus(queues) if !queues.is_empty() => {
+            Ok(queues.into_iter().next().unwrap())
+        }
+        other => Err(GavelError::UnexpectedReply(reply_kind(&other))),
+    }
+}
+
+fn submit_command_action(command: &str, options: &SubmitOptions) -> SubmitAction {
+    SubmitAction::Command {
+        command: command.to_string(),
+        gpu_num_required: options.gpus,
+        gpu_share: options.gpu_share.clone(),
+        gpu_requirement: options.gpu_requirement.clone(),
+        queue_name: options.queue.clone(),
+        name: options.name.clone(),
+        dependencies: options.dependencies.clone(),
+        max_runtime: options.max_runtime,
+        retry_policy: options.retry_policy.clone(),
+        exec: options.exec.clone(),
+    }
+}
+
+fn submit_script_action(script_path: &str, options: &SubmitOptions) -> SubmitAction {
+    SubmitAction::Script {
+        script_path: script_path.to_string(),
+        gpu_num_required: options.gpus,
+        gpu_share: options.gpu_share.clone(),
+        gpu_requirement: options.gpu_requirement.clone(),
+        queue_name: options.queue.clone(),
+        name: options.name.clone(),
+        dependencies: options.dependencies.clone(),
+        max_runtime: options.max_runtime,
+        retry_policy: options.retry_policy.clone(),
+        exec: options.exec.clone(),
+    }
+}
+
+/// Client of the daemon's Unix socket, one connection per request
+#[derive(Debug, Clone)]
+pub struct GavelClient {
+    socket_path: String,
+}
+
+/// Blocking variant of [`GavelClient`], for callers without a tokio runtime
+#[derive(Debug, Clone)]
+pub struct BlockingGavelClient {
+    socket_path: String,
+}
+
+impl GavelClient {
+    pub fn new(socket_path: impl Into<String>) -> Self {
+        Self { socket_path: socket_path.into() }
+    }
+
+    pub fn socket_path(&self) -> &str {
+        &self.socket_path
+    }
+
+    /// Connects and exchanges the version handshake
+    async fn connect(&self) -> Result<(tokio::net::UnixStream, Hello)> {
+        let mut stream = tokio::net::UnixStream::connect(&self.socket_path)
+            .await
+            .map_err(|source| GavelError::Connect { socket: self.socket_path.clone(), source })?;
+        write_frame_async(&mut stream, &Hello::current()).await?;
+        let daemon = check_daemon_hello(read_frame_async(&mut stream).await)?;
+        Ok((stream, daemon))
+    }
+
+    /// Handshake of the daemon: its protocol version, release and capabilities
+    pub async fn daemon_hello(&self) -> Result<Hello> {
+        Ok(self.connect().await?.1)
+    }
+
+    /// Sends a raw request. An Error reply is returned as `GavelError::Daemon`.
+    pub async fn request(&self, request: Message) -> Result<Message> {
+        let (mut stream, _) = self.connect().await?;
+        write_frame_async(&mut stream, &request).await?;
+        error_reply(read_frame_async(&mut stream).await?)
+    }
+
+    /// Opens an event subscription. Events arrive from the moment this returns.
+    pub async fn subscribe(&self, filter: EventFilter) -> Result<EventStream> {
+        let (mut stream, daemon) = self.connect().await?;
+        require_capability(&daemon, CAP_SUBSCRIBE)?;
+        write_frame_async(&mut stream, &Message::Subscribe(filter)).await?;
+        into_ack(error_reply(read_frame_async(&mut stream).await?)?)?;
+        Ok(EventStream { stream })
+    }
+
+    /// Streams the log of a task. Without `follow` the stream ends at the current end of the
+    /// log, otherwise once the task has finished.
+    pub async fn stream_logs(&self, request: LogRequest) -> Result<LogStream> {
+        let (mut stream, daemon) = self.connect().await?;
+        require_capability(&daemon, CAP_LOG_STREAM)?;
+        write_frame_async(&mut stream, &Message::StreamLogs(request)).await?;
+        Ok(LogStream { stream, done: false })
+    }
+}
+
+/// Events of a subscription opened with [`GavelClient::subscribe`]
+#[derive(Debug)]
+pub struct EventStream {
+    stream: tokio::net::UnixStream,
+}
+
+impl EventStream {
+    /// Waits for the next event; None once the daemon has ended the subscription
+    pub async fn next(&mut self) -> Result<Option<Event>> {
+        into_event(read_frame_async(&mut self.stream).await)
+    }
+}
+
+/// Chunks of a log stream opened with [`GavelClient::stream_logs`]
+#[derive(Debug)]
+pub struct LogStream {
+    stream: tokio::net::UnixStream,
+    done: bool,
+}
+
+impl LogStream {
+    /// Waits for the next chunk; None once the whole requested log has been sent
+    pub async fn next(&mut self) -> Result<Option<LogChunk>> {
+        if self.done {
+            return Ok(None);
+        }
+        let chunk = into_log_chunk(read_frame_async(&mut self.stream).await?);
+        self.done = !matches!(chunk, Ok(Some(_)));
+        chunk
+    }
+}
+
+impl BlockingGavelClient {
+    pub fn new(socket_path: impl Into<String>) -> Self {
+        Self { socket_path: socket_path.into() }
+    }
+
+    pub fn socket_path(&self) -> &str {
+        &self.socket_path
+    }
+
+    /// Sends a raw request. An Error reply is returned as `GavelError::Daemon`.
+    pub fn request(&self, request: Message) -> Result<Message> {
+        error_reply(self.exchange(&request)?)
+    }
+
+    /// Connects and exchanges the version handshake
+    fn connect(&self) -> Result<(std::os::unix::net::UnixStream, Hello)> {
+        let mut stream = std::os::unix::net::UnixStream::connect(&self.socket_path)
+            .map_err(|source| GavelError::Connect { socket: self.socket_path.clone(), source })?;
+        write_frame(&mut stream, &Hello::current())?;
+        let daemon = check_daemon_hello(read_frame(&mut stream))?;
+        Ok((stream, daemon))
+    }
+
+    /// Handshake of the daemon: its protocol version, release and capabilities
+    pub fn daemon_hello(&self) -> Result<Hello> {
+        Ok(self.connect()?.1)
+    }
+
+    /// Sends a request and returns whatever the daemon replied, Error replies included
+    pub(crate) fn exchange(&self, request: &Message) -> Result<Message> {
+        let (mut stream, _) = self.connect()?;
+        write_frame(&mut stream, request)?;
+        read_frame(&mut stream)
+    }
+
+    /// Opens an event subscription. Events arrive from the moment this returns.
+    pub fn subscribe(&self, filter: EventFilter) -> Result<EventIter> {
+        let (mut stream, daemon) = self.connect()?;
+        require_capability(&daemon, CAP_SUBSCRIBE)?;
+        write_frame(&mut stream, &Message::Subscribe(filter))?;
+        into_ack(error_reply(read_frame(&mut stream)?)?)?;
+        Ok(EventIter { stream, done: false })
+    }
+
+    /// Streams the log of a task. Without `follow` the stream ends at the current end of the
+    /// log, otherwise once the task has finished.
+    pub fn stream_logs(&self, request: LogRequest) -> Result<LogIter> {
+        let (mut stream, daemon) = self.connect()?;
+        require_capability(&daemon, CAP_LOG_STREAM)?;
+        write_frame(&mut stream, &Message::StreamLogs(request))?;
+        Ok(LogIter { stream, done: false })
+    }
+}
+
+/// Events of a subscription opened with [`BlockingGavelClient::subscribe`]. Iteration blocks
+/// until the next event and ends when the daemon closes the subscription or after an error.
+#[derive(Debug)]
+pub struct EventIter {
+    stream: std::os::unix::net::UnixStream,
+    done: bool,
+}
+
+impl Iterator for EventIter {
+    type Item = Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let event = into_event(read_frame(&mut self.stream)).transpose();
+        self.done = !matches!(event, Some(Ok(_)));
+        event
+    }
+}
+
+/// Chunks of a log stream opened with [`BlockingGavelClient::stream_logs`]. Iteration blocks
+/// until the next chunk and ends after the last one or after an error.
+#[derive(Debug)]
+pub struct LogIter {
+    stream: std::os::unix::net::UnixStream,
+    done: bool,
+}
+
+impl Iterator for LogIter {
+    type Item = Result<LogChunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let chunk = read_frame(&mut self.stream).and_then(into_log_chunk).transpose();
+        self.done = !matches!(chunk, Some(Ok(_)));
+        chunk
+    }
+}
+
+// Typed requests, implemented for both clients: `fn name(args) -> T { request } => conversion`
+macro_rules! client_methods {
+    ($(
+        $(#[$doc:meta])*
+        fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty { $request:expr } => $into:ident;
+    )*) => {
+        impl GavelClient {
+            $(
+                $(#[$doc])*
+                pub async fn $name(&self $(, $arg: $ty)*) -> Result<$ret> {
+                    $into(self.request($request).await?)
+                }
+            )*
+        }
+
+        impl BlockingGavelClient {
+            $(
+                $(#[$doc])*
+                pub fn $name(&self $(, $arg: $ty)*) -> Result<$ret> {
+                    $into(self.request($request)?)
+                }
+            )*
+        }
+    };
+}
+
+client_methods! {
+    /// Version, uptime and task, queue and GPU counts of the daemon
+    fn daemon_status(&self) -> DaemonStatus {
+        Message::DaemonCommand(DaemonAction::Status)
+    } => into_daemon_status;
+
+    fn stop_daemon(&self) -> String {
+        Message::DaemonCommand(DaemonAction::Stop)
+    } => into_ack;
+
+    fn submit(&self, action: SubmitAction) -> String {
+        Message::SubmitCommand(action)
+    } => into_ack;
+
+    fn submit_command(&self, command: &str, options: &SubmitOptions) -> String {
+        Message::SubmitCommand(submit_command_action(command, options))
+    } => into_ack;
+
+    fn submit_script(&self, script_path: &str, options: &SubmitOptions) -> String {
+        Message::SubmitCommand(submit_script_action(script_path, options))
+    } => into_ack;
+
+    /// Submits several tasks at once; their dependencies may refer to each other by name
+    fn submit_batch(&self, tasks: Vec<TaskMeta>, default_queue: Option<String>) -> String {
+        Message::SubmitCommand(SubmitAction::BatchJson {
+            tasks,
+            default_queue_name: default_queue,
+        })
+    } => into_ack;
+
+    fn list_tasks(&self, filter: TaskFilter) -> Vec<TaskMeta> {
+        Message::TaskCommand(TaskAction::List { filter })
+    } => into_tasks;
+
+    fn task_info(&self, task_id: u64) -> TaskMeta {
+        Message::TaskCommand(TaskAction::Info { task_id })
+    } => into_task;
+
+    /// Starts a waiting task right away
+    fn run_task(&self, task_id: u64) -> String {
+        Message::TaskCommand(TaskAction::Run { task_id })
+    } => into_ack;
+
+    /// Kills a running task, or cancels a waiting one
+    fn kill(&self, task_id: u64) -> String {
+        Message::TaskCommand(TaskAction::Kill { task_id })
+    } => into_ack;
+
+    fn remove_task(&self, task_id: u64) -> String {
+        Message::TaskCommand(TaskAction::Remove { task_id })
+    } => into_ack;
+
+    /// Tasks of an array, or of a slice of it
+    fn array_tasks(&self, array: ArraySelector) -> Vec<TaskMeta> {
+        Message::TaskCommand(TaskAction::List { filter: TaskFilter::ByArray(array) })
+    } => into_tasks;
+
+    /// Why a task is still waiting, with what each GPU of its queue lacks for it
+    fn explain_task(&self, task_id: u64) -> TaskExplanation {
+        Message::TaskCommand(TaskAction::Explain { task_id })
+    } => into_task_explanation;
+
+    /// Kills the running tasks of an array and cancels its waiting ones
+    fn kill_array(&self, array: ArraySelector) -> String {
+        Message::TaskCommand(TaskAction::KillArray { array })
+    } => into_ack;
+
+    /// Removes the tasks of an array; refused while one of them is running
+    fn remove_array(&self, array: ArraySelector) -> String {
+        Message::TaskCommand(TaskAction::RemoveArray { array })
+    } => into_ack;
+
+    fn list_gpus(&self) -> Vec<GpuStats> {
+        Message::GPUCommand(GPUAction::List)
+    } => into_gpus;
+
+    /// Stats of one GPU, or of all of them with None
+    fn gpu_info(&self, gpu_id: Option<u8>) -> Vec<GpuStats> {
+        Message::GPUCommand(GPUAction::Info { gpu_id })
+    } => into_gpus;
+
+    /// Allocates GPUs to a queue
+    fn allocate_gpus(&self, gpu_ids: Vec<u8>, queue: &str) -> String {
+        Message::GPUCommand(GPUAction::Allocate { gpu_ids, queue: queue.to_string() })
+    } => into_ack;
+
+    fn release_gpu(&self, gpu_id: u8) -> String {
+        Message::GPUCommand(GPUAction::Release { gpu_id })
+    } => into_ack;
+
+    fn ignore_gpu(&self, gpu_id: u8) -> String {
+        Message::GPUCommand(GPUAction::Ignore { gpu_id })
+    } => into_ack;
+
+    fn reset_ignored_gpus(&self) -> String {
+        Message::GPUCommand(GPUAction::ResetIgnored)
+    } => into_ack;
+
+    fn list_queues(&self) -> Vec<QueueMeta> {
+        Message::QueueCommand(QueueAction::List)
+    } => into_queues;
+
+    fn queue_status(&self, queue_name: &str) -> QueueMeta {
+        Message::QueueCommand(QueueAction::Status { queue_name: queue_name.to_string() })
+    } => into_queue;
+
+    fn create_queue(&self, name: &str, priority: u8) -> String {
+        Message::QueueCommand(QueueAction::Create { name: name.to_string(), priority })
+    } => into_ack;
+
+    /// Moves the tasks of `source` into `dest`
+    fn merge_queues(&self, source: &str, dest: &str) -> String {
+        Message::QueueCommand(QueueAction::Merge {
+            source: source.to_string(),
+            dest: dest.to_string(),
+        })
+    } => into_ack;
+
+    fn move_task(&self, task_id: u64, dest_queue: &str) -> String {
+        Message::QueueCommand(QueueAction::Move { task_id, dest_queue: dest_queue.to_string() })
+    } => into_ack;
+
+    fn set_task_priority(&self, task_id: u64, level: u8) -> String {
+        Message::QueueCommand(QueueAction::SetPriority { task_id, level })
+    } => into_ack;
+
+    fn set_array_priority(&self, array: ArraySelector, level: u8) -> String {
+        Message::QueueCommand(QueueAction::SetArrayPriority { array, level })
+    } => into_ack;
+
+    /// Whether the queue's tasks get whole GPUs or share them by declared memory
+    fn set_queue_gpu_mode(&self, queue_name: &str, mode: GpuMode) -> String {
+        Message::QueueCommand(QueueAction::SetGpuMode { queue_name: queue_name.to_string(), mode })
+    } => into_ack;
+
+    fn set_queue_max_concurrent(&self, queue_name: &str, max_concurrent: u8) -> String {
+        Message::QueueCommand(QueueAction::SetMaxConcurrent {
+            queue_name: queue_name.to_string(),
+            max_concurrent,
+        })
+    } => into_ack;
+
+    fn set_queue_placement(&self, queue_name: &str, placement: PlacementStrategy) -> String {
+        Message::QueueCommand(QueueAction::SetPlacement {
+            queue_name: queue_name.to_string(),
+            placement,
+        })
+    } => into_ack;
+
+    fn set_queue_fair_share(&self, queue_name: &str, weight: u32) -> String {
+        Message::QueueCommand(QueueAction::SetFairShare {
+            queue_name: queue_name.to_string(),
+            weight,
+        })
+    } => into_ack;
+
+    fn set_queue_resource_limit(&self, queue_name: &str, limit: ResourceLimit) -> String {
+        Message::QueueCommand(QueueAction::SetResourceLimit {
+            queue_name: queue_name.to_string(),
+            limit,
+        })
+    } => into_ack;
+
+    /// Default max runtime (seconds) of the queue's tasks, None removes it
+    fn set_queue_max_runtime(&self, queue_name: &str, max_runtime: Option<u64>) -> String {
+        Message::QueueCommand(QueueAction::SetMaxRuntime {
+            queue_name: queue_name.to_string(),
+            max_runtime,
+        })
+    } => into_ack;
+
+    /// Default retry policy of the queue's tasks, None removes it
+    fn set_queue_retry_policy(&self, queue_name: &str, policy: Option<RetryPolicy>) -> String {
+        Message::QueueCommand(QueueAction::SetRetryPolicy {
+            queue_name: queue_name.to_string(),
+            policy,
+        })
+    } => into_ack;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::protocol::PROTOCOL_VERSION;
+    use std::os::unix::net::UnixListener;
+    use std::thread;
+
+    // Runs a fake daemon that answers one handshake with `hello`, or closes the connection
+    // without answering if `hello` is None
+    fn daemon_hello_with(hello: Option<Hello>) -> Result<Hello> {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("gavel.sock");
+        let listener = UnixListener::bind(&socket_path).unwrap();
+        let daemon = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let client: Hello = read_frame(&mut stream).unwrap();
+            if let Some(hello) = hello {
+                write_frame(&mut stream, &hello).unwrap();
+            }
+            client
+        });
+
+        let reply = BlockingGavelClient::new(socket_path.to_string_lossy()).daemon_hello();
+        assert_eq!(daemon.join().unwrap(), Hello::current());
+        reply
+    }
+
+    #[test]
+    fn handshake_with_matching_daemon() {
+        let mut daemon = Hello::current();
+        daemon.software_version = "9.9.9".to_string();
+        daemon.capabilities = vec![CAP_SUBSCRIBE.to_string()];
+        let hello = daemon_hello_with(Some(daemon.clone())).unwrap();
+        assert_eq!(hello, daemon);
+        assert!(hello.supports(CAP_SUBSCRIBE));
+        assert!(!hello.supports(CAP_LOG_STREAM));
+    }
+
+    #[test]
+    fn handshake_with_other_protocol_version() {
+        let mut daemon = Hello::current();
+        daemon.protocol_version = PROTOCOL_VERSION + 1;
+        daemon.software_version = "9.9.9".to_string();
+        let err = daemon_hello_with(Some(daemon)).unwrap_err();
+        assert!(matches!(err, GavelError::Incompatible { .. }));
+        let message = err.to_string();
+        assert!(
+            message.contains(&format!(
+                "client v{} (gavel {})",
+                PROTOCOL_VERSION,
+                env!("CARGO_PKG_VERSION")
+            )),
+            "{}",
+            message
+        );
+        assert!(
+            message.contains(&format!("daemon v{} (gavel 9.9.9)", PROTOCOL_VERSION + 1)),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn handshake_with_something_else_than_gavel() {
+        let mut other = Hello::current();
+        other.magic = 0;
+        assert!(matches!(daemon_hello_with(Some(other)), Err(GavelError::Handshake(_))));
+        // Daemons from before the handshake hang up on the unknown message
+        assert!(matches!(daemon_hello_with(None), Err(GavelError::Handshake(_))));
+    }
+}
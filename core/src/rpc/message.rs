@@ -1,7 +1,15 @@
 // core/src/rpc/message.rs
 use crate::gpu::monitor::GpuStats;
-use crate::utils::models::{QueueMeta, TaskMeta};
+use crate::utils::array::format_indices;
+use crate::utils::models::{
+    ArrayParam, ExecContext, GpuMode, GpuRequirement, GpuShare, PendingReason, PlacementStrategy,
+    QueueMeta, RetryPolicy, TaskDependency, TaskMeta, TaskState,
+};
+use crate::utils::time::{format_duration_secs, now_secs};
 use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use thiserror::Error;
 /// 基础消息类型枚举
 #[derive(Encode, Decode, Debug)]
 pub enum Message {
@@ -18,8 +26,62 @@ pub enum Message {
     QueueStatus(Vec<QueueMeta>),
 
     // 系统消息
-    Ack(String),   // 操作确认
-    Error(String), // 错误响应
+    Ack(String),     // 操作确认
+    Error(RpcError), // 错误响应
+
+    // 事件订阅 (能力 "subscribe")：连接保持打开，守护进程先回复 Ack，之后逐条推送 Event
+    Subscribe(EventFilter),
+    Event(Event),
+
+    // 日志流 (能力 "log-stream")：守护进程逐块发送 LogChunk，以 Ack 结束或以 Error 中止
+    StreamLogs(LogRequest),
+    LogChunk(LogChunk),
+
+    // 新增的变体只能追加在末尾 (见 protocol.rs)
+    DaemonStatus(DaemonStatus),       // DaemonAction::Status 的回复
+    TaskExplanation(TaskExplanation), // TaskAction::Explain 的回复
+}
+
+/// 错误响应的类型，客户端据此区分错误而不必解析错误文本
+#[derive(Encode, Decode, Debug, Clone, PartialEq, Error)]
+pub enum RpcError {
+    #[error("{}", kind.not_found(id))]
+    NotFound { kind: ResourceKind, id: String }, // 任务、队列或GPU不存在
+    #[error("{0}")]
+    InvalidArgument(String), // 请求参数无效
+    #[error("{0}")]
+    Conflict(String), // 与当前状态冲突，例如删除运行中的任务
+    #[error("{0}")]
+    PermissionDenied(String), // 守护进程无权执行该操作
+    #[error("{0}")]
+    Internal(String), // 守护进程内部错误
+}
+
+impl RpcError {
+    pub fn not_found(kind: ResourceKind, id: impl ToString) -> Self {
+        RpcError::NotFound { kind, id: id.to_string() }
+    }
+}
+
+#[derive(Encode, Decode, Debug, Clone, Copy, PartialEq)]
+pub enum ResourceKind {
+    Task,
+    Queue,
+    Gpu,
+    Log,   // 任务的日志文件，id 为任务ID
+    Array, // 数组任务，id 为数组ID
+}
+
+impl ResourceKind {
+    fn not_found(&self, id: &str) -> String {
+        match self {
+            ResourceKind::Task => format!("Task with ID {} not found", id),
+            ResourceKind::Queue => format!("Queue '{}' does not exist", id),
+            ResourceKind::Gpu => format!("GPU with ID {} not found", id),
+            ResourceKind::Log => format!("Log file for task {} does not exist", id),
+            ResourceKind::Array => format!("No tasks of array {} found", id),
+        }
+    }
 }
 
 // 守护进程操作指令
@@ -37,7 +99,9 @@ pub enum TaskAction {
     Run { task_id: u64 },
     Kill { task_id: u64 },
     Remove { task_id: u64 }, // Add Remove variant
-    Logs { task_id: u64, tail: bool },
+    KillArray { array: ArraySelector }, // 终止运行中的子任务, 取消等待中的子任务
+    RemoveArray { array: ArraySelector },
+    Explain { task_id: u64 }, // 等待原因及队列中每块 GPU 的检查结果
 }
 
 // GPU操作指令
@@ -61,6 +125,13 @@ pub enum QueueAction {
     Move { task_id: u64, dest_queue: String },
     SetPriority { task_id: u64, level: u8 }, // 修正参数：针对任务而非队列
     SetResourceLimit { queue_name: String, limit: crate::utils::models::ResourceLimit },
+    SetMaxRuntime { queue_name: String, max_runtime: Option<u64> }, // 队列默认最长运行时间 (秒)
+    SetRetryPolicy { queue_name: String, policy: Option<RetryPolicy> }, // 队列默认重试策略
+    SetArrayPriority { array: ArraySelector, level: u8 },
+    SetGpuMode { queue_name: String, mode: GpuMode }, // 独占或共享 GPU
+    SetMaxConcurrent { queue_name: String, max_concurrent: u8 }, // 同时运行的任务数上限
+    SetPlacement { queue_name: String, placement: PlacementStrategy }, // 挑选 GPU 的顺序
+    SetFairShare { queue_name: String, weight: u32 }, // 公平共享权重
 }
 
 // 任务过滤条件
@@ -71,6 +142,32 @@ pub enum TaskFilter {
     Finished,
     ByQueue(String), // 按队列过滤
     ByUser(String),  // 预留用户字段
+    ByArray(ArraySelector), // 数组任务的子任务
+}
+
+/// 数组任务的全部子任务或其中一部分
+#[derive(Encode, Decode, Debug, Clone, PartialEq)]
+pub struct ArraySelector {
+    pub array_id: u64,
+    pub indices: Option<Vec<u32>>, // 子任务序号, None 表示全部
+}
+
+impl ArraySelector {
+    pub fn matches(&self, task: &TaskMeta) -> bool {
+        task.array.as_ref().is_some_and(|member| {
+            member.array_id == self.array_id
+                && self.indices.as_ref().is_none_or(|indices| indices.contains(&member.index))
+        })
+    }
+}
+
+impl fmt::Display for ArraySelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.indices {
+            Some(indices) => write!(f, "array {} [{}]", self.array_id, format_indices(indices)),
+            None => write!(f, "array {}", self.array_id),
+        }
+    }
 }
 
 #[derive(Encode, Decode, Debug, Clone, PartialEq)] // Added derive
@@ -78,18 +175,277 @@ pub enum SubmitAction {
     Command {
         command: String,
         gpu_num_required: u8,       // Use u8 consistent with other GPU counts
+        gpu_share: Option<GpuShare>, // Per-GPU demand for sharing GPUs, None for whole GPUs
+        gpu_requirement: Option<GpuRequirement>, // What each GPU must offer, None for any GPU
         queue_name: Option<String>, // Allow specifying queue
         name: Option<String>,       // Add optional name
+        dependencies: Vec<TaskDependency>,
+        max_runtime: Option<u64>, // Seconds, None uses the queue default
+        retry_policy: Option<RetryPolicy>, // None uses the queue default
+        exec: ExecContext,        // Working directory, environment and shell
     },
     Script {
         script_path: String,
         gpu_num_required: u8,       // Use u8
+        gpu_share: Option<GpuShare>, // Per-GPU demand for sharing GPUs, None for whole GPUs
+        gpu_requirement: Option<GpuRequirement>, // What each GPU must offer, None for any GPU
         queue_name: Option<String>, // Allow specifying queue
         name: Option<String>,       // Add optional name
+        dependencies: Vec<TaskDependency>,
+        max_runtime: Option<u64>, // Seconds, None uses the queue default
+        retry_policy: Option<RetryPolicy>, // None uses the queue default
+        exec: ExecContext,        // Working directory, environment and shell
     },
     BatchJson {
-        // For submitting multiple tasks from a JSON file
+        // For submitting multiple tasks from a JSON file.
+        // Dependencies may refer to other tasks of the batch by name.
         tasks: Vec<TaskMeta>,
         default_queue_name: Option<String>, // Default queue if not specified in task
     },
+    Array {
+        // Expanded by the daemon into one task per combination of the parameter values
+        command: String, // Template with {param} and {index} placeholders
+        params: Vec<ArrayParam>,
+        gpu_num_required: u8,
+        gpu_share: Option<GpuShare>,
+        gpu_requirement: Option<GpuRequirement>,
+        queue_name: Option<String>,
+        name: Option<String>, // Base name, tasks are named "<name>[<index>]"
+        dependencies: Vec<TaskDependency>, // Every task of the array waits for them
+        max_runtime: Option<u64>,
+        retry_policy: Option<RetryPolicy>,
+        exec: ExecContext,
+    },
+}
+
+/// 守护进程的运行状态
+#[derive(Encode, Decode, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub version: String, // 守护进程的 gavel 版本
+    pub pid: u32,
+    pub started_at: u64, // 启动时间 (Unix 时间戳, 秒)
+    pub tasks: TaskCounts,
+    pub queues: Vec<QueueSummary>,
+    pub gpus: GpuCounts,
+}
+
+/// 各状态的任务数
+#[derive(Encode, Decode, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TaskCounts {
+    pub total: u64,
+    pub waiting: u64,
+    pub running: u64,
+    pub finished: u64,
+    pub failed: u64,
+    pub cancelled: u64,
+    pub timed_out: u64,
+    pub retrying: u64,
+}
+
+#[derive(Encode, Decode, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueueSummary {
+    pub name: String,
+    pub priority: u8,
+    pub waiting: u64, // 等待中的任务数
+    pub running: u64, // 运行中的任务数
+    pub allocated_gpus: Vec<u8>,
+}
+
+#[derive(Encode, Decode, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GpuCounts {
+    pub total: u64,     // 检测到的 GPU, 包括被忽略的和异常的
+    pub allocated: u64, // 已分配给队列
+    pub available: u64, // 未分配、未被忽略且状态正常
+    pub ignored: u64,
+    pub unhealthy: u64, // 最近一次状态查询失败
+}
+
+/// 任务为何还在等待
+#[derive(Encode, Decode, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskExplanation {
+    pub task_id: u64,
+    pub state: TaskState,
+    pub reason: Option<PendingReason>, // 最近一轮调度记录的原因
+    pub gpus: Vec<GpuCheck>,           // 队列中每块 GPU 的检查结果, 只对等待中的 GPU 任务给出
+}
+
+/// 一块 GPU 现在能否运行该任务
+#[derive(Encode, Decode, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GpuCheck {
+    pub gpu_id: u32,
+    pub name: String,              // GPU 型号
+    pub problems: Vec<GpuProblem>, // 为空表示这块 GPU 现在可以运行该任务
+}
+
+/// GPU 不满足的条件
+#[derive(Encode, Decode, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GpuProblem {
+    Ignored,                                                        // 被 gpu ignore 排除
+    Unavailable, // 没有状态数据 (查询失败或已消失)
+    QueueMemoryLimit { free_mb: u64, required_mb: u64 }, // 空闲显存低于队列的资源限制
+    QueueUtilizationLimit { util_percent: u32, max_percent: f32 }, // 利用率超过队列的资源限制
+    InUse { task_ids: Vec<u64> }, // 被其他任务占用
+    SharedMemory { free_mb: u64, required_mb: u64 }, // 共享时剩余的声明显存不足
+    SharedUtilization { used_percent: u32, required_percent: u32 }, // 共享时剩余的算力份额不足
+    Model { model: String, required: String }, // 型号不符
+    TotalMemory { total_mb: u64, required_mb: u64 }, // 总显存不足
+    FreeMemory { free_mb: u64, required_mb: u64 }, // 空闲显存不足
+    Preempting { task_id: u64 }, // 抢占腾出的 GPU, 为其他任务保留
+    Reserved { task_id: u64, start_at: u64 }, // 回填预留给其他任务, 本任务无法在此之前结束
+}
+
+impl fmt::Display for GpuProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GpuProblem::Ignored => write!(f, "ignored"),
+            GpuProblem::Unavailable => write!(f, "no stats, the GPU failed its last query"),
+            GpuProblem::QueueMemoryLimit { free_mb, required_mb } => {
+                write!(f, "{} MB free, the queue limit asks for {} MB", free_mb, required_mb)
+            }
+            GpuProblem::QueueUtilizationLimit { util_percent, max_percent } => {
+                write!(f, "{}% utilization, the queue limit is {:.0}%", util_percent, max_percent)
+            }
+            GpuProblem::InUse { task_ids } => {
+                let ids: Vec<String> = task_ids.iter().map(|id| id.to_string()).collect();
+                write!(f, "in use by task {}", ids.join(", "))
+            }
+            GpuProblem::SharedMemory { free_mb, required_mb } => {
+                write!(f, "{} MB left to share, the task declared {} MB", free_mb, required_mb)
+            }
+            GpuProblem::SharedUtilization { used_percent, required_percent } => write!(
+                f,
+                "{}% utilization already declared, the task needs {}% more",
+                used_percent, required_percent
+            ),
+            GpuProblem::Model { model, required } => {
+                write!(f, "model {} does not match {}", model, required)
+            }
+            GpuProblem::TotalMemory { total_mb, required_mb } => {
+                write!(f, "{} MB in total, the task needs {} MB", total_mb, required_mb)
+            }
+            GpuProblem::FreeMemory { free_mb, required_mb } => {
+                write!(f, "{} MB free, the task needs {} MB", free_mb, required_mb)
+            }
+            GpuProblem::Preempting { task_id } => {
+                write!(f, "being freed by preemption for task {}", task_id)
+            }
+            GpuProblem::Reserved { task_id, start_at } => write!(
+                f,
+                "reserved for task {} starting in {}, before this task would be done",
+                task_id,
+                format_duration_secs(start_at.saturating_sub(now_secs()))
+            ),
+        }
+    }
+}
+
+/// 读取任务日志的请求
+#[derive(Encode, Decode, Debug, Clone, Default, PartialEq)]
+pub struct LogRequest {
+    pub task_id: u64,
+    pub offset: Option<u64>, // 从该字节偏移开始读取 (例如续传)，优先于 lines
+    pub lines: Option<u32>,  // 只读取最后 N 行；都为 None 时从头读取
+    pub follow: bool,        // 持续推送新输出，直到任务结束
+}
+
+/// 日志的一段内容
+#[derive(Encode, Decode, Debug, Clone, PartialEq)]
+pub struct LogChunk {
+    pub log_path: String, // 所在的日志文件，重试的任务每次运行写入不同文件
+    pub offset: u64,      // data 在该文件中的起始字节偏移
+    pub data: Vec<u8>,
+}
+
+/// 订阅的过滤条件，空列表表示不按该条件过滤
+#[derive(Encode, Decode, Debug, Clone, Default, PartialEq)]
+pub struct EventFilter {
+    pub queues: Vec<String>, // 只推送与这些队列相关的事件
+    pub task_ids: Vec<u64>,  // 只推送这些任务的事件
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &Event) -> bool {
+        if let EventKind::Lagged { .. } = event.kind {
+            return true;
+        }
+        let queue_ok = self.queues.is_empty()
+            || event.kind.queues().iter().any(|q| self.queues.iter().any(|f| f == q));
+        let task_ok = self.task_ids.is_empty()
+            || event.kind.task_id().is_some_and(|id| self.task_ids.contains(&id));
+        queue_ok && task_ok
+    }
+}
+
+/// 守护进程推送给订阅者的事件
+#[derive(Encode, Decode, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    pub time: u64, // Unix 时间戳 (秒)
+    pub kind: EventKind,
+}
+
+#[derive(Encode, Decode, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum EventKind {
+    TaskSubmitted {
+        task_id: u64,
+        name: String,
+        queue: String,
+    },
+    TaskStateChanged {
+        task_id: u64,
+        name: String,
+        queue: String,
+        from: TaskState,
+        to: TaskState,
+        reason: Option<String>, // 失败、取消或重试的原因
+    },
+    GpuAllocationChanged {
+        gpu_id: u32,
+        from: Option<String>,
+        to: Option<String>,
+    },
+    GpuHealthChanged {
+        gpu_id: u32,
+        healthy: bool,
+        queue: Option<String>,  // GPU 当前分配到的队列
+        reason: Option<String>, // 不健康的原因
+    },
+    QueueCreated {
+        name: String,
+        priority: u8,
+    },
+    QueuesMerged {
+        source: String,
+        dest: String,
+        moved_task_ids: Vec<u64>,
+    },
+    Lagged {
+        missed: u64,
+    }, // 订阅者处理太慢，丢失了这么多条事件
+}
+
+impl EventKind {
+    /// Queues the event concerns
+    pub fn queues(&self) -> Vec<&str> {
+        match self {
+            EventKind::TaskSubmitted { queue, .. } | EventKind::TaskStateChanged { queue, .. } => {
+                vec![queue]
+            }
+            EventKind::GpuAllocationChanged { from, to, .. } => {
+                from.iter().chain(to.iter()).map(|q| q.as_str()).collect()
+            }
+            EventKind::GpuHealthChanged { queue, .. } => queue.iter().map(|q| q.as_str()).collect(),
+            EventKind::QueueCreated { name, .. } => vec![name],
+            EventKind::QueuesMerged { source, dest, .. } => vec![source, dest],
+            EventKind::Lagged { .. } => Vec::new(),
+        }
+    }
+
+    /// Task the event concerns, if any
+    pub fn task_id(&self) -> Option<u64> {
+        match self {
+            EventKind::TaskSubmitted { task_id, .. }
+            | EventKind::TaskStateChanged { task_id, .. } => Some(*task_id),
+            _ => None,
+        }
+    }
 }
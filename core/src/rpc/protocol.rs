@@ -0,0 +1,182 @@
+// core/src/rpc/protocol.rs
+// Framing and version handshake of the daemon socket.
+//
+// Every connection starts with both sides sending a `Hello`, the client first. `Message` is
+// bincode-encoded positionally, so the two sides can only talk if they agree on its layout:
+//
+// - Changing how an existing message is encoded is incompatible and bumps PROTOCOL_VERSION:
+//   adding, removing or reordering fields, removing or reordering enum variants, changing
+//   field types. Clients and daemons refuse to talk across protocol versions.
+// - Appending a variant at the end of an enum (`Message` included) is the compatible change:
+//   the variants before it keep their index, so older messages decode unchanged. New variants
+//   always go at the end, never between existing ones. A new request is advertised as a
+//   capability, and clients only send it to daemons that have the capability. Replies to it
+//   may use variants appended to reply enums.
+//
+// `Hello` itself must never change, so that mismatches are always reported clearly.
+use super::client::{GavelError, Result};
+use bincode::config::standard as bincode_config;
+use bincode::{decode_from_slice, encode_to_vec, Decode, Encode};
+use std::io::{Read, Write};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Version of the `Message` wire format. Only released formats count: changes made before a
+/// release ships are part of that release's version.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Optional requests supported by this build. Clients check for them before sending them.
+pub const CAPABILITIES: &[&str] = &[CAP_SUBSCRIBE, CAP_LOG_STREAM];
+
+/// `Message::Subscribe` and the `Message::Event` stream
+pub const CAP_SUBSCRIBE: &str = "subscribe";
+
+/// `Message::StreamLogs` and the `Message::LogChunk` stream
+pub const CAP_LOG_STREAM: &str = "log-stream";
+
+/// Largest message accepted on the socket
+pub const MAX_MESSAGE_LEN: u32 = 10 * 1024 * 1024;
+
+const HELLO_MAGIC: u32 = 0x4741_564c; // "GAVL"
+
+/// First message of both sides of a connection
+#[derive(Encode, Decode, Debug, Clone, PartialEq)]
+pub struct Hello {
+    pub magic: u32,
+    pub protocol_version: u32,
+    pub software_version: String, // Version of the gavel release, for error messages
+    pub capabilities: Vec<String>,
+}
+
+impl Hello {
+    /// Hello of this build
+    pub fn current() -> Self {
+        Hello {
+            magic: HELLO_MAGIC,
+            protocol_version: PROTOCOL_VERSION,
+            software_version: env!("CARGO_PKG_VERSION").to_string(),
+            capabilities: CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+
+    /// Checks that a client sending `self` can talk to a daemon that answered `daemon`
+    pub fn check_daemon(&self, daemon: &Hello) -> Result<()> {
+        if daemon.magic != HELLO_MAGIC {
+            return Err(GavelError::Handshake(
+                "The socket did not answer with a gavel handshake".to_string(),
+            ));
+        }
+        if daemon.protocol_version != self.protocol_version {
+            return Err(GavelError::Incompatible {
+                client: self.protocol_version,
+                client_version: self.software_version.clone(),
+                daemon: daemon.protocol_version,
+                daemon_version: daemon.software_version.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether a daemon sending `self` accepts a client that sent `client`
+    pub fn accepts_client(&self, client: &Hello) -> bool {
+        client.magic == HELLO_MAGIC && client.protocol_version == self.protocol_version
+    }
+}
+
+// Frames are a u32 length (little endian) followed by the bincode encoding
+
+fn encode_frame<T: Encode>(value: &T) -> Result<Vec<u8>> {
+    let payload = encode_to_vec(value, bincode_config())?;
+    let mut frame = Vec::with_capacity(payload.len() + 4);
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(&payload);
+    Ok(frame)
+}
+
+fn frame_len(len_bytes: [u8; 4]) -> Result<usize> {
+    let len = u32::from_le_bytes(len_bytes);
+    if len > MAX_MESSAGE_LEN {
+        return Err(GavelError::MessageTooLarge(len));
+    }
+    Ok(len as usize)
+}
+
+fn decode_frame<T: Decode<()>>(buf: &[u8]) -> Result<T> {
+    let (value, _): (T, usize) = decode_from_slice(buf, bincode_config())?;
+    Ok(value)
+}
+
+pub fn write_frame<T: Encode>(stream: &mut impl Write, value: &T) -> Result<()> {
+    stream.write_all(&encode_frame(value)?)?;
+    stream.flush()?;
+    Ok(())
+}
+
+pub fn read_frame<T: Decode<()>>(stream: &mut impl Read) -> Result<T> {
+    let mut len_bytes = [0u8; 4];
+    stream.read_exact(&mut len_bytes)?;
+    let mut buf = vec![0u8; frame_len(len_bytes)?];
+    stream.read_exact(&mut buf)?;
+    decode_frame(&buf)
+}
+
+pub async fn write_frame_async<T: Encode>(
+    stream: &mut (impl AsyncWrite + Unpin),
+    value: &T,
+) -> Result<()> {
+    stream.write_all(&encode_frame(value)?).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+pub async fn read_frame_async<T: Decode<()>>(stream: &mut (impl AsyncRead + Unpin)) -> Result<T> {
+    let mut len_bytes = [0u8; 4];
+    stream.read_exact(&mut len_bytes).await?;
+    let mut buf = vec![0u8; frame_len(len_bytes)?];
+    stream.read_exact(&mut buf).await?;
+    decode_frame(&buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::message::{DaemonAction, LogRequest, Message};
+
+    #[test]
+    fn daemon_accepts_clients_of_its_protocol_version() {
+        let daemon = Hello::current();
+        let mut client = Hello::current();
+        client.software_version = "9.9.9".to_string();
+        client.capabilities.clear();
+        assert!(daemon.accepts_client(&client));
+
+        client.protocol_version = PROTOCOL_VERSION + 1;
+        assert!(!daemon.accepts_client(&client));
+        let mut other = Hello::current();
+        other.magic = 0;
+        assert!(!daemon.accepts_client(&other));
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, &Hello::current()).unwrap();
+        assert_eq!(read_frame::<Hello>(&mut buf.as_slice()).unwrap(), Hello::current());
+
+        let too_large = (MAX_MESSAGE_LEN + 1).to_le_bytes();
+        let err = read_frame::<Hello>(&mut too_large.as_slice()).unwrap_err();
+        assert!(matches!(err, GavelError::MessageTooLarge(_)));
+    }
+
+    // Variant indices are part of the wire format: moving a variant breaks older peers
+    #[test]
+    fn message_variants_keep_their_position() {
+        let index = |message: &Message| encode_to_vec(message, bincode_config()).unwrap()[0];
+        assert_eq!(index(&Message::DaemonCommand(DaemonAction::Stop)), 0);
+        assert_eq!(index(&Message::Ack(String::new())), 8);
+        assert_eq!(index(&Message::StreamLogs(LogRequest::default())), 12);
+    }
+}
@@ -0,0 +1,270 @@
+// core/src/utils/array.rs
+// Array jobs: parameter grids written by users, e.g. "lr in [1e-3,1e-4]" or "seed in 0..8",
+// command templates such as "train.py --lr {lr} --seed {seed}", and index slices like "0-3,7"
+use crate::utils::models::ArrayParam;
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+
+/// Largest number of tasks an array may expand to
+pub const MAX_ARRAY_SIZE: usize = 10_000;
+
+/// Placeholder replaced by the index of the task in its array
+pub const INDEX_PLACEHOLDER: &str = "index";
+
+/// (name, value) of each parameter for one task of an array
+pub type ParamValues = Vec<(String, String)>;
+
+/// Parses "NAME in [a,b,c]", "NAME in START..END" (END excluded) or "NAME in START..=END"
+pub fn parse_param(spec: &str) -> Result<ArrayParam> {
+    let (name, values) = spec
+        .split_once(" in ")
+        .ok_or_else(|| anyhow!("Invalid parameter '{}': expected 'NAME in VALUES'", spec))?;
+    let name = name.trim();
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(anyhow!("Invalid parameter name '{}': use letters, digits and '_'", name));
+    }
+    if name == INDEX_PLACEHOLDER {
+        return Err(anyhow!("Parameter name '{}' is reserved for the array index", name));
+    }
+
+    let values = values.trim();
+    let values: Vec<String> =
+        if let Some(list) = values.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            list.split(',').map(|v| unquote(v.trim()).to_string()).collect()
+        } else if let Some((start, end)) = values.split_once("..") {
+            let (end, inclusive) = match end.strip_prefix('=') {
+                Some(end) => (end, true),
+                None => (end, false),
+            };
+            let bound = |v: &str| {
+                v.trim().parse::<i64>().map_err(|_| anyhow!("Invalid range bound '{}'", v.trim()))
+            };
+            let (start, mut end) = (bound(start)?, bound(end)?);
+            if inclusive {
+                end = end.saturating_add(1);
+            }
+            if end.saturating_sub(start) > MAX_ARRAY_SIZE as i64 {
+                return Err(anyhow!("Range of '{}' has more than {} values", name, MAX_ARRAY_SIZE));
+            }
+            (start..end).map(|v| v.to_string()).collect()
+        } else {
+            return Err(anyhow!(
+                "Invalid values for '{}': expected a list such as [a,b] or a range such as 0..8",
+                name
+            ));
+        };
+
+    if values.is_empty() {
+        return Err(anyhow!("Parameter '{}' has no values", name));
+    }
+    if values.iter().any(|v| v.is_empty()) {
+        return Err(anyhow!("Parameter '{}' has an empty value", name));
+    }
+    Ok(ArrayParam { name: name.to_string(), values })
+}
+
+// 'a' or "a" -> a
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value.strip_prefix(quote).and_then(|v| v.strip_suffix(quote)) {
+            return inner;
+        }
+    }
+    value
+}
+
+/// Expands a command template over every combination of the parameter values, the last
+/// parameter varying fastest. Returns the command of each task with the values it got.
+pub fn expand_array(template: &str, params: &[ArrayParam]) -> Result<Vec<(String, ParamValues)>> {
+    if params.is_empty() {
+        return Err(anyhow!("An array needs at least one parameter"));
+    }
+    let mut names: Vec<&str> = Vec::new();
+    for param in params {
+        if names.contains(&param.name.as_str()) {
+            return Err(anyhow!("Parameter '{}' is given more than once", param.name));
+        }
+        if param.values.is_empty() {
+            return Err(anyhow!("Parameter '{}' has no values", param.name));
+        }
+        if !template.contains(&format!("{{{}}}", param.name)) {
+            return Err(anyhow!("Parameter '{}' is not used in the command", param.name));
+        }
+        names.push(&param.name);
+    }
+    let size = params
+        .iter()
+        .try_fold(1usize, |size, p| size.checked_mul(p.values.len()))
+        .filter(|size| *size <= MAX_ARRAY_SIZE)
+        .ok_or_else(|| anyhow!("The parameters make more than {} tasks", MAX_ARRAY_SIZE))?;
+
+    let mut tasks = Vec::with_capacity(size);
+    for index in 0..size {
+        let mut rest = index;
+        let mut values = vec![(String::new(), String::new()); params.len()];
+        for (slot, param) in values.iter_mut().zip(params).rev() {
+            *slot = (param.name.clone(), param.values[rest % param.values.len()].clone());
+            rest /= param.values.len();
+        }
+        tasks.push((render_template(template, index as u32, &values), values));
+    }
+    Ok(tasks)
+}
+
+/// Replaces `{NAME}` by the value of parameter NAME and `{index}` by the array index.
+/// Other braces, and `${...}` shell expansions, are left alone.
+pub fn render_template(template: &str, index: u32, values: &[(String, String)]) -> String {
+    let mut lookup: HashMap<&str, &str> =
+        values.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect();
+    let index = index.to_string();
+    lookup.insert(INDEX_PLACEHOLDER, &index);
+
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        let (before, from_brace) = rest.split_at(open);
+        out.push_str(before);
+        let replacement = from_brace[1..]
+            .find('}')
+            .filter(|_| !before.ends_with('$'))
+            .and_then(|close| Some((lookup.get(&from_brace[1..close + 1])?, close)));
+        match replacement {
+            Some((value, close)) => {
+                out.push_str(value);
+                rest = &from_brace[close + 2..];
+            }
+            None => {
+                out.push('{');
+                rest = &from_brace[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Parses a slice of array indices such as "3", "0-3" or "0-3,7". Returns them sorted.
+pub fn parse_indices(slice: &str) -> Result<Vec<u32>> {
+    let mut indices = Vec::new();
+    for part in slice.split(',').map(str::trim) {
+        let index = |v: &str| {
+            v.trim().parse::<u32>().map_err(|_| anyhow!("Invalid array index '{}'", v.trim()))
+        };
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (index(start)?, index(end)?);
+                if start > end {
+                    return Err(anyhow!("Invalid index range '{}': start is after end", part));
+                }
+                if (end - start) as usize >= MAX_ARRAY_SIZE {
+                    return Err(anyhow!("Index range '{}' is larger than any array", part));
+                }
+                indices.extend(start..=end);
+            }
+            None => indices.push(index(part)?),
+        }
+    }
+    indices.sort_unstable();
+    indices.dedup();
+    Ok(indices)
+}
+
+/// Formats sorted indices compactly, e.g. "0-3,7"
+pub fn format_indices(indices: &[u32]) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < indices.len() {
+        let start = indices[i];
+        while i + 1 < indices.len() && indices[i + 1] == indices[i] + 1 {
+            i += 1;
+        }
+        match indices[i] {
+            end if end == start => parts.push(start.to_string()),
+            end => parts.push(format!("{}-{}", start, end)),
+        }
+        i += 1;
+    }
+    parts.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(param: &ArrayParam) -> Vec<&str> {
+        param.values.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn parses_lists_and_ranges() {
+        let p = parse_param("lr in [1e-3, 1e-4]").unwrap();
+        assert_eq!(p.name, "lr");
+        assert_eq!(values(&p), ["1e-3", "1e-4"]);
+        assert_eq!(values(&parse_param("opt in ['adam', \"sgd\"]").unwrap()), ["adam", "sgd"]);
+        assert_eq!(values(&parse_param("seed in 0..3").unwrap()), ["0", "1", "2"]);
+        assert_eq!(values(&parse_param("seed in 0..=3").unwrap()), ["0", "1", "2", "3"]);
+        assert_eq!(values(&parse_param("x in -2..0").unwrap()), ["-2", "-1"]);
+    }
+
+    #[test]
+    fn rejects_invalid_params() {
+        assert!(parse_param("lr [1,2]").is_err());
+        assert!(parse_param("l-r in [1,2]").is_err());
+        assert!(parse_param(" in [1,2]").is_err());
+        assert!(parse_param("index in [1,2]").is_err());
+        assert!(parse_param("x in 1,2").is_err());
+        assert!(parse_param("x in []").is_err());
+        assert!(parse_param("x in [1,,2]").is_err());
+        assert!(parse_param("x in 3..3").is_err()); // Empty range
+        assert!(parse_param("x in 5..2").is_err());
+        assert!(parse_param("x in a..b").is_err());
+        assert!(parse_param(&format!("x in 0..{}", MAX_ARRAY_SIZE + 1)).is_err());
+        assert_eq!(
+            parse_param(&format!("x in 0..{}", MAX_ARRAY_SIZE)).unwrap().values.len(),
+            10_000
+        );
+    }
+
+    #[test]
+    fn expands_every_combination() {
+        let params = [parse_param("a in [1,2]").unwrap(), parse_param("b in [x,y]").unwrap()];
+        let tasks = expand_array("run {a} {b} {index}", &params).unwrap();
+        let commands: Vec<&str> = tasks.iter().map(|(cmd, _)| cmd.as_str()).collect();
+        assert_eq!(commands, ["run 1 x 0", "run 1 y 1", "run 2 x 2", "run 2 y 3"]);
+        assert_eq!(tasks[3].1, [("a".to_string(), "2".to_string()), ("b".into(), "y".into())]);
+    }
+
+    #[test]
+    fn rejects_invalid_arrays() {
+        let a = parse_param("a in [1,2]").unwrap();
+        assert!(expand_array("run", &[]).is_err());
+        assert!(expand_array("run", std::slice::from_ref(&a)).is_err()); // {a} not used
+        assert!(expand_array("run {a}", &[a.clone(), a.clone()]).is_err());
+        let big = parse_param(&format!("b in 0..{}", MAX_ARRAY_SIZE)).unwrap();
+        assert!(expand_array("run {a} {b}", &[a, big]).is_err());
+    }
+
+    #[test]
+    fn renders_only_known_placeholders() {
+        let values = [("lr".to_string(), "0.1".to_string())];
+        assert_eq!(render_template("--lr {lr} -i {index}", 7, &values), "--lr 0.1 -i 7");
+        assert_eq!(render_template("echo ${lr} {other} {", 0, &values), "echo ${lr} {other} {");
+        assert_eq!(render_template("{{lr}}", 0, &values), "{0.1}");
+        assert_eq!(render_template("awk '{print $1}' {lr}", 0, &values), "awk '{print $1}' 0.1");
+    }
+
+    #[test]
+    fn parses_and_formats_indices() {
+        assert_eq!(parse_indices("3").unwrap(), [3]);
+        assert_eq!(parse_indices("0-3,7").unwrap(), [0, 1, 2, 3, 7]);
+        assert_eq!(parse_indices("7, 2-3, 3").unwrap(), [2, 3, 7]);
+        assert_eq!(parse_indices("4-4").unwrap(), [4]);
+        assert!(parse_indices("3-1").is_err());
+        assert!(parse_indices("a").is_err());
+        assert!(parse_indices("1,").is_err());
+        assert!(parse_indices(&format!("0-{}", MAX_ARRAY_SIZE)).is_err());
+        assert_eq!(format_indices(&[0, 1, 2, 3, 7]), "0-3,7");
+        assert_eq!(format_indices(&[1, 3, 5, 6]), "1,3,5-6");
+        assert_eq!(format_indices(&[]), "");
+    }
+}
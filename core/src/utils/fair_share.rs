@@ -0,0 +1,132 @@
+// core/src/utils/fair_share.rs
+// Fair-share accounting: every queue accrues the GPU-seconds its running tasks use, decayed
+// with a half-life so that old usage counts less, and is entitled to GPU time in proportion
+// to its weight. Only queues are compared; tasks within a queue are not ordered by usage.
+use crate::utils::models::{QueueMeta, QueueUsage};
+use crate::utils::DEFAULT_WAITING_QUEUE_NAME;
+use std::collections::HashMap;
+
+/// Half-life of queue usage unless the daemon config sets another one
+pub const DEFAULT_HALF_LIFE_SECS: u64 = 24 * 3600;
+
+impl QueueUsage {
+    /// Decays the usage up to `now` and adds `gpus` GPUs held since the last update
+    pub fn accrue(&mut self, gpus: f64, now: u64, half_life_secs: u64) {
+        if self.updated_at == 0 || now < self.updated_at {
+            self.updated_at = now;
+            return;
+        }
+        let elapsed = (now - self.updated_at) as f64;
+        let decay = 0.5f64.powf(elapsed / half_life_secs.max(1) as f64);
+        self.gpu_secs = self.gpu_secs * decay + gpus * elapsed;
+        self.updated_at = now;
+    }
+}
+
+/// Usage of a queue compared with its entitlement
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShareStatus {
+    pub share: f64, // Fraction of the weights of all queues
+    pub usage: f64, // Fraction of the usage of all queues, 0 if no queue used any
+}
+
+impl ShareStatus {
+    /// Usage relative to the share: below 1 the queue got less than it is entitled to
+    pub fn ratio(&self) -> f64 {
+        if self.share > 0.0 {
+            self.usage / self.share
+        } else {
+            f64::INFINITY
+        }
+    }
+}
+
+/// Share status of every queue that runs tasks (all except the waiting queue)
+pub fn share_status(queues: &[QueueMeta]) -> HashMap<String, ShareStatus> {
+    let queues: Vec<&QueueMeta> =
+        queues.iter().filter(|q| q.name != DEFAULT_WAITING_QUEUE_NAME).collect();
+    let total_weight: f64 = queues.iter().map(|q| q.fair_share as f64).sum();
+    let total_usage: f64 = queues.iter().map(|q| q.usage.gpu_secs).sum();
+    queues
+        .iter()
+        .map(|q| {
+            let status = ShareStatus {
+                share: if total_weight > 0.0 { q.fair_share as f64 / total_weight } else { 0.0 },
+                usage: if total_usage > 0.0 { q.usage.gpu_secs / total_usage } else { 0.0 },
+            };
+            (q.name.clone(), status)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::models::{GpuMode, PlacementStrategy, ResourceLimit};
+
+    fn queue(name: &str, fair_share: u32, gpu_secs: f64) -> QueueMeta {
+        QueueMeta {
+            name: name.to_string(),
+            max_concurrent: 1,
+            priority: 0,
+            waiting_task_ids: vec![],
+            running_task_ids: vec![],
+            allocated_gpus: vec![],
+            resource_limit: ResourceLimit::default(),
+            default_max_runtime: None,
+            default_retry_policy: None,
+            gpu_mode: GpuMode::default(),
+            placement: PlacementStrategy::default(),
+            fair_share,
+            usage: QueueUsage { gpu_secs, updated_at: 1 },
+        }
+    }
+
+    #[test]
+    fn accrue_starts_the_clock_on_first_update() {
+        let mut usage = QueueUsage::default();
+        usage.accrue(2.0, 100, 10);
+        assert_eq!(usage, QueueUsage { gpu_secs: 0.0, updated_at: 100 });
+        usage.accrue(2.0, 110, DEFAULT_HALF_LIFE_SECS);
+        assert!((usage.gpu_secs - 20.0).abs() < 1e-3);
+        assert_eq!(usage.updated_at, 110);
+    }
+
+    #[test]
+    fn accrue_halves_usage_every_half_life() {
+        let mut usage = QueueUsage { gpu_secs: 80.0, updated_at: 100 };
+        usage.accrue(0.0, 110, 10);
+        assert!((usage.gpu_secs - 40.0).abs() < 1e-9);
+        usage.accrue(0.0, 130, 10);
+        assert!((usage.gpu_secs - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn accrue_ignores_clock_going_backwards() {
+        let mut usage = QueueUsage { gpu_secs: 5.0, updated_at: 100 };
+        usage.accrue(1.0, 50, 10);
+        assert_eq!(usage, QueueUsage { gpu_secs: 5.0, updated_at: 50 });
+    }
+
+    #[test]
+    fn share_status_compares_usage_with_weight() {
+        let queues = [
+            queue(DEFAULT_WAITING_QUEUE_NAME, 1, 1000.0),
+            queue("a", 1, 30.0),
+            queue("b", 3, 10.0),
+        ];
+        let shares = share_status(&queues);
+        assert_eq!(shares.len(), 2);
+        assert_eq!(shares["a"], ShareStatus { share: 0.25, usage: 0.75 });
+        assert_eq!(shares["b"], ShareStatus { share: 0.75, usage: 0.25 });
+        assert_eq!(shares["a"].ratio(), 3.0);
+        assert!(shares["b"].ratio() < 1.0);
+    }
+
+    #[test]
+    fn share_status_without_usage_or_weight() {
+        let shares = share_status(&[queue("a", 0, 0.0), queue("b", 0, 0.0)]);
+        assert_eq!(shares["a"], ShareStatus { share: 0.0, usage: 0.0 });
+        assert_eq!(shares["a"].ratio(), f64::INFINITY);
+    }
+}
@@ -12,7 +12,6 @@ impl SimpleLogger {
     pub fn init(log_file: &str, level: LevelFilter) -> Result<(), SetLoggerError> {
         let file = OpenOptions::new()
             .create(true)
-            .write(true)
             .append(true)
             .open(log_file)
             .expect("Failed to open log file");
@@ -1,6 +1,11 @@
 // core/src/gpu/mod.rs
+pub mod array;
+pub mod fair_share;
 pub mod logging;
 pub mod models;
+pub mod procfs;
+pub mod spool;
+pub mod time;
 
 // Define default queue names
 pub const DEFAULT_WAITING_QUEUE_NAME: &str = "waiting_queue";
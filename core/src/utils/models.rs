@@ -1,17 +1,22 @@
 // core/src/gpu/model.rs
+use crate::utils::time::{format_duration_secs, now_secs};
 use bincode::{Decode, Encode};
 use serde::{Deserialize, Serialize}; // 添加 serde 导入
 
-#[derive(Encode, Decode, Clone, Debug, Serialize, Deserialize, PartialEq)] // 添加 derive
+#[derive(Encode, Decode, Clone, Debug, Default, Serialize, Deserialize, PartialEq)] // 添加 derive
 pub enum TaskState {
+    #[default]
     Waiting,
     Running,
     Finished,
     Failed, // 添加 Failed 状态
+    Cancelled, // 依赖无法满足, 不会再运行
+    TimedOut,  // 超过 max_runtime 被终止
+    Retrying,  // 本次运行失败, 退避结束后重新排队
 }
 
 // 优化后的任务元数据
-#[derive(Encode, Decode, Clone, Debug, Serialize, Deserialize, PartialEq)] // 添加 derive
+#[derive(Encode, Decode, Clone, Debug, Default, Serialize, Deserialize, PartialEq)] // 添加 derive
 pub struct TaskMeta {
     pub pid: Option<i32>,
     pub id: u64,
@@ -25,6 +30,285 @@ pub struct TaskMeta {
     pub create_time: u64, // SystemTime转为时间戳
     pub gpu_ids: Vec<u8>, // 实际分配的GPU ID列表
     pub failure_reason: Option<String>, // 新增失败原因字段
+    #[serde(default)]
+    pub pid_start_time: Option<u64>, // /proc/<pid>/stat 中的启动时间, 用于重启后识别 PID 是否被复用
+    #[serde(default)]
+    pub exit_info: Option<ExitInfo>, // gavel-shim 记录的退出信息
+    #[serde(default)]
+    pub preempted: bool, // 被高优先级队列抢占: 终止中或重新排队等待, 再次启动时清除
+    #[serde(default)]
+    pub dependencies: Vec<TaskDependency>, // 依赖的任务, 全部满足后才会被调度
+    #[serde(default)]
+    pub max_runtime: Option<u64>, // 最长运行时间 (秒), None 表示使用队列默认值
+    #[serde(default)]
+    pub start_time: Option<u64>, // 最近一次开始运行的时间戳
+    #[serde(default)]
+    pub timed_out_at: Option<u64>, // 超时后发送 SIGTERM 的时间戳
+    #[serde(default)]
+    pub retry_policy: Option<RetryPolicy>, // 失败重试策略, None 表示使用队列默认值
+    #[serde(default)]
+    pub attempts: Vec<TaskAttempt>, // 已结束的各次运行
+    #[serde(default)]
+    pub retry_at: Option<u64>, // Retrying 状态下重新排队的时间戳
+    #[serde(default)]
+    pub kill_requested: bool, // 用户通过 task kill 终止, 不会被重试
+    #[serde(default)]
+    pub exec: ExecContext, // 工作目录、环境变量和 shell, 由 CLI 在提交时采集
+    #[serde(default)]
+    pub pgid: Option<i32>, // 任务的进程组 ID, 终止任务时向整个进程组发送信号
+    #[serde(default)]
+    pub array: Option<ArrayMember>, // 所属的数组任务, None 表示普通任务
+    #[serde(default)]
+    pub gpu_share: Option<GpuShare>, // 在共享模式的队列中与其他任务共用 GPU, None 表示独占 GPU
+    #[serde(default)]
+    pub gpu_requirement: Option<GpuRequirement>, // 对分配的每块 GPU 的要求, None 表示任意 GPU
+    #[serde(default)]
+    pub pending_reason: Option<PendingReason>, // 调度器每轮记录的等待原因, 离开 Waiting 时清除
+}
+
+// 等待中的任务为何还没有运行
+#[derive(Encode, Decode, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum PendingReason {
+    NotRunnable,                              // 在等待队列中, 需要 task run
+    Dependencies,                             // 依赖的任务尚未满足
+    QueueFull { max_concurrent: u8 },         // 队列同时运行的任务数已达上限
+    NoGpus,                                   // 队列没有可用的 GPU (未分配或都被忽略)
+    NeverFits { required: u8, eligible: u8 }, // 能满足任务要求的 GPU 少于所需数量
+    NotEnoughGpus { required: u8, free: u8 }, // 当前能运行该任务的 GPU 不足
+    Preempting,                               // 正在抢占低优先级队列的任务以腾出 GPU
+    Reserved { start_at: u64 },               // 已为其预留 GPU (回填), 预计开始时间
+}
+
+impl PendingReason {
+    pub fn describe(&self) -> String {
+        match self {
+            PendingReason::NotRunnable => {
+                "In the waiting queue, start it with `task run`".to_string()
+            }
+            PendingReason::Dependencies => "Waiting for its dependencies".to_string(),
+            PendingReason::QueueFull { max_concurrent } => {
+                format!("Queue is already running its maximum of {} tasks", max_concurrent)
+            }
+            PendingReason::NoGpus => "Queue has no usable GPUs".to_string(),
+            PendingReason::NeverFits { required, eligible } => format!(
+                "Needs {}, but only {} of the queue's GPUs meet its requirements",
+                gpus(*required),
+                eligible
+            ),
+            PendingReason::NotEnoughGpus { required, free } => {
+                format!("Needs {}, {} can take it now", gpus(*required), free)
+            }
+            PendingReason::Preempting => {
+                "Waiting for preempted tasks to release their GPUs".to_string()
+            }
+            PendingReason::Reserved { start_at } => format!(
+                "GPUs reserved for it, expected to be free in {}",
+                format_duration_secs(start_at.saturating_sub(now_secs()))
+            ),
+        }
+    }
+}
+
+// 共享 GPU 的任务在每块 GPU 上声明使用的资源
+#[derive(Encode, Decode, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct GpuShare {
+    pub memory_mb: u64, // 每块 GPU 上使用的显存 (MB)
+    #[serde(default)]
+    pub util_percent: Option<u8>, // 每块 GPU 上最多使用的算力份额 (%), None 表示不声明
+}
+
+impl GpuShare {
+    pub fn describe(&self) -> String {
+        match self.util_percent {
+            Some(util) => format!("{} MB, {}% utilization per GPU", self.memory_mb, util),
+            None => format!("{} MB per GPU", self.memory_mb),
+        }
+    }
+}
+
+// 任务对分配给它的每块 GPU 的要求
+#[derive(Encode, Decode, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct GpuRequirement {
+    pub memory_requirement_type: MemoryRequirementType, // 空闲显存要求的类型
+    pub memory_requirement_value: u64, // MB for AbsoluteMb, Percentage (0-100) for Percentage
+    #[serde(default)]
+    pub min_total_memory_mb: Option<u64>, // 最少总显存 (MB)
+    #[serde(default)]
+    pub model: Option<String>, // GPU 型号需包含的字符串, 不区分大小写, 如 "A100"
+}
+
+impl GpuRequirement {
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        match self.memory_requirement_type {
+            MemoryRequirementType::Ignore => {}
+            MemoryRequirementType::AbsoluteMb => {
+                parts.push(format!("free >= {} MB", self.memory_requirement_value))
+            }
+            MemoryRequirementType::Percentage => {
+                parts.push(format!("free >= {}%", self.memory_requirement_value))
+            }
+        }
+        if let Some(total) = self.min_total_memory_mb {
+            parts.push(format!("total >= {} MB", total));
+        }
+        if let Some(model) = &self.model {
+            parts.push(format!("model {}", model));
+        }
+        if parts.is_empty() {
+            return "Any GPU".to_string();
+        }
+        parts.join(", ")
+    }
+}
+
+// "1 GPU", "2 GPUs"
+fn gpus(count: u8) -> String {
+    match count {
+        1 => "1 GPU".to_string(),
+        count => format!("{} GPUs", count),
+    }
+}
+
+// 数组任务 (参数扫描) 中的一个子任务
+#[derive(Encode, Decode, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ArrayMember {
+    pub array_id: u64, // 同一数组的子任务共享
+    pub index: u32,    // 在数组中的序号, 从 0 开始
+    pub size: u32,     // 数组的子任务总数
+    #[serde(default)]
+    pub params: Vec<(String, String)>, // 本子任务的参数取值, 按提交时参数的顺序
+}
+
+// 数组任务的一个参数及其全部取值
+#[derive(Encode, Decode, Clone, Debug, PartialEq)]
+pub struct ArrayParam {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+// 任务的执行环境
+#[derive(Encode, Decode, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ExecContext {
+    #[serde(default)]
+    pub cwd: Option<String>, // 提交者的工作目录, None 表示守护进程的工作目录
+    #[serde(default)]
+    pub env: Vec<(String, String)>, // 提交者的环境变量
+    #[serde(default)]
+    pub full_env: bool, // true: env 是完整快照, 替换守护进程的环境; false: 叠加在守护进程的环境之上
+    #[serde(default)]
+    pub shell: Option<String>, // 通过 `<shell> -c <cmd>` 执行, None 表示拆分后直接执行
+}
+
+impl ExecContext {
+    /// Clears the values of the captured environment variables and keeps their names.
+    /// Task replies to clients go through this, since the values may hold credentials.
+    pub fn redact_env(&mut self) {
+        for (_, value) in &mut self.env {
+            value.clear();
+        }
+    }
+}
+
+// 失败重试策略
+#[derive(Encode, Decode, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32, // 最多运行次数, 包括第一次
+    #[serde(default)]
+    pub backoff_secs: u64, // 失败后等待多久再重新排队
+    #[serde(default)]
+    pub exit_codes: Vec<i32>, // 只在这些退出码时重试
+    #[serde(default)]
+    pub signals: Vec<i32>, // 只在被这些信号终止时重试
+}
+
+impl RetryPolicy {
+    /// Whether a run that ended with `exit_info` should be retried. Without exit code and
+    /// signal filters every failure is retried.
+    pub fn matches(&self, exit_info: &ExitInfo) -> bool {
+        if self.exit_codes.is_empty() && self.signals.is_empty() {
+            return true;
+        }
+        exit_info.exit_code.is_some_and(|code| self.exit_codes.contains(&code))
+            || exit_info.signal.is_some_and(|sig| self.signals.contains(&sig))
+    }
+}
+
+// 一次运行的记录
+#[derive(Encode, Decode, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct TaskAttempt {
+    pub attempt: u32, // 从 1 开始
+    pub log_path: String,
+    pub start_time: Option<u64>,
+    pub exit_info: Option<ExitInfo>,
+    pub state: TaskState, // 本次运行的结果
+    pub reason: Option<String>,
+}
+
+// 依赖类型: 父任务以何种结果结束后才能运行
+#[derive(Encode, Decode, Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DependencyKind {
+    AfterOk,     // 父任务成功结束
+    AfterAny,    // 父任务以任何方式结束 (包括取消)
+    AfterFailed, // 父任务失败
+}
+
+impl DependencyKind {
+    /// Checks a parent in `state`: Some(true) if the dependency is satisfied, Some(false) if
+    /// it can never be, None while the parent has not ended yet
+    pub fn check(&self, state: &TaskState) -> Option<bool> {
+        match state {
+            TaskState::Waiting | TaskState::Running | TaskState::Retrying => None,
+            TaskState::Finished => Some(*self != DependencyKind::AfterFailed),
+            TaskState::Failed | TaskState::TimedOut => Some(*self != DependencyKind::AfterOk),
+            TaskState::Cancelled => Some(*self == DependencyKind::AfterAny),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DependencyKind::AfterOk => "after-ok",
+            DependencyKind::AfterAny => "after-any",
+            DependencyKind::AfterFailed => "after-failed",
+        }
+    }
+}
+
+// 依赖边
+#[derive(Encode, Decode, Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TaskDependency {
+    pub kind: DependencyKind,
+    #[serde(default)]
+    pub task_id: u64, // 0 until `task_name` has been resolved by the daemon
+    #[serde(default)]
+    pub task_name: Option<String>, // 提交时使用的符号名称, 由守护进程解析为 task_id
+}
+
+// gavel-shim 写入状态文件的任务退出信息
+#[derive(Encode, Decode, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ExitInfo {
+    pub exit_code: Option<i32>, // Set if the process exited normally
+    pub signal: Option<i32>,    // Set if the process was terminated by a signal
+    pub user_time_ms: u64,
+    pub system_time_ms: u64,
+    pub max_rss_kb: u64,
+    pub finished_at: u64, // Unix timestamp
+}
+
+impl ExitInfo {
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    // Human readable description used as failure reason
+    pub fn describe(&self) -> String {
+        match (self.exit_code, self.signal) {
+            (Some(code), _) => format!("Process exited with code {}", code),
+            (None, Some(sig)) => format!("Process terminated by signal {}", sig),
+            (None, None) => "Process exited with unknown status".to_string(),
+        }
+    }
 }
 
 // 增强队列状态定义
@@ -37,11 +321,103 @@ pub struct QueueMeta {
     pub running_task_ids: Vec<u64>, // 存储运行中任务的 ID
     pub allocated_gpus: Vec<u8>,
     pub resource_limit: ResourceLimit, // 新增资源限制
+    #[serde(default)]
+    pub default_max_runtime: Option<u64>, // 队列中任务的默认最长运行时间 (秒)
+    #[serde(default)]
+    pub default_retry_policy: Option<RetryPolicy>, // 队列中任务的默认重试策略
+    #[serde(default)]
+    pub gpu_mode: GpuMode, // 独占 GPU, 或按声明的显存共享 GPU
+    #[serde(default)]
+    pub placement: PlacementStrategy, // 从满足要求的 GPU 中挑选的顺序
+    #[serde(default = "default_fair_share")]
+    pub fair_share: u32, // 公平共享权重, 队列按权重比例分得 GPU 时间
+    #[serde(default)]
+    pub usage: QueueUsage, // 队列近期使用的 GPU 时间
+}
+
+/// Fair-share weight of new queues
+pub const DEFAULT_FAIR_SHARE: u32 = 1;
+
+fn default_fair_share() -> u32 {
+    DEFAULT_FAIR_SHARE
+}
+
+// 队列使用的 GPU 时间, 随时间指数衰减
+#[derive(Encode, Decode, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct QueueUsage {
+    pub gpu_secs: f64,   // 衰减后的 GPU 秒数
+    pub updated_at: u64, // 上次结算的时间 (Unix 时间戳, 秒), 0 表示从未结算
+}
+
+// 队列中任务使用 GPU 的方式
+#[derive(Encode, Decode, Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub enum GpuMode {
+    #[default]
+    Exclusive, // 每块 GPU 同时只运行一个任务
+    Shared, // 声明了 gpu_share 的任务按显存和算力份额共用 GPU
+}
+
+impl GpuMode {
+    pub const NAMES: &'static [&'static str] = &["exclusive", "shared"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GpuMode::Exclusive => "exclusive",
+            GpuMode::Shared => "shared",
+        }
+    }
+}
+
+impl std::str::FromStr for GpuMode {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> anyhow::Result<Self> {
+        match value {
+            "exclusive" => Ok(GpuMode::Exclusive),
+            "shared" => Ok(GpuMode::Shared),
+            _ => Err(anyhow::anyhow!("Unknown GPU mode '{}', expected exclusive or shared", value)),
+        }
+    }
+}
+
+// 任务在满足要求的 GPU 中如何挑选
+#[derive(Encode, Decode, Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub enum PlacementStrategy {
+    #[default]
+    BestFit, // 空闲显存最少的 GPU 优先, 把大块空闲显存留给大任务
+    MostFree, // 空闲显存最多的 GPU 优先, 让任务分散
+}
+
+impl PlacementStrategy {
+    pub const NAMES: &'static [&'static str] = &["best-fit", "most-free"];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PlacementStrategy::BestFit => "best-fit",
+            PlacementStrategy::MostFree => "most-free",
+        }
+    }
+}
+
+impl std::str::FromStr for PlacementStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> anyhow::Result<Self> {
+        match value {
+            "best-fit" => Ok(PlacementStrategy::BestFit),
+            "most-free" => Ok(PlacementStrategy::MostFree),
+            _ => Err(anyhow::anyhow!(
+                "Unknown placement '{}', expected best-fit or most-free",
+                value
+            )),
+        }
+    }
 }
 
 // 新增显存要求类型枚举
-#[derive(Encode, Decode, Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Encode, Decode, Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
 pub enum MemoryRequirementType {
+    #[default]
     Ignore,
     AbsoluteMb,
     Percentage,
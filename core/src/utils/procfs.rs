@@ -0,0 +1,20 @@
+// core/src/utils/procfs.rs
+
+/// Start time of a live process (field 22 of `/proc/<pid>/stat`, in clock ticks since boot).
+/// Returns None if the process does not exist or is already a zombie.
+/// Together with the PID this identifies a process even if the PID is later reused.
+pub fn process_start_time(pid: i32) -> Option<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name (field 2) may contain spaces and parentheses, so parse after the last ')'
+    let mut fields = stat[stat.rfind(')')? + 1..].split_whitespace();
+    let state = fields.next()?; // Field 3
+    if state == "Z" || state == "X" {
+        return None;
+    }
+    fields.nth(18)?.parse().ok() // Field 22
+}
+
+/// Whether `pid` is still the process that was started at `start_time`
+pub fn is_same_process(pid: i32, start_time: u64) -> bool {
+    process_start_time(pid) == Some(start_time)
+}
@@ -0,0 +1,66 @@
+// core/src/utils/spool.rs
+// Files shared between gavel-daemon and gavel-shim in a task's spool directory
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::models::ExitInfo;
+
+pub const DEFAULT_SPOOL_DIR: &str = "/tmp/gavel_spool"; // Used unless the daemon config sets `spool-dir`
+pub const PID_FILE: &str = "pid.json"; // Written by the shim once the task has started
+pub const STATUS_FILE: &str = "status.json"; // Written by the shim once the task has exited
+
+/// Spool directory of a task under the daemon's spool root
+pub fn task_spool_dir(spool_root: &Path, task_id: u64) -> PathBuf {
+    spool_root.join(task_id.to_string())
+}
+
+/// Processes started for a task: the shim that supervises it and the task itself
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShimPidInfo {
+    pub shim_pid: i32,
+    pub shim_start_time: Option<u64>,
+    pub pid: i32,
+    pub pid_start_time: Option<u64>,
+    #[serde(default)]
+    pub pgid: Option<i32>, // Process group of the task, None for shims that did not create one
+}
+
+/// Writes `value` as JSON via a temporary file and a rename, so readers never see a partial file
+pub fn write_json_atomic<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let mut tmp_name = path.as_os_str().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+
+    let bytes = serde_json::to_vec_pretty(value).context("Failed to serialize spool file")?;
+    let mut file = fs::File::create(&tmp_path)
+        .with_context(|| format!("Failed to create {}", tmp_path.display()))?;
+    file.write_all(&bytes).with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+    file.sync_all().with_context(|| format!("Failed to sync {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("Failed to move {} into place", path.display()))?;
+    Ok(())
+}
+
+// Reads a JSON spool file, None if it does not exist (yet)
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>> {
+    match fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map(Some)
+            .with_context(|| format!("Failed to parse {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+pub fn read_pid_info(spool_dir: &Path) -> Result<Option<ShimPidInfo>> {
+    read_json(&spool_dir.join(PID_FILE))
+}
+
+pub fn read_exit_info(spool_dir: &Path) -> Result<Option<ExitInfo>> {
+    read_json(&spool_dir.join(STATUS_FILE))
+}
@@ -0,0 +1,106 @@
+// core/src/utils/time.rs
+// Durations written by users, e.g. "90", "45s", "30m", "2h", "1d", "1h30m"
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+
+/// Current Unix timestamp in seconds
+pub fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// Parses a duration into seconds. A bare number is taken as seconds.
+pub fn parse_duration_secs(input: &str) -> Result<u64> {
+    let input = input.trim();
+    if let Ok(secs) = input.parse::<u64>() {
+        return Ok(secs);
+    }
+
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return Err(anyhow!("Invalid duration '{}': unknown unit '{}'", input, c)),
+        };
+        if number.is_empty() {
+            return Err(anyhow!("Invalid duration '{}': missing number", input));
+        }
+        total = number
+            .parse::<u64>()
+            .ok()
+            .and_then(|value| value.checked_mul(unit))
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or_else(|| anyhow!("Invalid duration '{}': too large", input))?;
+        number.clear();
+    }
+    if !number.is_empty() {
+        return Err(anyhow!("Invalid duration '{}': missing unit after {}", input, number));
+    }
+    if input.is_empty() {
+        return Err(anyhow!("Empty duration"));
+    }
+    Ok(total)
+}
+
+/// Formats seconds like "1h30m" or "45s"
+pub fn format_duration_secs(secs: u64) -> String {
+    if secs == 0 {
+        return "0s".to_string();
+    }
+    let parts = [
+        (secs / 86400, "d"),
+        (secs % 86400 / 3600, "h"),
+        (secs % 3600 / 60, "m"),
+        (secs % 60, "s"),
+    ];
+    parts.iter().filter(|(v, _)| *v > 0).map(|(v, unit)| format!("{}{}", v, unit)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bare_seconds_and_units() {
+        assert_eq!(parse_duration_secs("90").unwrap(), 90);
+        assert_eq!(parse_duration_secs(" 45s ").unwrap(), 45);
+        assert_eq!(parse_duration_secs("30m").unwrap(), 1800);
+        assert_eq!(parse_duration_secs("2h").unwrap(), 7200);
+        assert_eq!(parse_duration_secs("1d").unwrap(), 86400);
+        assert_eq!(parse_duration_secs("1h30m").unwrap(), 5400);
+        assert_eq!(parse_duration_secs("1d2h3m4s").unwrap(), 93784);
+    }
+
+    #[test]
+    fn rejects_malformed_durations() {
+        assert!(parse_duration_secs("").is_err());
+        assert!(parse_duration_secs("h").is_err());
+        assert!(parse_duration_secs("10x").is_err());
+        assert!(parse_duration_secs("1h30").is_err());
+        assert!(parse_duration_secs("-5s").is_err());
+    }
+
+    #[test]
+    fn rejects_overflowing_durations() {
+        assert!(parse_duration_secs("99999999999999999d").is_err());
+        assert!(parse_duration_secs("99999999999999999999s").is_err());
+        assert!(parse_duration_secs(&format!("{}s1s", u64::MAX)).is_err());
+        assert_eq!(parse_duration_secs(&format!("{}s", u64::MAX)).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn formats_durations() {
+        assert_eq!(format_duration_secs(0), "0s");
+        assert_eq!(format_duration_secs(45), "45s");
+        assert_eq!(format_duration_secs(5400), "1h30m");
+        assert_eq!(format_duration_secs(93784), "1d2h3m4s");
+    }
+}
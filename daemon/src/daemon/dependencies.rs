@@ -0,0 +1,316 @@
+// src/daemon/dependencies.rs
+use crate::daemon::state::DaemonState;
+use anyhow::Result;
+use gavel_core::rpc::message::RpcError;
+use gavel_core::utils::models::{TaskDependency, TaskMeta, TaskState};
+use log::warn;
+use std::collections::{HashMap, HashSet};
+
+/// Where a waiting task stands with respect to its dependencies
+#[derive(Debug, Clone, PartialEq)]
+pub enum DependencyStatus {
+    Satisfied,
+    Pending,
+    Unsatisfiable(String), // Reason the task gets cancelled with
+}
+
+pub fn dependency_status(task: &TaskMeta, all_tasks: &HashMap<u64, TaskMeta>) -> DependencyStatus {
+    let mut pending = false;
+    for dep in &task.dependencies {
+        let Some(parent) = all_tasks.get(&dep.task_id) else {
+            return DependencyStatus::Unsatisfiable(format!(
+                "Dependency task {} no longer exists.",
+                dep.task_id
+            ));
+        };
+        match dep.kind.check(&parent.state) {
+            Some(true) => {}
+            Some(false) => {
+                return DependencyStatus::Unsatisfiable(format!(
+                    "{} dependency on task {} ('{}') cannot be satisfied: it ended as {:?}.",
+                    dep.kind.as_str(),
+                    parent.id,
+                    parent.name,
+                    parent.state
+                ))
+            }
+            None => pending = true,
+        }
+    }
+    if pending {
+        DependencyStatus::Pending
+    } else {
+        DependencyStatus::Satisfied
+    }
+}
+
+/// Cancels waiting tasks whose dependencies can never be satisfied anymore, e.g. the
+/// dependents of an after-ok parent that failed. Cancellation cascades down the whole chain.
+pub async fn cancel_unsatisfiable_tasks(state: &DaemonState) -> Result<()> {
+    let mut all_tasks: HashMap<u64, TaskMeta> =
+        state.get_all_tasks().await.into_iter().map(|t| (t.id, t)).collect();
+
+    loop {
+        let to_cancel: Vec<(u64, String)> = all_tasks
+            .values()
+            .filter(|t| t.state == TaskState::Waiting && !t.dependencies.is_empty())
+            .filter_map(|t| match dependency_status(t, &all_tasks) {
+                DependencyStatus::Unsatisfiable(reason) => Some((t.id, reason)),
+                _ => None,
+            })
+            .collect();
+        if to_cancel.is_empty() {
+            return Ok(());
+        }
+
+        for (task_id, reason) in to_cancel {
+            warn!("Cancelling task {}: {}", task_id, reason);
+            state.update_task_state(task_id, TaskState::Cancelled, None, Some(reason)).await?;
+            if let Some(task) = all_tasks.get_mut(&task_id) {
+                task.state = TaskState::Cancelled;
+            }
+        }
+    }
+}
+
+/// Resolves symbolic task names in `deps` into task IDs and checks that every referenced task
+/// exists. A name is first looked up in `batch` (the tasks submitted together, name -> new ID),
+/// then among existing tasks, where the most recently created task with that name wins.
+pub fn resolve_dependencies(
+    deps: &mut [TaskDependency],
+    batch: &HashMap<String, u64>,
+    existing: &[TaskMeta],
+) -> Result<()> {
+    for dep in deps.iter_mut() {
+        if let Some(name) = &dep.task_name {
+            dep.task_id = match batch.get(name) {
+                Some(id) => *id,
+                None => existing
+                    .iter()
+                    .filter(|t| &t.name == name)
+                    .max_by_key(|t| (t.create_time, t.id))
+                    .map(|t| t.id)
+                    .ok_or_else(|| {
+                        RpcError::InvalidArgument(format!(
+                            "Dependency '{}' does not match any task",
+                            name
+                        ))
+                    })?,
+            };
+        } else if !batch.values().any(|id| *id == dep.task_id)
+            && !existing.iter().any(|t| t.id == dep.task_id)
+        {
+            return Err(RpcError::InvalidArgument(format!(
+                "Dependency task {} does not exist",
+                dep.task_id
+            ))
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Returns a task of `tasks` that is part of a dependency cycle among them, if any
+pub fn find_cycle(tasks: &[TaskMeta]) -> Option<u64> {
+    let edges: HashMap<u64, Vec<u64>> =
+        tasks.iter().map(|t| (t.id, t.dependencies.iter().map(|d| d.task_id).collect())).collect();
+
+    // Depth-first search; a task reached again while still on the stack closes a cycle
+    fn visit(
+        id: u64,
+        edges: &HashMap<u64, Vec<u64>>,
+        on_stack: &mut HashSet<u64>,
+        done: &mut HashSet<u64>,
+    ) -> Option<u64> {
+        if done.contains(&id) || !edges.contains_key(&id) {
+            return None;
+        }
+        if !on_stack.insert(id) {
+            return Some(id);
+        }
+        for parent in &edges[&id] {
+            if let Some(found) = visit(*parent, edges, on_stack, done) {
+                return Some(found);
+            }
+        }
+        on_stack.remove(&id);
+        done.insert(id);
+        None
+    }
+
+    let mut on_stack = HashSet::new();
+    let mut done = HashSet::new();
+    tasks.iter().find_map(|t| visit(t.id, &edges, &mut on_stack, &mut done))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gavel_core::utils::models::DependencyKind;
+
+    fn task(id: u64, name: &str, state: TaskState, deps: &[(DependencyKind, u64)]) -> TaskMeta {
+        TaskMeta {
+            id,
+            name: name.to_string(),
+            state,
+            dependencies: deps
+                .iter()
+                .map(|(kind, task_id)| TaskDependency {
+                    kind: *kind,
+                    task_id: *task_id,
+                    task_name: None,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn by_name(kind: DependencyKind, name: &str) -> TaskDependency {
+        TaskDependency { kind, task_id: 0, task_name: Some(name.to_string()) }
+    }
+
+    // Status of a task depending on a single parent in `parent_state`
+    fn status_after(kind: DependencyKind, parent_state: TaskState) -> DependencyStatus {
+        let parent = task(1, "parent", parent_state, &[]);
+        let child = task(2, "child", TaskState::Waiting, &[(kind, 1)]);
+        dependency_status(&child, &HashMap::from([(1, parent)]))
+    }
+
+    #[test]
+    fn dependency_status_against_each_parent_state() {
+        use DependencyKind::*;
+        use DependencyStatus::*;
+
+        for state in [TaskState::Waiting, TaskState::Running, TaskState::Retrying] {
+            for kind in [AfterOk, AfterAny, AfterFailed] {
+                assert_eq!(status_after(kind, state.clone()), Pending);
+            }
+        }
+        let cases = [
+            (TaskState::Finished, [true, true, false]),
+            (TaskState::Failed, [false, true, true]),
+            (TaskState::TimedOut, [false, true, true]),
+            (TaskState::Cancelled, [false, true, false]),
+        ];
+        for (state, satisfied) in cases {
+            for (kind, satisfied) in [AfterOk, AfterAny, AfterFailed].into_iter().zip(satisfied) {
+                let status = status_after(kind, state.clone());
+                if satisfied {
+                    assert_eq!(status, Satisfied, "{:?} after {:?}", kind, state);
+                } else {
+                    assert!(
+                        matches!(status, Unsatisfiable(_)),
+                        "{:?} after {:?}: {:?}",
+                        kind,
+                        state,
+                        status
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dependency_status_waits_for_every_parent() {
+        let all_tasks = HashMap::from([
+            (1, task(1, "a", TaskState::Finished, &[])),
+            (2, task(2, "b", TaskState::Running, &[])),
+        ]);
+        let child = task(
+            3,
+            "c",
+            TaskState::Waiting,
+            &[(DependencyKind::AfterOk, 1), (DependencyKind::AfterOk, 2)],
+        );
+        assert_eq!(dependency_status(&child, &all_tasks), DependencyStatus::Pending);
+        assert_eq!(
+            dependency_status(&task(4, "d", TaskState::Waiting, &[]), &all_tasks),
+            DependencyStatus::Satisfied
+        );
+    }
+
+    #[test]
+    fn dependency_status_with_missing_parent() {
+        let child = task(2, "child", TaskState::Waiting, &[(DependencyKind::AfterAny, 1)]);
+        assert_eq!(
+            dependency_status(&child, &HashMap::new()),
+            DependencyStatus::Unsatisfiable("Dependency task 1 no longer exists.".to_string())
+        );
+    }
+
+    #[test]
+    fn find_cycle_detects_cycles() {
+        use DependencyKind::AfterOk;
+
+        let chain = [
+            task(1, "a", TaskState::Waiting, &[]),
+            task(2, "b", TaskState::Waiting, &[(AfterOk, 1)]),
+            task(3, "c", TaskState::Waiting, &[(AfterOk, 1), (AfterOk, 2)]),
+        ];
+        assert_eq!(find_cycle(&chain), None);
+
+        let two = [
+            task(1, "a", TaskState::Waiting, &[(AfterOk, 2)]),
+            task(2, "b", TaskState::Waiting, &[(AfterOk, 1)]),
+        ];
+        assert!(matches!(find_cycle(&two), Some(1 | 2)));
+
+        let three = [
+            task(1, "a", TaskState::Waiting, &[]),
+            task(2, "b", TaskState::Waiting, &[(AfterOk, 4)]),
+            task(3, "c", TaskState::Waiting, &[(AfterOk, 2)]),
+            task(4, "d", TaskState::Waiting, &[(AfterOk, 3), (AfterOk, 1)]),
+        ];
+        assert!(matches!(find_cycle(&three), Some(2..=4)));
+    }
+
+    #[test]
+    fn find_cycle_ignores_tasks_outside_the_batch() {
+        // Task 9 already exists; it cannot depend on tasks of a new batch
+        let batch = [task(1, "a", TaskState::Waiting, &[(DependencyKind::AfterOk, 9)])];
+        assert_eq!(find_cycle(&batch), None);
+    }
+
+    #[test]
+    fn resolve_dependencies_prefers_batch_then_latest_existing() {
+        let mut old = task(10, "prep", TaskState::Finished, &[]);
+        old.create_time = 100;
+        let mut new = task(11, "prep", TaskState::Finished, &[]);
+        new.create_time = 200;
+        let existing = [old, new, task(12, "train", TaskState::Running, &[])];
+
+        let mut deps = [by_name(DependencyKind::AfterOk, "prep")];
+        resolve_dependencies(&mut deps, &HashMap::new(), &existing).unwrap();
+        assert_eq!(deps[0].task_id, 11);
+
+        let batch = HashMap::from([("prep".to_string(), 20)]);
+        let mut deps = [by_name(DependencyKind::AfterOk, "prep")];
+        resolve_dependencies(&mut deps, &batch, &existing).unwrap();
+        assert_eq!(deps[0].task_id, 20);
+
+        // Plain IDs are checked against both the batch and existing tasks
+        let mut deps = [
+            TaskDependency { kind: DependencyKind::AfterAny, task_id: 12, task_name: None },
+            TaskDependency { kind: DependencyKind::AfterAny, task_id: 20, task_name: None },
+        ];
+        resolve_dependencies(&mut deps, &batch, &existing).unwrap();
+    }
+
+    #[test]
+    fn resolve_dependencies_rejects_unknown_tasks() {
+        let existing = [task(10, "prep", TaskState::Finished, &[])];
+        let batch = HashMap::from([("train".to_string(), 20)]);
+
+        let mut deps = [by_name(DependencyKind::AfterOk, "eval")];
+        let err = resolve_dependencies(&mut deps, &batch, &existing).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<RpcError>(),
+            Some(&RpcError::InvalidArgument("Dependency 'eval' does not match any task".into()))
+        );
+
+        let mut deps =
+            [TaskDependency { kind: DependencyKind::AfterOk, task_id: 30, task_name: None }];
+        let err = resolve_dependencies(&mut deps, &batch, &existing).unwrap_err();
+        assert!(matches!(err.downcast_ref::<RpcError>(), Some(RpcError::InvalidArgument(_))));
+    }
+}
@@ -0,0 +1,63 @@
+// src/daemon/events.rs
+// Live event stream of subscribed connections
+use crate::daemon::state::DaemonState;
+use anyhow::{Context, Result};
+use gavel_core::rpc::message::{Event, EventFilter, EventKind, Message};
+use gavel_core::rpc::protocol::write_frame_async;
+use gavel_core::utils::time::now_secs;
+use tokio::io::AsyncReadExt;
+use tokio::net::UnixStream;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::watch;
+
+/// Acknowledges a `Subscribe` request and writes the matching events to the connection until
+/// the client disconnects or the daemon shuts down. A subscriber that falls too far behind
+/// gets a `Lagged` event with the number of events it missed instead of blocking the daemon.
+pub async fn serve_subscription(
+    mut stream: UnixStream,
+    state: &DaemonState,
+    filter: EventFilter,
+    mut shutdown_rx: watch::Receiver<bool>,
+) -> Result<()> {
+    // Subscribe before the Ack so that no event after it is lost
+    let mut rx = state.subscribe_events();
+    let (mut reader, mut writer) = stream.split();
+    write_frame_async(&mut writer, &Message::Ack("Subscribed".to_string()))
+        .await
+        .context("Failed to acknowledge subscription")?;
+    log::info!(
+        "Client subscribed to events (queues: {:?}, tasks: {:?})",
+        filter.queues,
+        filter.task_ids
+    );
+
+    let mut buf = [0u8; 64];
+    loop {
+        tokio::select! {
+            received = rx.recv() => {
+                let event = match received {
+                    Ok(event) if filter.matches(&event) => event,
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(missed)) => {
+                        log::warn!("Event subscriber lagged behind by {} events", missed);
+                        Event { time: now_secs(), kind: EventKind::Lagged { missed } }
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                if let Err(e) = write_frame_async(&mut writer, &Message::Event(event)).await {
+                    log::debug!("Event subscriber went away: {}", e);
+                    break;
+                }
+            }
+            // Clients send nothing after subscribing, so this only notices disconnects
+            read = reader.read(&mut buf) => {
+                if matches!(read, Ok(0) | Err(_)) {
+                    break;
+                }
+            }
+            _ = shutdown_rx.changed() => break,
+        }
+    }
+    log::info!("Event subscription closed.");
+    Ok(())
+}
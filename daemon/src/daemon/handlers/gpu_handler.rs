@@ -1,7 +1,7 @@
 use crate::daemon::state::DaemonState;
 use anyhow::Result;
 use gavel_core::gpu::monitor::GpuStats;
-use gavel_core::rpc::message::{GPUAction, Message};
+use gavel_core::rpc::message::{GPUAction, Message, ResourceKind, RpcError};
 
 /// Handles GPU commands
 pub async fn handle_gpu_command(action: GPUAction, state: DaemonState) -> Result<Message> {
@@ -44,10 +44,7 @@ async fn handle_gpu_info(gpu_id: Option<u8>, state: DaemonState) -> Result<Messa
                 }
                 None => {
                     log::warn!("GPU with ID {} not found or status unavailable", id);
-                    Ok(Message::Error(format!(
-                        "GPU with ID {} not found or status unavailable",
-                        id
-                    )))
+                    Ok(Message::Error(RpcError::not_found(ResourceKind::Gpu, id)))
                 }
             }
         }
@@ -77,13 +74,15 @@ async fn handle_gpu_allocate(
 
     if gpu_ids.is_empty() {
         log::warn!("No GPU IDs provided for allocation");
-        return Ok(Message::Error("No GPU IDs provided for allocation".to_string()));
+        return Ok(Message::Error(RpcError::InvalidArgument(
+            "No GPU IDs provided for allocation".to_string(),
+        )));
     }
 
     // Check if queue exists first
     if state.get_queue(&queue).await.is_none() {
         log::warn!("Destination queue '{}' does not exist", queue);
-        return Ok(Message::Error(format!("Destination queue '{}' does not exist", queue)));
+        return Ok(Message::Error(RpcError::not_found(ResourceKind::Queue, queue)));
     }
 
     let mut errors = Vec::new();
@@ -133,32 +132,6 @@ async fn handle_gpu_allocate(
         }
     }
 
-    // Update the queue's allocated_gpus list (This logic might need refinement depending on QueueMeta structure)
-    // This part requires careful handling of the QueueMeta update, potentially needing a dedicated state method.
-    // For now, we assume set_gpu_allocation handles the persistence, but QueueMeta might need separate update.
-    // Example (conceptual - needs proper implementation in DaemonState or here):
-    /*
-    if !successes.is_empty() {
-        if let Some(mut queue_meta) = state.get_queue(&queue).await {
-            let mut state_write = state.inner.write().await; // Need write access if modifying queue directly
-            if let Some(q) = state_write.queues.get_mut(&queue) {
-                for gpu_id_str in &successes {
-                    if let Ok(gpu_id) = gpu_id_str.parse::<u32>() {
-                        if !q.allocated_gpus.contains(&(gpu_id as u8)) {
-                            q.allocated_gpus.push(gpu_id as u8);
-                        }
-                    }
-                }
-                // Persist state after modifying queue
-                if let Err(e) = state.persist().await {
-                     error!("Failed to persist state after updating queue '{}' allocations: {}", queue, e);
-                     // Decide how to handle persistence error - maybe revert changes or just log
-                }
-            }
-        }
-    }
-    */
-
     if errors.is_empty() {
         Ok(Message::Ack(format!(
             "Successfully allocated GPU(s) {} to queue '{}'",
@@ -167,18 +140,18 @@ async fn handle_gpu_allocate(
         )))
     } else {
         if successes.is_empty() {
-            Ok(Message::Error(format!(
+            Ok(Message::Error(RpcError::Conflict(format!(
                 "Failed to allocate GPUs to queue '{}': {}",
                 queue,
                 errors.join("; ")
-            )))
+            ))))
         } else {
-            Ok(Message::Error(format!(
+            Ok(Message::Error(RpcError::Conflict(format!(
                 "Partially failed to allocate to queue '{}': {}. Successfully allocated: {}",
                 queue,
                 errors.join("; "),
                 successes.join(", ")
-            )))
+            ))))
         }
     }
 }
@@ -193,7 +166,7 @@ async fn handle_gpu_release(gpu_id: u8, state: DaemonState) -> Result<Message> {
     for task in all_tasks.iter() {
         // 只处理 Running 状态且 gpu_ids 包含该 gpu_id 的任务
         if task.state == gavel_core::utils::models::TaskState::Running
-            && task.gpu_ids.contains(&(gpu_id as u8))
+            && task.gpu_ids.contains(&gpu_id)
         {
             match super::task_handler::handle_task_kill(task.id, state.clone()).await {
                 Ok(msg) => {
@@ -209,28 +182,14 @@ async fn handle_gpu_release(gpu_id: u8, state: DaemonState) -> Result<Message> {
     match state.set_gpu_allocation(gpu_id_u32, None).await {
         Ok(_) => {
             log::info!("Successfully released GPU {}", gpu_id_u32);
-
-            // Update the queue's allocated_gpus list if it was allocated
-            // Similar to allocate, this might need a dedicated state method for atomicity
-            /*
-            if let Some(queue_name) = queue_to_update {
-                 if let Some(mut queue_meta) = state.get_queue(&queue_name).await {
-                     let mut state_write = state.inner.write().await; // Need write access
-                     if let Some(q) = state_write.queues.get_mut(&queue_name) {
-                         q.allocated_gpus.retain(|&id| id != gpu_id);
-                         // Persist state after modifying queue
-                         if let Err(e) = state.persist().await {
-                             error!("Failed to persist state after updating queue '{}' allocations: {}", queue_name, e);
-                         }
-                     }
-                 }
-            }
-            */
             Ok(Message::Ack(format!("Successfully released GPU {}", gpu_id_u32)))
         }
         Err(e) => {
             log::error!("Failed to release GPU {}: {}", gpu_id_u32, e);
-            Ok(Message::Error(format!("Failed to release GPU {}: {}", gpu_id_u32, e)))
+            Ok(Message::Error(RpcError::Internal(format!(
+                "Failed to release GPU {}: {}",
+                gpu_id_u32, e
+            ))))
         }
     }
 }
@@ -247,7 +206,7 @@ async fn handle_gpu_ignore(gpu_id: u8, state: DaemonState) -> Result<Message> {
             gpu_id_u32, queue_name
         );
         log::warn!("{}", err_msg);
-        return Ok(Message::Error(err_msg));
+        return Ok(Message::Error(RpcError::Conflict(err_msg)));
     }
 
     // Perform ignore using the new state method
@@ -260,7 +219,10 @@ async fn handle_gpu_ignore(gpu_id: u8, state: DaemonState) -> Result<Message> {
         }
         Err(e) => {
             log::error!("Failed to set GPU {} to ignored state: {}", gpu_id_u32, e);
-            Ok(Message::Error(format!("Failed to set GPU {} to ignored state: {}", gpu_id_u32, e)))
+            Ok(Message::Error(RpcError::Internal(format!(
+                "Failed to set GPU {} to ignored state: {}",
+                gpu_id_u32, e
+            ))))
         }
     }
 }
@@ -299,13 +261,16 @@ async fn handle_gpu_reset_ignored(state: DaemonState) -> Result<Message> {
         Ok(Message::Ack(format!("Successfully unignored GPUs: {}", successes.join(", "))))
     } else {
         if successes.is_empty() {
-            Ok(Message::Error(format!("Failed to unignore GPUs: {}", errors.join("; "))))
+            Ok(Message::Error(RpcError::Internal(format!(
+                "Failed to unignore GPUs: {}",
+                errors.join("; ")
+            ))))
         } else {
-            Ok(Message::Error(format!(
+            Ok(Message::Error(RpcError::Internal(format!(
                 "Partially failed to unignore GPUs: {}. Successfully unignored: {}",
                 errors.join("; "),
                 successes.join(", ")
-            )))
+            ))))
         }
     }
 }
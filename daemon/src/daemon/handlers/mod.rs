@@ -12,7 +12,9 @@ pub use task_handler::handle_task_command; // Export submit handler
 
 use crate::daemon::DaemonState;
 use anyhow::Result;
-use gavel_core::utils::models::{QueueMeta, ResourceLimit};
+use gavel_core::utils::models::{
+    GpuMode, PlacementStrategy, QueueMeta, QueueUsage, ResourceLimit, DEFAULT_FAIR_SHARE,
+};
 use gavel_core::utils::{DEFAULT_RUNNING_QUEUE_NAME, DEFAULT_WAITING_QUEUE_NAME};
 
 // Function to ensure default queues exist
@@ -27,6 +29,12 @@ pub async fn ensure_default_queues_exist(state: &DaemonState) -> Result<()> {
             waiting_task_ids: Vec::new(),
             running_task_ids: Vec::new(),
             resource_limit: ResourceLimit::default(),
+            default_max_runtime: None,
+            default_retry_policy: None,
+            gpu_mode: GpuMode::default(),
+            placement: PlacementStrategy::default(),
+            fair_share: DEFAULT_FAIR_SHARE,
+            usage: QueueUsage::default(),
         };
         state.add_queue(waiting_queue).await?;
         log::info!("Created default waiting queue: {}", DEFAULT_WAITING_QUEUE_NAME);
@@ -48,6 +56,12 @@ pub async fn ensure_default_queues_exist(state: &DaemonState) -> Result<()> {
             waiting_task_ids: Vec::new(),
             running_task_ids: Vec::new(),
             resource_limit: ResourceLimit::default(),
+            default_max_runtime: None,
+            default_retry_policy: None,
+            gpu_mode: GpuMode::default(),
+            placement: PlacementStrategy::default(),
+            fair_share: DEFAULT_FAIR_SHARE,
+            usage: QueueUsage::default(),
         };
         state.add_queue(running_queue).await?;
         log::info!(
@@ -1,7 +1,13 @@
+use crate::daemon::handlers::task_handler::select_array_tasks;
 use crate::daemon::state::DaemonState;
 use anyhow::Result;
-use gavel_core::rpc::message::{Message, QueueAction};
-use gavel_core::utils::models::{QueueMeta, ResourceLimit};
+use gavel_core::rpc::message::{
+    ArraySelector, EventKind, Message, QueueAction, ResourceKind, RpcError,
+};
+use gavel_core::utils::models::{
+    GpuMode, PlacementStrategy, QueueMeta, QueueUsage, ResourceLimit, RetryPolicy,
+    DEFAULT_FAIR_SHARE,
+};
 
 /// Handles queue commands
 pub async fn handle_queue_command(action: QueueAction, state: DaemonState) -> Result<Message> {
@@ -19,6 +25,27 @@ pub async fn handle_queue_command(action: QueueAction, state: DaemonState) -> Re
         QueueAction::SetResourceLimit { queue_name, limit } => {
             handle_queue_set_limit(queue_name, limit, state).await
         }
+        QueueAction::SetMaxRuntime { queue_name, max_runtime } => {
+            handle_queue_set_max_runtime(queue_name, max_runtime, state).await
+        }
+        QueueAction::SetRetryPolicy { queue_name, policy } => {
+            handle_queue_set_retry_policy(queue_name, policy, state).await
+        }
+        QueueAction::SetArrayPriority { array, level } => {
+            handle_array_priority(array, level, state).await
+        }
+        QueueAction::SetGpuMode { queue_name, mode } => {
+            handle_queue_set_gpu_mode(queue_name, mode, state).await
+        }
+        QueueAction::SetMaxConcurrent { queue_name, max_concurrent } => {
+            handle_queue_set_max_concurrent(queue_name, max_concurrent, state).await
+        }
+        QueueAction::SetPlacement { queue_name, placement } => {
+            handle_queue_set_placement(queue_name, placement, state).await
+        }
+        QueueAction::SetFairShare { queue_name, weight } => {
+            handle_queue_set_fair_share(queue_name, weight, state).await
+        }
     }
 }
 
@@ -47,7 +74,7 @@ async fn handle_queue_status(queue_name: String, state: DaemonState) -> Result<M
         }
         None => {
             log::warn!("Queue with name '{}' not found", queue_name);
-            Ok(Message::Error(format!("Queue with name '{}' not found", queue_name)))
+            Ok(Message::Error(RpcError::not_found(ResourceKind::Queue, queue_name)))
         }
     }
 }
@@ -59,17 +86,19 @@ async fn handle_queue_merge(source: String, dest: String, state: DaemonState) ->
     // Check if source and destination queues exist
     if state.get_queue(&source).await.is_none() {
         log::warn!("Source queue '{}' does not exist", source);
-        return Ok(Message::Error(format!("Source queue '{}' does not exist", source)));
+        return Ok(Message::Error(RpcError::not_found(ResourceKind::Queue, source)));
     }
 
     if state.get_queue(&dest).await.is_none() {
         log::warn!("Destination queue '{}' does not exist", dest);
-        return Ok(Message::Error(format!("Destination queue '{}' does not exist", dest)));
+        return Ok(Message::Error(RpcError::not_found(ResourceKind::Queue, dest)));
     }
 
     if source == dest {
         log::warn!("Source and destination queues cannot be the same");
-        return Ok(Message::Error("Source and destination queues cannot be the same".to_string()));
+        return Ok(Message::Error(RpcError::InvalidArgument(
+            "Source and destination queues cannot be the same".to_string(),
+        )));
     }
 
     // Get all tasks and filter those in the source queue
@@ -79,11 +108,13 @@ async fn handle_queue_merge(source: String, dest: String, state: DaemonState) ->
 
     // Move tasks one by one using the state method
     let mut moved_count = 0;
+    let mut moved_task_ids = Vec::new();
     let mut errors = Vec::new();
     for task_id in &tasks_to_move {
         match state.update_task_queue(*task_id, dest.clone()).await {
             Ok(_) => {
                 moved_count += 1;
+                moved_task_ids.push(*task_id);
                 log::debug!("Moved task ID: {}", task_id);
             }
             Err(e) => {
@@ -93,6 +124,12 @@ async fn handle_queue_merge(source: String, dest: String, state: DaemonState) ->
         }
     }
 
+    state.publish(EventKind::QueuesMerged {
+        source: source.clone(),
+        dest: dest.clone(),
+        moved_task_ids,
+    });
+
     // Log results
     if moved_count == 0 && errors.is_empty() {
         log::info!("No tasks needed to be moved from queue '{}'", source);
@@ -124,13 +161,13 @@ async fn handle_queue_merge(source: String, dest: String, state: DaemonState) ->
             )))
         }
     } else {
-        Ok(Message::Error(format!(
+        Ok(Message::Error(RpcError::Internal(format!(
             "Failed to move some tasks from '{}' to '{}'. Moved: {}. Errors: {}",
             source,
             dest,
             moved_count,
             errors.join("; ")
-        )))
+        ))))
     }
 }
 
@@ -141,7 +178,7 @@ async fn handle_queue_create(name: String, priority: u8, state: DaemonState) ->
     // Check if queue already exists
     if state.get_queue(&name).await.is_some() {
         log::warn!("Queue '{}' already exists", name);
-        return Ok(Message::Error(format!("Queue '{}' already exists", name)));
+        return Ok(Message::Error(RpcError::Conflict(format!("Queue '{}' already exists", name))));
     }
 
     // 创建队列元数据
@@ -153,6 +190,12 @@ async fn handle_queue_create(name: String, priority: u8, state: DaemonState) ->
         running_task_ids: Vec::new(),
         allocated_gpus: Vec::new(),
         resource_limit: ResourceLimit::default(), // 使用 Default trait 初始化
+        default_max_runtime: None,
+        default_retry_policy: None,
+        gpu_mode: GpuMode::default(),
+        placement: PlacementStrategy::default(),
+        fair_share: DEFAULT_FAIR_SHARE,
+        usage: QueueUsage::default(),
     };
 
     // 添加新队列
@@ -166,7 +209,10 @@ async fn handle_queue_create(name: String, priority: u8, state: DaemonState) ->
         }
         Err(e) => {
             log::error!("Failed to create queue '{}': {}", name, e);
-            Ok(Message::Error(format!("Failed to create queue '{}': {}", name, e)))
+            Ok(Message::Error(RpcError::Internal(format!(
+                "Failed to create queue '{}': {}",
+                name, e
+            ))))
         }
     }
 }
@@ -188,14 +234,14 @@ async fn handle_queue_move(
         Some(t) => t,
         None => {
             log::warn!("Task with ID {} not found", task_id);
-            return Ok(Message::Error(format!("Task with ID {} not found", task_id)));
+            return Ok(Message::Error(RpcError::not_found(ResourceKind::Task, task_id)));
         }
     };
 
     // Check if destination queue exists
     if state.get_queue(&dest_queue).await.is_none() {
         log::warn!("Destination queue '{}' does not exist", dest_queue);
-        return Ok(Message::Error(format!("Destination queue '{}' does not exist", dest_queue)));
+        return Ok(Message::Error(RpcError::not_found(ResourceKind::Queue, dest_queue)));
     }
 
     let source_queue = task.queue.clone();
@@ -222,7 +268,10 @@ async fn handle_queue_move(
         }
         Err(e) => {
             log::error!("Failed to move task {}: {}", task_id, e);
-            Ok(Message::Error(format!("Failed to move task {}: {}", task_id, e)))
+            Ok(Message::Error(RpcError::Internal(format!(
+                "Failed to move task {}: {}",
+                task_id, e
+            ))))
         }
     }
 }
@@ -234,7 +283,10 @@ async fn handle_task_priority(task_id: u64, level: u8, state: DaemonState) -> Re
     // Check if priority level is valid (0-9)
     if level > 9 {
         log::warn!("Invalid priority level {}, must be 0-9", level);
-        return Ok(Message::Error(format!("Invalid priority level {}, must be 0-9", level)));
+        return Ok(Message::Error(RpcError::InvalidArgument(format!(
+            "Invalid priority level {}, must be 0-9",
+            level
+        ))));
     }
 
     // Get the current priority before updating
@@ -242,7 +294,7 @@ async fn handle_task_priority(task_id: u64, level: u8, state: DaemonState) -> Re
         Some(task) => task.priority,
         None => {
             log::warn!("Task with ID {} not found", task_id);
-            return Ok(Message::Error(format!("Task with ID {} not found", task_id)));
+            return Ok(Message::Error(RpcError::not_found(ResourceKind::Task, task_id)));
         }
     };
 
@@ -262,9 +314,49 @@ async fn handle_task_priority(task_id: u64, level: u8, state: DaemonState) -> Re
         }
         Err(e) => {
             log::error!("Failed to update priority for task {}: {}", task_id, e);
-            Ok(Message::Error(format!("Failed to update priority for task {}: {}", task_id, e)))
+            Ok(Message::Error(RpcError::Internal(format!(
+                "Failed to update priority for task {}: {}",
+                task_id, e
+            ))))
+        }
+    }
+}
+
+/// Handles the task priority command for an array
+async fn handle_array_priority(
+    array: ArraySelector,
+    level: u8,
+    state: DaemonState,
+) -> Result<Message> {
+    log::info!("Handling task priority command for {}, priority level: {}", array, level);
+
+    if level > 9 {
+        log::warn!("Invalid priority level {}, must be 0-9", level);
+        return Ok(Message::Error(RpcError::InvalidArgument(format!(
+            "Invalid priority level {}, must be 0-9",
+            level
+        ))));
+    }
+    let tasks = match select_array_tasks(&array, &state).await {
+        Ok(tasks) => tasks,
+        Err(e) => return Ok(Message::Error(e)),
+    };
+
+    let count = tasks.len();
+    for task in tasks {
+        if let Err(e) = state.update_task_priority(task.id, level).await {
+            log::error!("Failed to update priority for task {}: {}", task.id, e);
+            return Ok(Message::Error(RpcError::Internal(format!(
+                "Failed to update priority for task {} of {}: {}",
+                task.id, array, e
+            ))));
         }
     }
+    log::info!("Successfully set priority {} on {} task(s) of {}", level, count, array);
+    Ok(Message::Ack(format!(
+        "Successfully set priority {} on {} task(s) of {}",
+        level, count, array
+    )))
 }
 
 async fn handle_queue_set_limit(
@@ -273,6 +365,10 @@ async fn handle_queue_set_limit(
     state: DaemonState,
 ) -> Result<Message> {
     log::info!("Handling set resource limit for queue: {}, limit: {:?}", queue_name, limit);
+    if state.get_queue(&queue_name).await.is_none() {
+        log::warn!("Queue '{}' does not exist", queue_name);
+        return Ok(Message::Error(RpcError::not_found(ResourceKind::Queue, queue_name)));
+    }
     match state.update_queue_resource_limit(queue_name.clone(), limit).await {
         Ok(_) => {
             log::info!("Successfully set resource limit for queue '{}'", queue_name);
@@ -280,10 +376,206 @@ async fn handle_queue_set_limit(
         }
         Err(e) => {
             log::error!("Failed to set resource limit for queue '{}': {}", queue_name, e);
-            Ok(Message::Error(format!(
+            Ok(Message::Error(RpcError::Internal(format!(
                 "Failed to set resource limit for queue '{}': {}",
                 queue_name, e
+            ))))
+        }
+    }
+}
+
+/// Handles setting the default max runtime of a queue
+async fn handle_queue_set_max_runtime(
+    queue_name: String,
+    max_runtime: Option<u64>,
+    state: DaemonState,
+) -> Result<Message> {
+    log::info!(
+        "Handling set max runtime for queue: {}, max runtime: {:?}",
+        queue_name,
+        max_runtime
+    );
+    if state.get_queue(&queue_name).await.is_none() {
+        log::warn!("Queue '{}' does not exist", queue_name);
+        return Ok(Message::Error(RpcError::not_found(ResourceKind::Queue, queue_name)));
+    }
+    match state.update_queue_max_runtime(queue_name.clone(), max_runtime).await {
+        Ok(_) => {
+            log::info!("Successfully set max runtime for queue '{}'", queue_name);
+            Ok(Message::Ack(format!(
+                "Successfully set default max runtime for queue '{}'",
+                queue_name
+            )))
+        }
+        Err(e) => {
+            log::error!("Failed to set max runtime for queue '{}': {}", queue_name, e);
+            Ok(Message::Error(RpcError::Internal(format!(
+                "Failed to set default max runtime for queue '{}': {}",
+                queue_name, e
+            ))))
+        }
+    }
+}
+
+/// Handles setting the default retry policy of a queue
+async fn handle_queue_set_retry_policy(
+    queue_name: String,
+    policy: Option<RetryPolicy>,
+    state: DaemonState,
+) -> Result<Message> {
+    log::info!("Handling set retry policy for queue: {}, policy: {:?}", queue_name, policy);
+    if state.get_queue(&queue_name).await.is_none() {
+        log::warn!("Queue '{}' does not exist", queue_name);
+        return Ok(Message::Error(RpcError::not_found(ResourceKind::Queue, queue_name)));
+    }
+    match state.update_queue_retry_policy(queue_name.clone(), policy).await {
+        Ok(_) => {
+            log::info!("Successfully set retry policy for queue '{}'", queue_name);
+            Ok(Message::Ack(format!(
+                "Successfully set default retry policy for queue '{}'",
+                queue_name
+            )))
+        }
+        Err(e) => {
+            log::error!("Failed to set retry policy for queue '{}': {}", queue_name, e);
+            Ok(Message::Error(RpcError::Internal(format!(
+                "Failed to set default retry policy for queue '{}': {}",
+                queue_name, e
+            ))))
+        }
+    }
+}
+
+/// Handles switching a queue between exclusive and shared GPUs
+async fn handle_queue_set_gpu_mode(
+    queue_name: String,
+    mode: GpuMode,
+    state: DaemonState,
+) -> Result<Message> {
+    log::info!("Handling set GPU mode for queue: {}, mode: {:?}", queue_name, mode);
+    if state.get_queue(&queue_name).await.is_none() {
+        log::warn!("Queue '{}' does not exist", queue_name);
+        return Ok(Message::Error(RpcError::not_found(ResourceKind::Queue, queue_name)));
+    }
+    match state.update_queue_gpu_mode(queue_name.clone(), mode).await {
+        Ok(_) => {
+            log::info!("Successfully set GPU mode of queue '{}' to {}", queue_name, mode.as_str());
+            Ok(Message::Ack(format!(
+                "Queue '{}' now uses GPUs in {} mode",
+                queue_name,
+                mode.as_str()
+            )))
+        }
+        Err(e) => {
+            log::error!("Failed to set GPU mode of queue '{}': {}", queue_name, e);
+            Ok(Message::Error(RpcError::Internal(format!(
+                "Failed to set GPU mode of queue '{}': {}",
+                queue_name, e
+            ))))
+        }
+    }
+}
+
+/// Handles setting how many tasks of a queue may run at the same time
+async fn handle_queue_set_max_concurrent(
+    queue_name: String,
+    max_concurrent: u8,
+    state: DaemonState,
+) -> Result<Message> {
+    log::info!(
+        "Handling set max concurrent for queue: {}, max concurrent: {}",
+        queue_name,
+        max_concurrent
+    );
+    if max_concurrent == 0 {
+        return Ok(Message::Error(RpcError::InvalidArgument(
+            "Max concurrent tasks must be at least 1".to_string(),
+        )));
+    }
+    if state.get_queue(&queue_name).await.is_none() {
+        log::warn!("Queue '{}' does not exist", queue_name);
+        return Ok(Message::Error(RpcError::not_found(ResourceKind::Queue, queue_name)));
+    }
+    match state.update_queue_max_concurrent(queue_name.clone(), max_concurrent).await {
+        Ok(_) => {
+            log::info!("Successfully set max concurrent of queue '{}'", queue_name);
+            Ok(Message::Ack(format!(
+                "Queue '{}' now runs up to {} tasks at a time",
+                queue_name, max_concurrent
+            )))
+        }
+        Err(e) => {
+            log::error!("Failed to set max concurrent of queue '{}': {}", queue_name, e);
+            Ok(Message::Error(RpcError::Internal(format!(
+                "Failed to set max concurrent of queue '{}': {}",
+                queue_name, e
+            ))))
+        }
+    }
+}
+
+async fn handle_queue_set_placement(
+    queue_name: String,
+    placement: PlacementStrategy,
+    state: DaemonState,
+) -> Result<Message> {
+    log::info!("Handling set placement for queue: {}, placement: {:?}", queue_name, placement);
+    if state.get_queue(&queue_name).await.is_none() {
+        log::warn!("Queue '{}' does not exist", queue_name);
+        return Ok(Message::Error(RpcError::not_found(ResourceKind::Queue, queue_name)));
+    }
+    match state.update_queue_placement(queue_name.clone(), placement).await {
+        Ok(_) => {
+            log::info!(
+                "Successfully set placement of queue '{}' to {}",
+                queue_name,
+                placement.as_str()
+            );
+            Ok(Message::Ack(format!(
+                "Queue '{}' now uses {} placement",
+                queue_name,
+                placement.as_str()
+            )))
+        }
+        Err(e) => {
+            log::error!("Failed to set placement of queue '{}': {}", queue_name, e);
+            Ok(Message::Error(RpcError::Internal(format!(
+                "Failed to set placement of queue '{}': {}",
+                queue_name, e
+            ))))
+        }
+    }
+}
+
+async fn handle_queue_set_fair_share(
+    queue_name: String,
+    weight: u32,
+    state: DaemonState,
+) -> Result<Message> {
+    log::info!("Handling set fair share for queue: {}, weight: {}", queue_name, weight);
+    if weight == 0 {
+        return Ok(Message::Error(RpcError::InvalidArgument(
+            "Fair-share weight must be at least 1".to_string(),
+        )));
+    }
+    if state.get_queue(&queue_name).await.is_none() {
+        log::warn!("Queue '{}' does not exist", queue_name);
+        return Ok(Message::Error(RpcError::not_found(ResourceKind::Queue, queue_name)));
+    }
+    match state.update_queue_fair_share(queue_name.clone(), weight).await {
+        Ok(_) => {
+            log::info!("Successfully set fair-share weight of queue '{}'", queue_name);
+            Ok(Message::Ack(format!(
+                "Queue '{}' now has a fair-share weight of {}",
+                queue_name, weight
             )))
         }
+        Err(e) => {
+            log::error!("Failed to set fair-share weight of queue '{}': {}", queue_name, e);
+            Ok(Message::Error(RpcError::Internal(format!(
+                "Failed to set fair-share weight of queue '{}': {}",
+                queue_name, e
+            ))))
+        }
     }
 }
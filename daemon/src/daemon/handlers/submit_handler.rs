@@ -1,28 +1,19 @@
-use crate::daemon::state::DaemonState;
+use crate::daemon::dependencies::{find_cycle, resolve_dependencies};
+use crate::daemon::state::{DaemonState, MAX_SUBMITTED_TASK_ID};
 use anyhow::Result; // Import anyhow
-use gavel_core::rpc::message::{Message, SubmitAction};
-use gavel_core::utils::models::{TaskMeta, TaskState}; // Import TaskMeta and TaskState
+use gavel_core::rpc::message::{Message, RpcError, SubmitAction};
+use gavel_core::utils::array;
+use gavel_core::utils::models::{ArrayMember, TaskMeta, TaskState}; // Import TaskMeta and TaskState
 use gavel_core::utils::DEFAULT_WAITING_QUEUE_NAME;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf; // For log path
 use std::sync::atomic::{AtomicU64, Ordering}; // For atomic counter
-use std::time::{SystemTime, UNIX_EPOCH}; // For generating task IDs and timestamps // Import default waiting queue name
+use std::time::{SystemTime, UNIX_EPOCH}; // For timestamps // Import default waiting queue name
 const DEFAULT_LOG_DIR: &str = "/tmp/gavel_logs"; // Define a default log directory
 
 // Counter for default task names
 static DEFAULT_TASK_NAME_COUNTER: AtomicU64 = AtomicU64::new(1);
 
-// Helper function to generate a unique task ID (shorter)
-fn generate_task_id() -> u64 {
-    // Use seconds modulo 1,000,000 for a 6-digit ID
-    // Add pid to add some variance, although pid is not guaranteed unique over time
-    let secs_part =
-        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() % 1_000_000;
-    // Simple counter fallback if pid is not available or 0
-    static ID_COUNTER: AtomicU64 = AtomicU64::new(0);
-    let counter_part = ID_COUNTER.fetch_add(1, Ordering::Relaxed) % 100; // Add a small counter part
-    (secs_part * 100) + counter_part // Combine them, still aiming for ~6-7 digits
-}
-
 // Helper function to generate log path
 fn generate_log_path(task_id: u64) -> Result<String> {
     let log_dir = PathBuf::from(DEFAULT_LOG_DIR);
@@ -38,15 +29,28 @@ fn generate_default_task_name() -> String {
 
 pub async fn handle_submit_command(action: SubmitAction, state: DaemonState) -> Result<Message> {
     match action {
-        SubmitAction::Command { command, gpu_num_required, queue_name, name } => {
+        SubmitAction::Command {
+            command,
+            gpu_num_required,
+            gpu_share,
+            gpu_requirement,
+            queue_name,
+            name,
+            mut dependencies,
+            max_runtime,
+            retry_policy,
+            exec,
+        } => {
             log::info!(
-                "Handling SubmitCommand::Command: cmd={}, gpus={}, queue={:?}, name={:?}",
+                "Handling SubmitCommand::Command: cmd={}, gpus={}, queue={:?}, name={:?}, deps={:?}",
                 command,
                 gpu_num_required,
                 queue_name,
-                name
+                name,
+                dependencies
             );
-            let task_id = generate_task_id();
+            resolve_dependencies(&mut dependencies, &HashMap::new(), &state.get_all_tasks().await)?;
+            let task_id = state.next_task_id().await?;
             let log_path = generate_log_path(task_id)?;
             // Use provided queue_name, or default to DEFAULT_WAITING_QUEUE_NAME if None
             let queue = queue_name.unwrap_or_else(|| DEFAULT_WAITING_QUEUE_NAME.to_string());
@@ -67,7 +71,14 @@ pub async fn handle_submit_command(action: SubmitAction, state: DaemonState) ->
                     .unwrap_or_default()
                     .as_secs(),
                 gpu_ids: Vec::new(),
-                failure_reason: None
+                failure_reason: None,
+                dependencies,
+                max_runtime,
+                retry_policy,
+                exec,
+                gpu_share,
+                gpu_requirement,
+                ..Default::default()
             };
             state.add_task(task).await?;
             log::info!("Command task {} ('{}') submitted to queue '{}'", task_id, task_name, queue);
@@ -76,27 +87,48 @@ pub async fn handle_submit_command(action: SubmitAction, state: DaemonState) ->
                 task_id, task_name, queue
             )))
         }
-        SubmitAction::Script { script_path, gpu_num_required, queue_name, name } => {
+        SubmitAction::Script {
+            script_path,
+            gpu_num_required,
+            gpu_share,
+            gpu_requirement,
+            queue_name,
+            name,
+            mut dependencies,
+            max_runtime,
+            retry_policy,
+            mut exec,
+        } => {
             log::info!(
-                "Handling SubmitCommand::Script: path={}, gpus={}, queue={:?}, name={:?}",
+                "Handling SubmitCommand::Script: path={}, gpus={}, queue={:?}, name={:?}, deps={:?}",
                 script_path,
                 gpu_num_required,
                 queue_name,
-                name
+                name,
+                dependencies
             );
-            let task_id = generate_task_id();
+            resolve_dependencies(&mut dependencies, &HashMap::new(), &state.get_all_tasks().await)?;
+            let task_id = state.next_task_id().await?;
             let log_path = generate_log_path(task_id)?;
             // Use provided queue_name, or default to DEFAULT_WAITING_QUEUE_NAME if None
             let queue = queue_name.unwrap_or_else(|| DEFAULT_WAITING_QUEUE_NAME.to_string());
             let task_name = name.unwrap_or_else(generate_default_task_name); // Use provided name or generate default
-                                                                             // Assuming the command to run the script is simply the path itself
-                                                                             // Adjust if a specific interpreter (like bash, python) is needed
-            let command = script_path.clone();
+            // The script is executed directly, or passed to the chosen interpreter
+            let command = match exec.shell.take() {
+                Some(interpreter) => shlex::try_join([interpreter.as_str(), script_path.as_str()])
+                    .map_err(|e| {
+                        RpcError::InvalidArgument(format!(
+                            "Invalid script path or interpreter: {}",
+                            e
+                        ))
+                    })?,
+                None => script_path.clone(),
+            };
             let task = TaskMeta {
                 pid: None,
                 id: task_id,
                 name: task_name.clone(), // Assign name
-                cmd: command,            // Use script path as command for now
+                cmd: command,            // Script path, prefixed by the interpreter if any
                 gpu_require: gpu_num_required,
                 state: TaskState::Waiting,
                 log_path,
@@ -107,7 +139,14 @@ pub async fn handle_submit_command(action: SubmitAction, state: DaemonState) ->
                     .unwrap_or_default()
                     .as_secs(),
                 gpu_ids: Vec::new(),
-                failure_reason: None
+                failure_reason: None,
+                dependencies,
+                max_runtime,
+                retry_policy,
+                exec,
+                gpu_share,
+                gpu_requirement,
+                ..Default::default()
             };
             state.add_task(task).await?;
             log::info!("Script task {} ('{}') submitted to queue '{}'", task_id, task_name, queue);
@@ -116,6 +155,84 @@ pub async fn handle_submit_command(action: SubmitAction, state: DaemonState) ->
                 task_id, task_name, script_path, queue
             )))
         }
+        SubmitAction::Array {
+            command,
+            params,
+            gpu_num_required,
+            gpu_share,
+            gpu_requirement,
+            queue_name,
+            name,
+            mut dependencies,
+            max_runtime,
+            retry_policy,
+            exec,
+        } => {
+            log::info!(
+                "Handling SubmitCommand::Array: cmd={}, params={:?}, gpus={}, queue={:?}, name={:?}",
+                command,
+                params,
+                gpu_num_required,
+                queue_name,
+                name
+            );
+            let expanded = array::expand_array(&command, &params)
+                .map_err(|e| RpcError::InvalidArgument(format!("{:#}", e)))?;
+            resolve_dependencies(&mut dependencies, &HashMap::new(), &state.get_all_tasks().await)?;
+            let array_id = state.next_task_id().await?;
+            let queue = queue_name.unwrap_or_else(|| DEFAULT_WAITING_QUEUE_NAME.to_string());
+            let base_name = name.unwrap_or_else(generate_default_task_name);
+            let size = expanded.len() as u32;
+            let create_time =
+                SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+
+            let mut task_ids = Vec::with_capacity(expanded.len());
+            for (index, (cmd, values)) in expanded.into_iter().enumerate() {
+                let task_id = state.next_task_id().await?;
+                let task = TaskMeta {
+                    id: task_id,
+                    name: format!("{}[{}]", base_name, index),
+                    cmd,
+                    gpu_require: gpu_num_required,
+                    state: TaskState::Waiting,
+                    log_path: generate_log_path(task_id)?,
+                    priority: 5, // Default priority
+                    queue: queue.clone(),
+                    create_time,
+                    dependencies: dependencies.clone(),
+                    max_runtime,
+                    retry_policy: retry_policy.clone(),
+                    exec: exec.clone(),
+                    gpu_share: gpu_share.clone(),
+                    gpu_requirement: gpu_requirement.clone(),
+                    array: Some(ArrayMember {
+                        array_id,
+                        index: index as u32,
+                        size,
+                        params: values,
+                    }),
+                    ..Default::default()
+                };
+                state.add_task(task).await?;
+                task_ids.push(task_id);
+            }
+            log::info!(
+                "Array {} ('{}') of {} tasks submitted to queue '{}'",
+                array_id,
+                base_name,
+                size,
+                queue
+            );
+            Ok(Message::Ack(format!(
+                "Array {} ('{}') submitted to queue '{}': {} tasks, IDs {} to {}",
+                array_id,
+                base_name,
+                queue,
+                size,
+                task_ids[0],
+                task_ids[task_ids.len() - 1]
+            )))
+        }
         SubmitAction::BatchJson { mut tasks, default_queue_name } => {
             let num_tasks = tasks.len();
             log::info!(
@@ -126,34 +243,79 @@ pub async fn handle_submit_command(action: SubmitAction, state: DaemonState) ->
             // Use provided default_queue_name, or default to DEFAULT_WAITING_QUEUE_NAME if None
             let default_q =
                 default_queue_name.unwrap_or_else(|| DEFAULT_WAITING_QUEUE_NAME.to_string());
-            let mut submitted_count = 0;
-            let mut errors = Vec::new();
+            let existing_tasks = state.get_all_tasks().await;
 
-            for task_meta in tasks.iter_mut() {
-                // Assign default queue if task queue is empty
-                if task_meta.queue.is_empty() {
-                    task_meta.queue = default_q.clone();
+            // IDs given in the batch (0 means none) are checked before anything is submitted
+            let existing_ids: HashSet<u64> = existing_tasks.iter().map(|t| t.id).collect();
+            let mut batch_ids = HashSet::new();
+            for task_meta in tasks.iter().filter(|t| t.id != 0) {
+                let id = task_meta.id;
+                if existing_ids.contains(&id) {
+                    return Err(
+                        RpcError::Conflict(format!("Task with ID {} already exists", id)).into()
+                    );
+                }
+                if id > MAX_SUBMITTED_TASK_ID {
+                    return Err(RpcError::InvalidArgument(format!(
+                        "Task ID {} is too large, IDs go up to {}",
+                        id, MAX_SUBMITTED_TASK_ID
+                    ))
+                    .into());
                 }
+                if !batch_ids.insert(id) {
+                    return Err(RpcError::InvalidArgument(format!(
+                        "Task ID {} is used more than once in the batch",
+                        id
+                    ))
+                    .into());
+                }
+            }
+
+            // Assign names and IDs first, so that dependencies can refer to any task of the batch
+            let mut batch_names: HashMap<String, u64> = HashMap::new();
+            for task_meta in tasks.iter_mut() {
                 // Assign default name if task name is empty
                 if task_meta.name.is_empty() {
                     task_meta.name = generate_default_task_name();
                 }
-                // Ensure basic fields are set (ID, state, log path, create_time if not present)
                 if task_meta.id == 0 {
-                    // Assuming 0 is not a valid ID from JSON
-                    task_meta.id = generate_task_id();
+                    // Skip the IDs given to other tasks of the batch
+                    task_meta.id = state.next_task_id().await?;
+                    while batch_ids.contains(&task_meta.id) {
+                        task_meta.id = state.next_task_id().await?;
+                    }
+                }
+                if batch_names.insert(task_meta.name.clone(), task_meta.id).is_some() {
+                    return Err(RpcError::InvalidArgument(format!(
+                        "Task name '{}' is used more than once in the batch",
+                        task_meta.name
+                    ))
+                    .into());
+                }
+            }
+
+            // A batch with a broken dependency is rejected as a whole
+            for task_meta in tasks.iter_mut() {
+                resolve_dependencies(&mut task_meta.dependencies, &batch_names, &existing_tasks)
+                    .map_err(|e| e.context(format!("Task '{}'", task_meta.name)))?;
+            }
+            if let Some(task_id) = find_cycle(&tasks) {
+                return Err(RpcError::InvalidArgument(format!(
+                    "Dependency cycle involving task {} in the batch",
+                    task_id
+                ))
+                .into());
+            }
+
+            for task_meta in tasks.iter_mut() {
+                // Assign default queue if task queue is empty
+                if task_meta.queue.is_empty() {
+                    task_meta.queue = default_q.clone();
                 }
                 if task_meta.log_path.is_empty() {
-                    match generate_log_path(task_meta.id) {
-                        Ok(p) => task_meta.log_path = p,
-                        Err(e) => {
-                            errors.push(format!(
-                                "Failed to generate log path for task {}: {}",
-                                task_meta.id, e
-                            ));
-                            continue; // Skip this task
-                        }
-                    }
+                    task_meta.log_path = generate_log_path(task_meta.id).map_err(|e| {
+                        e.context(format!("Failed to generate log path for task {}", task_meta.id))
+                    })?;
                 }
                 if task_meta.create_time == 0 {
                     task_meta.create_time =
@@ -161,35 +323,96 @@ pub async fn handle_submit_command(action: SubmitAction, state: DaemonState) ->
                 }
                 task_meta.state = TaskState::Waiting; // Ensure state starts as Waiting
                 task_meta.pid = None; // Ensure pid is None initially
+                task_meta.start_time = None;
+                task_meta.timed_out_at = None;
+                task_meta.attempts = Vec::new();
+                task_meta.retry_at = None;
+                task_meta.kill_requested = false;
                 task_meta.gpu_ids = Vec::new(); // Ensure gpu_ids is empty initially
-
-                match state.add_task(task_meta.clone()).await {
-                    Ok(_) => {
-                        submitted_count += 1;
-                        log::info!(
-                            "Batch task {} ('{}') submitted to queue '{}'",
-                            task_meta.id,
-                            task_meta.name,
-                            task_meta.queue
-                        );
-                    }
-                    Err(e) => {
-                        let error_msg =
-                            format!("Failed to submit batch task {}: {}", task_meta.id, e);
-                        log::error!("{}", error_msg);
-                        errors.push(error_msg);
-                    }
-                }
             }
 
-            if errors.is_empty() {
-                Ok(Message::Ack(format!(
-                    "Batch JSON tasks submitted: {} tasks successfully",
-                    submitted_count
-                )))
-            } else {
-                Err(anyhow::anyhow!("Errors submitting batch tasks: {}", errors.join("; ")))
+            for task_meta in &tasks {
+                state.add_task(task_meta.clone()).await.map_err(|e| {
+                    e.context(format!("Failed to submit batch task {}", task_meta.id))
+                })?;
+                log::info!(
+                    "Batch task {} ('{}') submitted to queue '{}'",
+                    task_meta.id,
+                    task_meta.name,
+                    task_meta.queue
+                );
             }
+
+            Ok(Message::Ack(format!(
+                "Batch JSON tasks submitted: {} tasks successfully",
+                num_tasks
+            )))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gavel_core::gpu::simulated::{SimulatedBackend, SimulatedFixture};
+    use gavel_core::utils::spool;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn new_state() -> DaemonState {
+        let backend = SimulatedBackend::new(SimulatedFixture {
+            devices: vec![],
+            timeline: vec![],
+            loop_secs: None,
+        });
+        let spool_dir = PathBuf::from(spool::DEFAULT_SPOOL_DIR);
+        DaemonState::new(Arc::new(backend), None, Duration::from_secs(1), spool_dir)
+    }
+
+    fn task(id: u64, name: &str) -> TaskMeta {
+        TaskMeta {
+            id,
+            name: name.to_string(),
+            queue: "q".to_string(),
+            log_path: format!("/tmp/{}.log", name),
+            ..Default::default()
+        }
+    }
+
+    async fn submit_batch(state: &DaemonState, tasks: Vec<TaskMeta>) -> Result<Message> {
+        let action = SubmitAction::BatchJson { tasks, default_queue_name: None };
+        handle_submit_command(action, state.clone()).await
+    }
+
+    fn rpc_error(result: Result<Message>) -> RpcError {
+        result.unwrap_err().downcast::<RpcError>().unwrap()
+    }
+
+    #[tokio::test]
+    async fn batch_is_rejected_as_a_whole_on_bad_ids() {
+        let state = new_state();
+        state.add_task(task(7, "existing")).await.unwrap();
+
+        let existing = submit_batch(&state, vec![task(0, "a"), task(7, "b")]).await;
+        assert!(matches!(rpc_error(existing), RpcError::Conflict(_)));
+        let too_large = submit_batch(&state, vec![task(0, "a"), task(u64::MAX, "b")]).await;
+        assert!(matches!(rpc_error(too_large), RpcError::InvalidArgument(_)));
+        let twice = submit_batch(&state, vec![task(0, "a"), task(9, "b"), task(9, "c")]).await;
+        assert!(matches!(rpc_error(twice), RpcError::InvalidArgument(_)));
+
+        let names: Vec<String> = state.get_all_tasks().await.into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["existing".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn batch_ids_are_kept_clear_of_given_ones() {
+        let state = new_state();
+        // The ID the daemon would hand out next, given to another task of the batch
+        let next = state.next_task_id().await.unwrap() + 1;
+        submit_batch(&state, vec![task(0, "a"), task(next, "b")]).await.unwrap();
+
+        let ids: HashSet<u64> = state.get_all_tasks().await.into_iter().map(|t| t.id).collect();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&next));
+    }
+}
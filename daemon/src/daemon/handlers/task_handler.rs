@@ -1,14 +1,13 @@
+use crate::daemon::process;
+use crate::daemon::scheduler;
 use crate::daemon::state::DaemonState;
-use anyhow::{Context, Result};
-use gavel_core::rpc::message::{Message, TaskAction, TaskFilter};
-use gavel_core::utils::models::TaskState;
+use anyhow::Result;
+use gavel_core::rpc::message::{
+    ArraySelector, Message, ResourceKind, RpcError, TaskAction, TaskFilter,
+};
+use gavel_core::utils::models::{TaskMeta, TaskState};
 use gavel_core::utils::DEFAULT_RUNNING_QUEUE_NAME; // Import the default running queue name
 use gavel_core::utils::DEFAULT_WAITING_QUEUE_NAME;
-use nix::sys::signal::{kill, Signal};
-use nix::unistd::Pid;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
 
 /// Handles task commands
 pub async fn handle_task_command(action: TaskAction, state: DaemonState) -> Result<Message> {
@@ -18,7 +17,33 @@ pub async fn handle_task_command(action: TaskAction, state: DaemonState) -> Resu
         TaskAction::Run { task_id } => handle_task_run(task_id, state).await,
         TaskAction::Kill { task_id } => handle_task_kill(task_id, state).await,
         TaskAction::Remove { task_id } => handle_task_remove(task_id, state).await, // Add Remove case
-        TaskAction::Logs { task_id, tail } => handle_task_logs(task_id, tail, state).await,
+        TaskAction::KillArray { array } => handle_array_kill(array, state).await,
+        TaskAction::RemoveArray { array } => handle_array_remove(array, state).await,
+        TaskAction::Explain { task_id } => handle_task_explain(task_id, state).await,
+    }
+}
+
+/// The selected tasks of an array, ordered by index. NotFound if none matches.
+pub async fn select_array_tasks(
+    array: &ArraySelector,
+    state: &DaemonState,
+) -> Result<Vec<TaskMeta>, RpcError> {
+    let mut tasks: Vec<TaskMeta> =
+        state.get_all_tasks().await.into_iter().filter(|t| array.matches(t)).collect();
+    if tasks.is_empty() {
+        return Err(RpcError::not_found(ResourceKind::Array, array.array_id));
+    }
+    tasks.sort_by_key(|t| t.array.as_ref().map(|a| a.index));
+    Ok(tasks)
+}
+
+// Outcome of an operation applied to each task of an array: the replies of the tasks it
+// failed for become the error
+fn array_reply(summary: String, errors: Vec<String>) -> Message {
+    if errors.is_empty() {
+        Message::Ack(summary)
+    } else {
+        Message::Error(RpcError::Conflict(format!("{}. Errors: {}", summary, errors.join("; "))))
     }
 }
 
@@ -26,7 +51,7 @@ pub async fn handle_task_command(action: TaskAction, state: DaemonState) -> Resu
 async fn handle_task_list(filter: TaskFilter, state: DaemonState) -> Result<Message> {
     log::info!("Handling task list command, filter: {:?}", filter);
     let all_tasks = state.get_all_tasks().await;
-    let filtered_tasks: Vec<_> = all_tasks
+    let mut filtered_tasks: Vec<_> = all_tasks
         .into_iter()
         .filter(|task| match &filter {
             TaskFilter::All => true,
@@ -37,6 +62,7 @@ async fn handle_task_list(filter: TaskFilter, state: DaemonState) -> Result<Mess
                 log::warn!("Filtering by user is not yet implemented");
                 true // Temporarily include all tasks
             }
+            TaskFilter::ByArray(array) => array.matches(task),
         })
         .collect();
 
@@ -46,6 +72,7 @@ async fn handle_task_list(filter: TaskFilter, state: DaemonState) -> Result<Mess
     }
 
     log::debug!("Returning {} tasks", filtered_tasks.len());
+    filtered_tasks.iter_mut().for_each(|task| task.exec.redact_env());
     Ok(Message::TaskStatus(filtered_tasks))
 }
 
@@ -53,17 +80,27 @@ async fn handle_task_list(filter: TaskFilter, state: DaemonState) -> Result<Mess
 async fn handle_task_info(task_id: u64, state: DaemonState) -> Result<Message> {
     log::info!("Handling task info command, task ID: {}", task_id);
     match state.get_task(task_id).await {
-        Some(task) => {
+        Some(mut task) => {
+            task.exec.redact_env();
             log::debug!("Found task {}: {:?}", task_id, task);
             Ok(Message::TaskStatus(vec![task])) // Return a list containing the single task
         }
         None => {
             log::warn!("Task with ID {} not found", task_id);
-            Ok(Message::Error(format!("Task with ID {} not found", task_id)))
+            Ok(Message::Error(RpcError::not_found(ResourceKind::Task, task_id)))
         }
     }
 }
 
+/// Handles the task explain command
+async fn handle_task_explain(task_id: u64, state: DaemonState) -> Result<Message> {
+    log::info!("Handling task explain command, task ID: {}", task_id);
+    match scheduler::explain_task(&state, task_id).await {
+        Some(explanation) => Ok(Message::TaskExplanation(explanation)),
+        None => Ok(Message::Error(RpcError::not_found(ResourceKind::Task, task_id))),
+    }
+}
+
 /// Handles the task run command
 async fn handle_task_run(task_id: u64, state: DaemonState) -> Result<Message> {
     log::info!("Handling task run command, task ID: {}", task_id);
@@ -73,7 +110,7 @@ async fn handle_task_run(task_id: u64, state: DaemonState) -> Result<Message> {
         Some(t) => t,
         None => {
             log::warn!("Task with ID {} not found for run command", task_id);
-            return Ok(Message::Error(format!("Task with ID {} not found", task_id)));
+            return Ok(Message::Error(RpcError::not_found(ResourceKind::Task, task_id)));
         }
     };
 
@@ -85,10 +122,10 @@ async fn handle_task_run(task_id: u64, state: DaemonState) -> Result<Message> {
             task.state,
             task.queue
         );
-        return Ok(Message::Error(format!(
+        return Ok(Message::Error(RpcError::Conflict(format!(
             "Task {} cannot be run. It must be in a Waiting queue. Current state: {:?}, queue: '{}'",
             task_id, task.state, task.queue
-        )));
+        ))));
     }
 
     // b. Move task to the default running queue
@@ -113,10 +150,10 @@ async fn handle_task_run(task_id: u64, state: DaemonState) -> Result<Message> {
                 DEFAULT_RUNNING_QUEUE_NAME,
                 e
             );
-            return Ok(Message::Error(format!(
+            Ok(Message::Error(RpcError::Internal(format!(
                 "Could not move task {} to queue '{}': {}",
                 task_id, DEFAULT_RUNNING_QUEUE_NAME, e
-            )));
+            ))))
         }
     }
 }
@@ -130,10 +167,17 @@ pub async fn handle_task_kill(task_id: u64, state: DaemonState) -> Result<Messag
         Some(t) => t,
         None => {
             log::warn!("Task with ID {} not found", task_id);
-            return Ok(Message::Error(format!("Task with ID {} not found", task_id)));
+            return Ok(Message::Error(RpcError::not_found(ResourceKind::Task, task_id)));
         }
     };
 
+    // A task waiting out its retry backoff is not running; killing it cancels the retry
+    if task.state == TaskState::Retrying {
+        let reason = "Retry cancelled by the user.".to_string();
+        state.update_task_state(task_id, TaskState::Cancelled, None, Some(reason)).await?;
+        return Ok(Message::Ack(format!("Cancelled pending retry of task {}", task_id)));
+    }
+
     // Check if the task is running and has a PID
     if task.state != TaskState::Running {
         log::warn!("Task {} is not in Running state, no need to kill", task_id);
@@ -144,27 +188,103 @@ pub async fn handle_task_kill(task_id: u64, state: DaemonState) -> Result<Messag
     }
 
     if let Some(pid_val) = task.pid {
-        let pid = Pid::from_raw(pid_val as i32);
-        // Send SIGTERM signal to the process using nix::sys::signal::kill
-        match kill(pid, Signal::SIGTERM) {
+        // Failures caused by the user are not retried
+        state.set_task_kill_requested(task_id).await?;
+        let grace = state.kill_grace();
+        match process::terminate_task(&state, &task, grace) {
             Ok(_) => {
-                // Success
-                log::info!("Successfully sent SIGTERM signal to process {}", pid_val);
-                Ok(Message::Ack(format!("Sent kill signal to task {} (PID: {})", task_id, pid_val)))
+                log::info!("Sent SIGTERM to the processes of task {} (PID {})", task_id, pid_val);
+                Ok(Message::Ack(format!(
+                    "Sent SIGTERM to task {} (PID: {}) and its child processes, SIGKILL follows in {}s if needed. The task is marked failed once all of them have exited.",
+                    task_id,
+                    pid_val,
+                    grace.as_secs()
+                )))
             }
             Err(e) => {
-                // Error sending signal
-                log::error!("Failed to send SIGTERM signal to process {}: {}", pid_val, e);
-                Ok(Message::Error(format!(
-                    "Failed to kill task {} (PID: {}): {}",
-                    task_id, pid_val, e
-                )))
+                log::error!("Failed to kill task {} (PID {}): {}", task_id, pid_val, e);
+                let message =
+                    format!("Failed to kill task {} (PID: {}): {:#}", task_id, pid_val, e);
+                Ok(Message::Error(match e.downcast_ref::<nix::errno::Errno>() {
+                    Some(nix::errno::Errno::EPERM) => RpcError::PermissionDenied(message),
+                    _ => RpcError::Internal(message),
+                }))
             }
         }
     } else {
         log::warn!("Task {} has no associated process ID, cannot kill", task_id);
-        Ok(Message::Error(format!("Task {} has no associated process ID, cannot kill", task_id)))
+        Ok(Message::Error(RpcError::Conflict(format!(
+            "Task {} has no associated process ID, cannot kill",
+            task_id
+        ))))
+    }
+}
+
+/// Handles the task kill command for an array: running tasks are killed, waiting ones are
+/// cancelled so that they never start
+async fn handle_array_kill(array: ArraySelector, state: DaemonState) -> Result<Message> {
+    log::info!("Handling task kill command for {}", array);
+    let tasks = match select_array_tasks(&array, &state).await {
+        Ok(tasks) => tasks,
+        Err(e) => return Ok(Message::Error(e)),
+    };
+
+    let (mut killed, mut cancelled, mut skipped) = (0, 0, 0);
+    let mut errors = Vec::new();
+    for task in tasks {
+        match task.state {
+            TaskState::Waiting => {
+                let reason = "Cancelled by the user.".to_string();
+                state.update_task_state(task.id, TaskState::Cancelled, None, Some(reason)).await?;
+                cancelled += 1;
+            }
+            TaskState::Running | TaskState::Retrying => {
+                match handle_task_kill(task.id, state.clone()).await? {
+                    Message::Error(e) => errors.push(e.to_string()),
+                    _ => killed += 1,
+                }
+            }
+            _ => skipped += 1, // Already ended
+        }
+    }
+    let summary = format!(
+        "{}: {} task(s) killed, {} waiting task(s) cancelled, {} already ended",
+        array, killed, cancelled, skipped
+    );
+    log::info!("{}", summary);
+    Ok(array_reply(summary, errors))
+}
+
+/// Handles the task remove command for an array. Nothing is removed if a selected task is
+/// still running.
+async fn handle_array_remove(array: ArraySelector, state: DaemonState) -> Result<Message> {
+    log::info!("Handling task remove command for {}", array);
+    let tasks = match select_array_tasks(&array, &state).await {
+        Ok(tasks) => tasks,
+        Err(e) => return Ok(Message::Error(e)),
+    };
+
+    let running: Vec<u64> =
+        tasks.iter().filter(|t| t.state == TaskState::Running).map(|t| t.id).collect();
+    if !running.is_empty() {
+        log::warn!("Attempted to remove {} while tasks {:?} are running.", array, running);
+        return Ok(Message::Error(RpcError::Conflict(format!(
+            "Task(s) {:?} of {} are currently running. Please kill them before removing.",
+            running, array
+        ))));
+    }
+
+    let mut removed = 0;
+    let mut errors = Vec::new();
+    for task in tasks {
+        match handle_task_remove(task.id, state.clone()).await? {
+            Message::Error(e) => errors.push(e.to_string()),
+            _ => removed += 1,
+        }
     }
+    let summary = format!("{}: {} task(s) removed", array, removed);
+    log::info!("{}", summary);
+    Ok(array_reply(summary, errors))
 }
 
 /// Handles the task remove command
@@ -176,7 +296,7 @@ async fn handle_task_remove(task_id: u64, state: DaemonState) -> Result<Message>
         Some(t) => t,
         None => {
             log::warn!("Task with ID {} not found for removal", task_id);
-            return Ok(Message::Error(format!("Task with ID {} not found", task_id)));
+            return Ok(Message::Error(RpcError::not_found(ResourceKind::Task, task_id)));
         }
     };
 
@@ -186,11 +306,33 @@ async fn handle_task_remove(task_id: u64, state: DaemonState) -> Result<Message>
             "Attempted to remove running task {}. Task must be killed or finished first.",
             task_id
         );
-        return Ok(Message::Error(format!(
+        return Ok(Message::Error(RpcError::Conflict(format!(
             "Task {} is currently running (PID: {:?}). Please kill it before removing.",
             task_id,
             task.pid.unwrap_or(-1) // Show PID if available
-        )));
+        ))));
+    }
+
+    // Waiting dependents would be cancelled if their parent disappeared
+    let dependents: Vec<u64> = state
+        .get_all_tasks()
+        .await
+        .iter()
+        .filter(|t| {
+            t.state == TaskState::Waiting && t.dependencies.iter().any(|d| d.task_id == task_id)
+        })
+        .map(|t| t.id)
+        .collect();
+    if !dependents.is_empty() {
+        log::warn!(
+            "Attempted to remove task {} which waiting tasks {:?} depend on.",
+            task_id,
+            dependents
+        );
+        return Ok(Message::Error(RpcError::Conflict(format!(
+            "Task {} is a dependency of waiting task(s) {:?}. Remove them first.",
+            task_id, dependents
+        ))));
     }
 
     // Attempt to remove the task from the state
@@ -202,63 +344,17 @@ async fn handle_task_remove(task_id: u64, state: DaemonState) -> Result<Message>
         Ok(None) => {
             // This case should ideally not happen if get_task succeeded earlier, but handle defensively
             log::warn!("Task {} was found but could not be removed (already gone?)", task_id);
-            Ok(Message::Error(format!(
+            Ok(Message::Error(RpcError::Conflict(format!(
                 "Task {} could not be removed (might have been removed already)",
                 task_id
-            )))
+            ))))
         }
         Err(e) => {
             log::error!("Failed to remove task {}: {}", task_id, e);
-            Ok(Message::Error(format!("Failed to remove task {}: {}", task_id, e)))
+            Ok(Message::Error(RpcError::Internal(format!(
+                "Failed to remove task {}: {}",
+                task_id, e
+            ))))
         }
     }
 }
-
-/// Handles the task logs command
-async fn handle_task_logs(task_id: u64, tail: bool, state: DaemonState) -> Result<Message> {
-    log::info!("Handling task logs command, task ID: {}, tail mode: {}", task_id, tail);
-
-    // Get task info
-    let task = match state.get_task(task_id).await {
-        Some(t) => t,
-        None => {
-            log::warn!("Task with ID {} not found", task_id);
-            return Ok(Message::Error(format!("Task with ID {} not found", task_id)));
-        }
-    };
-
-    // Check if log file exists
-    let log_path = Path::new(&task.log_path);
-    if !log_path.exists() {
-        log::warn!("Log file for task {} does not exist: {}", task_id, task.log_path);
-        return Ok(Message::Error(format!("Log file for task {} does not exist", task_id)));
-    }
-
-    // Read log file content
-    let file =
-        File::open(log_path).context(format!("Could not open log file: {}", task.log_path))?;
-    let reader = BufReader::new(file);
-
-    // If tail is true, read only the last few lines (simple implementation: read all, take last 10)
-    let mut lines: Vec<String> = Vec::new();
-    for line in reader.lines() {
-        match line {
-            Ok(l) => lines.push(l),
-            Err(e) => {
-                log::error!("Failed to read log line: {}", e);
-                continue;
-            }
-        }
-    }
-
-    let log_content = if tail && lines.len() > 10 {
-        let start_idx = lines.len() - 10;
-        lines[start_idx..].join("\n")
-    } else {
-        lines.join("\n")
-    };
-
-    // Return log content
-    log::debug!("Returning log content for task {}, {} lines total", task_id, lines.len());
-    Ok(Message::Ack(log_content))
-}
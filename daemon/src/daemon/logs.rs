@@ -0,0 +1,234 @@
+// src/daemon/logs.rs
+// Streaming of task logs, optionally following the output of a running task
+use crate::daemon::state::DaemonState;
+use anyhow::{Context, Result};
+use gavel_core::rpc::message::{LogChunk, LogRequest, Message, ResourceKind, RpcError};
+use gavel_core::rpc::protocol::write_frame_async;
+use gavel_core::utils::models::TaskState;
+use std::io::{ErrorKind, SeekFrom};
+use std::time::Duration;
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite};
+use tokio::net::UnixStream;
+use tokio::sync::watch;
+
+// Data per LogChunk, well below the frame size limit
+const CHUNK_LEN: usize = 64 * 1024;
+// How often a followed log is checked for new output
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Sends the requested part of a task's log as `LogChunk`s followed by an Ack. In follow mode
+/// new output is sent as it is written, across retry attempts, until the task has finished.
+pub async fn serve_log_stream(
+    mut stream: UnixStream,
+    state: &DaemonState,
+    request: LogRequest,
+    mut shutdown_rx: watch::Receiver<bool>,
+) -> Result<()> {
+    log::info!(
+        "Streaming logs of task {} (offset: {:?}, lines: {:?}, follow: {})",
+        request.task_id,
+        request.offset,
+        request.lines,
+        request.follow
+    );
+    let (mut reader, mut writer) = stream.split();
+    let Some(task) = state.get_task(request.task_id).await else {
+        let reply = Message::Error(RpcError::not_found(ResourceKind::Task, request.task_id));
+        return send(&mut writer, &reply).await;
+    };
+
+    let mut log_path = task.log_path;
+    let mut file: Option<File> = None;
+    let mut offset = 0;
+    let mut first_file = true;
+    loop {
+        // Read the task state before the log, so that output written before it finished is
+        // sent before the stream ends
+        let task = state.get_task(request.task_id).await;
+        let finished = task.as_ref().is_none_or(|t| is_final(&t.state));
+
+        if file.is_none() {
+            match File::open(&log_path).await {
+                Ok(mut opened) => {
+                    offset = if first_file {
+                        start_offset(&mut opened, &request).await?
+                    } else {
+                        0 // A new attempt starts a new file
+                    };
+                    file = Some(opened);
+                }
+                // Tasks only create their log when they start
+                Err(e) if e.kind() == ErrorKind::NotFound && request.follow => {}
+                Err(e) => {
+                    log::warn!("Could not open log file {}: {}", log_path, e);
+                    let reply = match e.kind() {
+                        ErrorKind::NotFound => {
+                            RpcError::not_found(ResourceKind::Log, request.task_id)
+                        }
+                        ErrorKind::PermissionDenied => RpcError::PermissionDenied(format!(
+                            "Could not open log file {}: {}",
+                            log_path, e
+                        )),
+                        _ => RpcError::Internal(format!(
+                            "Could not open log file {}: {}",
+                            log_path, e
+                        )),
+                    };
+                    return send(&mut writer, &Message::Error(reply)).await;
+                }
+            }
+        }
+        if let Some(opened) = file.as_mut() {
+            first_file = false;
+            offset = send_new_output(&mut writer, opened, &log_path, offset).await?;
+        }
+
+        if !request.follow {
+            return send(&mut writer, &Message::Ack("End of log".to_string())).await;
+        }
+        match task {
+            None => {
+                let reply = Message::Ack(format!("Task {} was removed", request.task_id));
+                return send(&mut writer, &reply).await;
+            }
+            Some(task) if finished => {
+                let reply = Message::Ack(format!("Task {} ended as {:?}", task.id, task.state));
+                return send(&mut writer, &reply).await;
+            }
+            Some(task) if task.log_path != log_path => {
+                // Retried: the rest of the previous attempt's log was sent above
+                log_path = task.log_path;
+                file = None;
+            }
+            Some(_) => {}
+        }
+
+        let mut buf = [0u8; 64];
+        tokio::select! {
+            _ = tokio::time::sleep(FOLLOW_INTERVAL) => {}
+            // Clients send nothing after the request, so this only notices disconnects
+            read = reader.read(&mut buf) => {
+                if matches!(read, Ok(0) | Err(_)) {
+                    log::debug!("Log stream client of task {} went away.", request.task_id);
+                    return Ok(());
+                }
+            }
+            _ = shutdown_rx.changed() => return Ok(()),
+        }
+    }
+}
+
+fn is_final(state: &TaskState) -> bool {
+    matches!(
+        state,
+        TaskState::Finished | TaskState::Failed | TaskState::Cancelled | TaskState::TimedOut
+    )
+}
+
+async fn send(writer: &mut (impl AsyncWrite + Unpin), message: &Message) -> Result<()> {
+    write_frame_async(writer, message).await.context("Failed to send log stream message")
+}
+
+/// Where streaming of the first log file starts: the requested offset, the start of the last
+/// `lines` lines, or the beginning
+async fn start_offset(file: &mut File, request: &LogRequest) -> Result<u64> {
+    match (request.offset, request.lines) {
+        (Some(offset), _) => Ok(offset),
+        (None, Some(lines)) => tail_offset(file, lines).await.context("Failed to read log file"),
+        (None, None) => Ok(0),
+    }
+}
+
+/// Byte offset of the start of the last `lines` lines, reading the file backwards
+async fn tail_offset(file: &mut File, lines: u32) -> std::io::Result<u64> {
+    let len = file.metadata().await?.len();
+    if lines == 0 {
+        return Ok(len);
+    }
+    let mut found = 0;
+    let mut end = len;
+    let mut buf = vec![0u8; 8192];
+    while end > 0 {
+        let start = end.saturating_sub(buf.len() as u64);
+        let block = &mut buf[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start)).await?;
+        file.read_exact(block).await?;
+        for (i, _) in block.iter().enumerate().rev().filter(|(_, &b)| b == b'\n') {
+            let pos = start + i as u64;
+            // A newline at the very end closes the last line instead of starting one
+            if pos + 1 == len {
+                continue;
+            }
+            found += 1;
+            if found == lines {
+                return Ok(pos + 1);
+            }
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+/// Sends everything from `offset` to the current end of the file and returns the new offset
+async fn send_new_output(
+    writer: &mut (impl AsyncWrite + Unpin),
+    file: &mut (impl AsyncRead + AsyncSeek + Unpin),
+    log_path: &str,
+    mut offset: u64,
+) -> Result<u64> {
+    file.seek(SeekFrom::Start(offset)).await.context("Failed to seek in log file")?;
+    let mut buf = vec![0u8; CHUNK_LEN];
+    loop {
+        let read = file.read(&mut buf).await.context("Failed to read log file")?;
+        if read == 0 {
+            return Ok(offset);
+        }
+        let chunk = LogChunk { log_path: log_path.to_string(), offset, data: buf[..read].to_vec() };
+        send(writer, &Message::LogChunk(chunk)).await?;
+        offset += read as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn offset_of(content: &[u8], lines: u32) -> u64 {
+        let tmp = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(tmp.path(), content).unwrap();
+        let mut file = File::open(tmp.path()).await.unwrap();
+        tail_offset(&mut file, lines).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn tail_offset_finds_start_of_last_lines() {
+        assert_eq!(offset_of(b"a\nb\nc\n", 1).await, 4);
+        assert_eq!(offset_of(b"a\nb\nc\n", 2).await, 2);
+        // Without a final newline the partial last line counts as a line
+        assert_eq!(offset_of(b"a\nb\nc", 1).await, 4);
+        assert_eq!(offset_of(b"a\nb\nc", 2).await, 2);
+    }
+
+    #[tokio::test]
+    async fn tail_offset_edge_cases() {
+        assert_eq!(offset_of(b"", 10).await, 0);
+        assert_eq!(offset_of(b"a\nb\n", 10).await, 0); // Fewer lines than asked for
+        assert_eq!(offset_of(b"a\nb\n", 0).await, 4); // No lines: the end of the file
+        assert_eq!(offset_of(b"\n\n\n", 2).await, 1);
+    }
+
+    #[tokio::test]
+    async fn tail_offset_spans_read_blocks() {
+        // Lines longer than the 8KB read block
+        let line = [b'x'; 5000];
+        let mut content = Vec::new();
+        for _ in 0..4 {
+            content.extend_from_slice(&line);
+            content.push(b'\n');
+        }
+        assert_eq!(offset_of(&content, 1).await, 3 * 5001);
+        assert_eq!(offset_of(&content, 3).await, 5001);
+        assert_eq!(offset_of(&content, 4).await, 0);
+    }
+}
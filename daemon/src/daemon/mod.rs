@@ -1,19 +1,33 @@
 // src/daemon/mod.rs
+pub mod dependencies;
+pub mod events;
 pub mod handlers;
+pub mod logs;
+pub mod monitor;
+pub mod persistence;
+pub mod process;
+pub mod retry;
 pub mod scheduler;
 pub mod state; // Add scheduler module
+pub mod walltime;
 
-use crate::daemon::scheduler::run_scheduler;
+use crate::daemon::scheduler::{run_scheduler, SchedulerConfig};
 use anyhow::{Context, Result};
-use bincode::config::standard as bincode_config;
-use bincode::{decode_from_slice, encode_to_vec};
-use gavel_core::rpc::message::{DaemonAction, Message};
+use gavel_core::gpu::backend::GpuBackend;
+use gavel_core::rpc::message::{
+    DaemonAction, DaemonStatus, GpuCounts, Message, QueueSummary, RpcError, TaskCounts,
+};
+use gavel_core::rpc::protocol::{read_frame_async, write_frame_async, Hello};
+use gavel_core::rpc::GavelError;
+use gavel_core::utils::models::TaskState;
 use handlers::{
     handle_gpu_command, handle_queue_command, handle_submit_command, handle_task_command,
 }; // Import handle_submit_command
 use state::DaemonState; // Import DaemonState
-use std::path::Path;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::{UnixListener, UnixStream};
 use tokio::sync::watch; // Use tokio's RwLock // Import the scheduler function
 
@@ -21,9 +35,22 @@ use tokio::sync::watch; // Use tokio's RwLock // Import the scheduler function
 type ShutdownSender = watch::Sender<bool>;
 // Define a type for the shutdown signal receiver
 
+/// Settings the daemon is started with, built from the config file
+pub struct DaemonOptions {
+    pub sock_path: String,
+    pub gpu_backend: Arc<dyn GpuBackend>,
+    pub state_path: Option<PathBuf>, // None disables persistence
+    pub snapshot_interval: Duration,
+    pub scheduler: SchedulerConfig,
+    pub gpu_monitor_interval: Duration,
+    pub kill_grace: Duration, // SIGTERM -> SIGKILL delay for killed tasks and their leftovers
+    pub spool_dir: PathBuf,   // Root of the task spool directories written by gavel-shim
+}
+
 /// Starts the daemon, listens for connections, and handles messages.
 /// Runs until a Stop command is received or an error occurs.
-pub async fn start(sock_path: &str) -> Result<()> {
+pub async fn start(options: DaemonOptions) -> Result<()> {
+    let sock_path = options.sock_path.as_str();
     log::info!("Daemon starting, attempting to listen on socket: {}", sock_path);
 
     // Ensure the socket file doesn't exist before binding
@@ -38,10 +65,16 @@ pub async fn start(sock_path: &str) -> Result<()> {
         .with_context(|| format!("Failed to bind to socket: {}", sock_path))?;
     log::info!("Successfully bound to socket: {}", sock_path);
 
-    // Create the shared state (assuming persistence path is derived or configured)
-    // For now, let's use a temporary path for state persistence.
-    // TODO: Get persistence path from config or a standard location.
-    let daemon_state = DaemonState::new();
+    // Create the shared state and restore the last snapshot, if any
+    let daemon_state = DaemonState::new(
+        options.gpu_backend,
+        options.state_path,
+        options.kill_grace,
+        options.spool_dir,
+    );
+    if daemon_state.load_snapshot().await? {
+        persistence::reconcile_running_tasks(&daemon_state).await;
+    }
     match daemon_state.update_all_gpu_stats().await {
         Ok(_) => { /* GPU stats updated successfully */ }
         Err(e) => log::error!("Failed to update GPU stats: {}", e),
@@ -53,10 +86,13 @@ pub async fn start(sock_path: &str) -> Result<()> {
 
     // --- Start the scheduler task ---
     let scheduler_state = daemon_state.clone(); // Clone state for the scheduler
-    tokio::spawn(run_scheduler(scheduler_state)); // Spawn the scheduler in a background task
+    tokio::spawn(run_scheduler(scheduler_state, options.scheduler)); // Spawn the scheduler in a background task
     log::info!("Scheduler task started.");
     // --- Scheduler task started ---
 
+    tokio::spawn(monitor::run_gpu_monitor(daemon_state.clone(), options.gpu_monitor_interval));
+    tokio::spawn(persistence::run_persister(daemon_state.clone(), options.snapshot_interval));
+
     log::info!("Daemon ready and listening for connections.");
 
     loop {
@@ -93,7 +129,10 @@ pub async fn start(sock_path: &str) -> Result<()> {
     }
 
     log::info!("Daemon shutting down...");
-    // Perform cleanup, e.g., save state one last time
+    // Save state one last time
+    if let Err(e) = daemon_state.save_snapshot().await {
+        log::error!("Failed to save state snapshot during shutdown: {:#}", e);
+    }
     // Ensure the socket file is removed on shutdown
     if let Err(e) = tokio::fs::remove_file(sock_path).await {
         log::warn!("Failed to remove socket file during shutdown {}: {}", sock_path, e);
@@ -103,30 +142,73 @@ pub async fn start(sock_path: &str) -> Result<()> {
     Ok(())
 }
 
+/// Error reply for a request whose handler failed. Handlers raise an `RpcError` for failures
+/// caused by the request itself; anything else is an internal error of the daemon.
+fn rpc_error(e: &anyhow::Error, command: &str) -> RpcError {
+    let message = format!("{:#}", e);
+    match e.downcast_ref::<RpcError>() {
+        Some(RpcError::NotFound { kind, id }) => RpcError::NotFound { kind: *kind, id: id.clone() },
+        Some(RpcError::InvalidArgument(_)) => RpcError::InvalidArgument(message),
+        Some(RpcError::Conflict(_)) => RpcError::Conflict(message),
+        Some(RpcError::PermissionDenied(_)) => RpcError::PermissionDenied(message),
+        Some(RpcError::Internal(_)) | None => {
+            RpcError::Internal(format!("Error handling {}: {}", command, message))
+        }
+    }
+}
+
 /// Handles a single client connection asynchronously.
 async fn handle_connection(
     mut stream: UnixStream,
     state: DaemonState,
     shutdown_tx: ShutdownSender,
 ) -> Result<()> {
-    // Read message length (u32)
-    let mut len_bytes = [0u8; 4];
-    stream.read_exact(&mut len_bytes).await.context("Failed to read message length")?;
-    let len = u32::from_le_bytes(len_bytes);
-
-    // Basic sanity check for length
-    if len > 10 * 1024 * 1024 {
-        // e.g., limit message size to 10MB
-        return Err(anyhow::anyhow!("Received excessively large message length: {}", len));
+    // Version handshake: the client's Hello first, then ours
+    let local = Hello::current();
+    let client: Hello = match read_frame_async(&mut stream).await {
+        Ok(hello) => hello,
+        Err(e) => {
+            log::warn!(
+                "Closing connection without a valid handshake (client older than gavel {}?): {}",
+                local.software_version,
+                e
+            );
+            return Ok(());
+        }
+    };
+    write_frame_async(&mut stream, &local).await.context("Failed to send handshake")?;
+    if !local.accepts_client(&client) {
+        // The client reports the mismatch to its user
+        log::warn!(
+            "Rejected client with protocol v{} (gavel {}), daemon speaks v{}",
+            client.protocol_version,
+            client.software_version,
+            local.protocol_version
+        );
+        return Ok(());
     }
 
-    // Read message data
-    let mut msg_buf = vec![0u8; len as usize];
-    stream.read_exact(&mut msg_buf).await.context("Failed to read message data")?;
+    let message: Message = match read_frame_async(&mut stream).await {
+        Ok(message) => message,
+        // Clients may only want the handshake
+        Err(GavelError::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+            log::debug!("Client closed the connection after the handshake.");
+            return Ok(());
+        }
+        Err(e) => return Err(e).context("Failed to read request message"),
+    };
 
-    // Decode the message
-    let (message, _): (Message, usize) =
-        decode_from_slice(&msg_buf, bincode_config()).context("Failed to decode message")?;
+    // Subscriptions and log streams keep the connection open instead of sending a single reply
+    match message {
+        Message::Subscribe(filter) => {
+            return events::serve_subscription(stream, &state, filter, shutdown_tx.subscribe())
+                .await
+        }
+        Message::StreamLogs(request) => {
+            return logs::serve_log_stream(stream, &state, request, shutdown_tx.subscribe()).await
+        }
+        _ => {}
+    }
 
     // Process the message and potentially create a reply
     let reply_message = match message {
@@ -137,7 +219,7 @@ async fn handle_connection(
                 if shutdown_tx.send(true).is_err() {
                     log::error!("Failed to send shutdown signal: receiver dropped?");
                     // Still try to send an error back if possible
-                    Message::Error("Failed to initiate shutdown".to_string())
+                    Message::Error(RpcError::Internal("Failed to initiate shutdown".to_string()))
                 } else {
                     Message::Ack("Shutdown initiated".to_string())
                 }
@@ -145,9 +227,10 @@ async fn handle_connection(
             DaemonAction::Status => {
                 log::info!("Received Status command.");
                 match status(&state).await {
-                    // Pass state to status check
-                    Ok(status_string) => Message::Ack(status_string),
-                    Err(e) => Message::Error(format!("Status check failed: {}", e)),
+                    Ok(daemon_status) => Message::DaemonStatus(daemon_status),
+                    Err(e) => {
+                        Message::Error(RpcError::Internal(format!("Status check failed: {}", e)))
+                    }
                 }
             }
         },
@@ -155,21 +238,21 @@ async fn handle_connection(
             Ok(reply) => reply,
             Err(e) => {
                 log::error!("Error handling TaskCommand: {}", e);
-                Message::Error(format!("Error handling TaskCommand: {}", e))
+                Message::Error(rpc_error(&e, "TaskCommand"))
             }
         },
         Message::GPUCommand(action) => match handle_gpu_command(action, state.clone()).await {
             Ok(reply) => reply,
             Err(e) => {
                 log::error!("Error handling GPUCommand: {}", e);
-                Message::Error(format!("Error handling GPUCommand: {}", e))
+                Message::Error(rpc_error(&e, "GPUCommand"))
             }
         },
         Message::QueueCommand(action) => match handle_queue_command(action, state.clone()).await {
             Ok(reply) => reply,
             Err(e) => {
                 log::error!("Error handling QueueCommand: {}", e);
-                Message::Error(format!("Error handling QueueCommand: {}", e))
+                Message::Error(rpc_error(&e, "QueueCommand"))
             }
         },
         Message::SubmitCommand(action) => {
@@ -178,87 +261,101 @@ async fn handle_connection(
                 Ok(reply) => reply,
                 Err(e) => {
                     log::error!("Error handling SubmitCommand: {}", e);
-                    Message::Error(format!("Error handling SubmitCommand: {}", e))
+                    Message::Error(rpc_error(&e, "SubmitCommand"))
                 }
             }
         }
+        Message::Subscribe(_) | Message::StreamLogs(_) => unreachable!("streams are served above"),
         // Handle status/ack/error messages received from client (shouldn't happen in request/reply)
         Message::GPUStatus(_)
         | Message::TaskStatus(_)
         | Message::QueueStatus(_)
+        | Message::DaemonStatus(_)
+        | Message::TaskExplanation(_)
         | Message::Ack(_)
-        | Message::Error(_) => {
+        | Message::Error(_)
+        | Message::Event(_)
+        | Message::LogChunk(_) => {
             log::warn!("Received status/ack/error message type from client, which is unexpected in a request.");
-            Message::Error("Daemon received unexpected status/ack/error message type".to_string())
+            Message::Error(RpcError::InvalidArgument(
+                "Daemon received unexpected status/ack/error message type".to_string(),
+            ))
         }
     };
 
     // Send the reply message back
-    let encoded_reply = encode_to_vec(&reply_message, bincode_config())
-        .context("Failed to encode reply message")?;
-    let reply_len = encoded_reply.len() as u32;
-
-    stream.write_all(&reply_len.to_le_bytes()).await.context("Failed to write reply length")?;
-    stream.write_all(&encoded_reply).await.context("Failed to write reply data")?;
-    stream.flush().await.context("Failed to flush stream for reply")?;
+    write_frame_async(&mut stream, &reply_message).await.context("Failed to send reply")?;
 
     log::debug!("Sent reply and closing connection.");
     Ok(())
 }
 
 /// Performs internal status checks. Called when a Status command is received.
-async fn status(state: &DaemonState) -> Result<String> {
-    // 实现更加完善的状态检查
-    log::debug!("执行状态检查...");
+async fn status(state: &DaemonState) -> Result<DaemonStatus> {
+    log::debug!("Collecting daemon status...");
 
-    // 获取统计数据
     let all_tasks = state.get_all_tasks().await;
     let all_queues = state.get_all_queues().await;
     let gpu_stats = state.get_all_gpu_stats().await;
     let gpu_allocations = state.get_gpu_allocations().await;
     let ignored_gpus = state.get_ignored_gpus().await;
 
-    // 计算任务统计
-    let running_tasks = all_tasks
-        .iter()
-        .filter(|t| t.state == gavel_core::utils::models::TaskState::Running)
-        .count();
-    let waiting_tasks = all_tasks
-        .iter()
-        .filter(|t| t.state == gavel_core::utils::models::TaskState::Waiting)
-        .count();
-    let finished_tasks = all_tasks
-        .iter()
-        .filter(|t| t.state == gavel_core::utils::models::TaskState::Finished)
-        .count();
-
-    // 构建状态报告
-    let mut status = format!(
-        "守护进程状态\n==================\n\n任务总数: {}\n- 运行中: {}\n- 等待中: {}\n- 已完成: {}\n\n",
-        all_tasks.len(), running_tasks, waiting_tasks, finished_tasks
-    );
-
-    status.push_str(&format!("队列总数: {}\n", all_queues.len()));
-    for queue in &all_queues {
-        status.push_str(&format!(
-            "- {}: 优先级 {}，分配GPU: {:?}\n",
-            queue.name, queue.priority, queue.allocated_gpus
-        ));
+    let mut tasks = TaskCounts { total: all_tasks.len() as u64, ..Default::default() };
+    for task in &all_tasks {
+        let count = match task.state {
+            TaskState::Waiting => &mut tasks.waiting,
+            TaskState::Running => &mut tasks.running,
+            TaskState::Finished => &mut tasks.finished,
+            TaskState::Failed => &mut tasks.failed,
+            TaskState::Cancelled => &mut tasks.cancelled,
+            TaskState::TimedOut => &mut tasks.timed_out,
+            TaskState::Retrying => &mut tasks.retrying,
+        };
+        *count += 1;
     }
 
-    status.push_str(&format!("\nGPU总数: {}\n", gpu_stats.len()));
-    status.push_str(&format!(
-        "- 已分配: {}\n",
-        gpu_allocations.values().filter(|a| a.is_some()).count()
-    ));
-    status.push_str(&format!("- 已忽略: {}\n", ignored_gpus.len()));
-    status.push_str(&format!(
-        "- 可用: {}\n",
-        gpu_allocations.values().filter(|a| a.is_none()).count()
-    ));
+    let mut queues: Vec<QueueSummary> = all_queues
+        .into_iter()
+        .map(|queue| QueueSummary {
+            waiting: queue.waiting_task_ids.len() as u64,
+            running: queue.running_task_ids.len() as u64,
+            name: queue.name,
+            priority: queue.priority,
+            allocated_gpus: queue.allocated_gpus,
+        })
+        .collect();
+    queues.sort_by(|a, b| b.priority.cmp(&a.priority).then_with(|| a.name.cmp(&b.name)));
 
-    // 添加系统健康状态检查结果
-    status.push_str("\n系统健康状态: 正常");
+    // Ignored GPUs have no stats, and unhealthy ones may have lost theirs
+    let unhealthy_gpus = state.get_unhealthy_gpus();
+    let known_gpus: HashSet<u32> =
+        gpu_stats.keys().chain(ignored_gpus.iter()).chain(unhealthy_gpus.iter()).copied().collect();
+    // Queues list the GPUs they schedule on, including ones given to them at startup
+    let allocated_gpus: HashSet<u32> = queues
+        .iter()
+        .flat_map(|q| q.allocated_gpus.iter().map(|id| *id as u32))
+        .chain(gpu_allocations.iter().filter(|(_, q)| q.is_some()).map(|(id, _)| *id))
+        .collect();
+    let is_allocated = |id: &u32| allocated_gpus.contains(id);
+    let gpus = GpuCounts {
+        total: known_gpus.len() as u64,
+        allocated: known_gpus.iter().filter(|id| is_allocated(id)).count() as u64,
+        available: known_gpus
+            .iter()
+            .filter(|id| {
+                !is_allocated(id) && !ignored_gpus.contains(id) && !unhealthy_gpus.contains(id)
+            })
+            .count() as u64,
+        ignored: ignored_gpus.len() as u64,
+        unhealthy: unhealthy_gpus.len() as u64,
+    };
 
-    Ok(status)
+    Ok(DaemonStatus {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        pid: std::process::id(),
+        started_at: state.started_at(),
+        tasks,
+        queues,
+        gpus,
+    })
 }
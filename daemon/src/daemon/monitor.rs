@@ -0,0 +1,18 @@
+// src/daemon/monitor.rs
+use crate::daemon::state::DaemonState;
+use log::{error, info};
+use std::time::Duration;
+
+/// Samples the GPUs at a fixed interval, independently of the scheduling cycles.
+pub async fn run_gpu_monitor(state: DaemonState, interval: Duration) {
+    info!("GPU monitor started (interval: {:?}).", interval);
+    let mut ticker = tokio::time::interval(interval);
+    ticker.tick().await; // The first tick completes immediately, and the daemon sampled at startup
+
+    loop {
+        ticker.tick().await;
+        if let Err(e) = state.update_all_gpu_stats().await {
+            error!("Failed to update GPU stats: {}", e);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+// src/daemon/persistence.rs
+use crate::daemon::scheduler::{adopt_task_process, monitor_shim, record_task_exit};
+use crate::daemon::state::DaemonState;
+use gavel_core::utils::models::TaskState;
+use gavel_core::utils::{procfs, spool};
+use log::{error, info, warn};
+use std::time::Duration;
+use tokio::time::sleep;
+
+// Short delay after a mutation so bursts (e.g. a batch submit) become a single write
+const MUTATION_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Writes snapshots of the daemon state: periodically, and shortly after every mutation.
+pub async fn run_persister(state: DaemonState, interval: Duration) {
+    info!("State persister started (interval: {:?}).", interval);
+    let mut ticker = tokio::time::interval(interval);
+    ticker.tick().await; // The first tick completes immediately
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = state.wait_for_mutation() => {
+                sleep(MUTATION_DEBOUNCE).await;
+            }
+        }
+
+        if let Err(e) = state.save_snapshot().await {
+            error!("Failed to save state snapshot: {:#}", e);
+        }
+    }
+}
+
+/// Resolves tasks that a loaded snapshot still records as Running.
+/// A task whose gavel-shim is still alive is re-adopted, and a task whose shim already
+/// wrote an exit status gets that outcome. Without a shim, a task process that is still
+/// alive (same PID and same start time) is re-adopted; any other task cannot be observed
+/// anymore and is marked Failed with an explicit reason.
+pub async fn reconcile_running_tasks(state: &DaemonState) {
+    let running: Vec<_> =
+        state.get_all_tasks().await.into_iter().filter(|t| t.state == TaskState::Running).collect();
+
+    for task in running {
+        let spool_dir = state.task_spool_dir(task.id);
+        let pid_info = spool::read_pid_info(&spool_dir).unwrap_or_else(|e| {
+            warn!("Ignoring spool of task {} (ID: {}): {:#}", task.name, task.id, e);
+            None
+        });
+        if let Some(pid_info) = pid_info {
+            let shim_alive = pid_info
+                .shim_start_time
+                .is_some_and(|start_time| procfs::is_same_process(pid_info.shim_pid, start_time));
+            if shim_alive {
+                info!("Re-adopting task '{}' (ID: {}) through its supervisor", task.name, task.id);
+                monitor_shim(state, &task, pid_info);
+                continue;
+            }
+            if matches!(spool::read_exit_info(&spool_dir), Ok(Some(_))) {
+                record_task_exit(state, task.id, &task.name).await;
+                continue;
+            }
+        }
+
+        let reason = match (task.pid, task.pid_start_time) {
+            (Some(pid), Some(start_time)) if procfs::is_same_process(pid, start_time) => {
+                adopt_task_process(state, task, pid, start_time);
+                continue;
+            }
+            (Some(pid), Some(_)) => format!(
+                "Process (PID {}) exited while the daemon was not running; its outcome is unknown.",
+                pid
+            ),
+            (Some(pid), None) => format!(
+                "Daemon restarted while the task was running (PID {}) and the process identity could not be verified.",
+                pid
+            ),
+            (None, _) => "Daemon restarted while the task was running; its outcome is unknown."
+                .to_string(),
+        };
+        warn!("Reconciling task {} (ID: {}) after restart: {}", task.name, task.id, reason);
+        if let Err(e) =
+            state.update_task_state(task.id, TaskState::Failed, None, Some(reason)).await
+        {
+            error!("Failed to reconcile task {} after restart: {}", task.id, e);
+        }
+    }
+}
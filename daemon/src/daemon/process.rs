@@ -0,0 +1,129 @@
+// src/daemon/process.rs
+use crate::daemon::state::DaemonState;
+use anyhow::Result;
+use gavel_core::utils::models::{TaskMeta, TaskState};
+use gavel_core::utils::procfs::is_same_process;
+use log::{debug, error, warn};
+use nix::errno::Errno;
+use nix::sys::signal::{kill, killpg, Signal};
+use nix::unistd::Pid;
+use std::io;
+use std::os::fd::{FromRawFd, OwnedFd};
+use std::time::Duration;
+use tokio::io::unix::AsyncFd;
+use tokio::io::Interest;
+use tokio::time::sleep;
+
+// Fallback polling interval when pidfd is not available (kernels older than 5.3)
+const EXIT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Waits until the process identified by (`pid`, `start_time`) has exited.
+/// Works for processes that are not children of the daemon, using a pidfd when
+/// the kernel supports it and polling `/proc` otherwise.
+pub async fn wait_for_exit(pid: i32, start_time: u64) {
+    match pidfd_open(pid) {
+        Ok(fd) => {
+            // The PID may have been recycled before the pidfd was opened
+            if !is_same_process(pid, start_time) {
+                return;
+            }
+            match AsyncFd::with_interest(fd, Interest::READABLE) {
+                Ok(async_fd) => {
+                    // A pidfd becomes readable once the process terminates
+                    if async_fd.readable().await.is_ok() {
+                        return;
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to register pidfd for PID {}: {}. Polling instead.", pid, e)
+                }
+            }
+        }
+        Err(e) => debug!("pidfd_open failed for PID {}: {}. Polling instead.", pid, e),
+    }
+
+    while is_same_process(pid, start_time) {
+        sleep(EXIT_POLL_INTERVAL).await;
+    }
+}
+
+/// Waits until no process with `pid` exists anymore, for processes whose start time is
+/// unknown. A recycled PID delays the return, so prefer `wait_for_exit` when possible.
+pub async fn wait_for_pid_exit(pid: i32) {
+    while kill(Pid::from_raw(pid), None) != Err(Errno::ESRCH) {
+        sleep(EXIT_POLL_INTERVAL).await;
+    }
+}
+
+/// Sends `signal` to every process of a task: its process group if it has one (tasks
+/// started by gavel-shim), otherwise only its main process
+pub fn signal_task(task: &TaskMeta, signal: Signal) -> nix::Result<()> {
+    match (task.pgid, task.pid) {
+        (Some(pgid), _) => killpg(Pid::from_raw(pgid), signal),
+        (None, Some(pid)) => kill(Pid::from_raw(pid), signal),
+        (None, None) => Err(Errno::ESRCH),
+    }
+}
+
+/// Whether any process of the process group is still around
+pub fn group_alive(pgid: i32) -> bool {
+    killpg(Pid::from_raw(pgid), None) != Err(Errno::ESRCH)
+}
+
+/// Sends SIGTERM to all processes of a running task and SIGKILL if the task is still
+/// running `grace` later. The task's monitor records the outcome once it has exited.
+pub fn terminate_task(state: &DaemonState, task: &TaskMeta, grace: Duration) -> Result<()> {
+    match signal_task(task, Signal::SIGTERM) {
+        Ok(_) | Err(Errno::ESRCH) => {} // ESRCH: already exited, the monitor records it
+        Err(e) => {
+            let context = format!("Failed to send SIGTERM to task {}", task.id);
+            return Err(anyhow::Error::new(e).context(context));
+        }
+    }
+
+    let state = state.clone();
+    let task = task.clone();
+    tokio::spawn(async move {
+        sleep(grace).await;
+        // Still the same run of the task: same main process, not yet marked as ended
+        let still_running = state
+            .get_task(task.id)
+            .await
+            .is_some_and(|t| t.state == TaskState::Running && t.pid == task.pid);
+        if still_running {
+            warn!("Task {} is still running {:?} after SIGTERM, sending SIGKILL.", task.id, grace);
+            if let Err(e) = signal_task(&task, Signal::SIGKILL) {
+                if e != Errno::ESRCH {
+                    error!("Failed to send SIGKILL to task {}: {}", task.id, e);
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Waits until no process of the group is left, sending SIGKILL to the group once `grace`
+/// has passed. Used when the supervisor of a task is gone but its processes might not be.
+pub async fn wait_for_group_exit(pgid: i32, grace: Duration) {
+    let mut waited = Duration::ZERO;
+    let mut killed = false;
+    while group_alive(pgid) {
+        if !killed && waited >= grace {
+            warn!("Processes of group {} are still alive, sending SIGKILL.", pgid);
+            let _ = killpg(Pid::from_raw(pgid), Signal::SIGKILL);
+            killed = true;
+        }
+        sleep(EXIT_POLL_INTERVAL).await;
+        waited += EXIT_POLL_INTERVAL;
+    }
+}
+
+fn pidfd_open(pid: i32) -> io::Result<OwnedFd> {
+    // SAFETY: pidfd_open takes a PID and flags and returns a new file descriptor or -1
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: the descriptor was just created and is owned by nobody else
+    Ok(unsafe { OwnedFd::from_raw_fd(fd as i32) })
+}
@@ -0,0 +1,209 @@
+// src/daemon/retry.rs
+// Automatic retries of failed tasks
+use crate::daemon::state::DaemonState;
+use anyhow::Result;
+use gavel_core::utils::models::{QueueMeta, RetryPolicy, TaskMeta, TaskState};
+use gavel_core::utils::time::{format_duration_secs, now_secs};
+use log::{error, info};
+
+/// Retry policy of a task: its own policy, otherwise the default of its queue
+pub fn effective_retry_policy(task: &TaskMeta, queue: Option<&QueueMeta>) -> Option<RetryPolicy> {
+    task.retry_policy.clone().or_else(|| queue.and_then(|q| q.default_retry_policy.clone()))
+}
+
+/// Log file of an attempt (counted from 1). The first attempt writes to the task's original
+/// log file, later ones to `<name>.attempt<N>.log` next to it.
+pub fn attempt_log_path(first_log: &str, attempt: u32) -> String {
+    if attempt <= 1 {
+        return first_log.to_string();
+    }
+    let stem = first_log.strip_suffix(".log").unwrap_or(first_log);
+    format!("{}.attempt{}.log", stem, attempt)
+}
+
+/// Adds the run of a task that just ended with `outcome` to its attempt history. If the run
+/// failed and the retry policy allows another attempt, the task is set to Retrying and true
+/// is returned; otherwise the caller sets the final state.
+pub async fn record_attempt(
+    state: &DaemonState,
+    task_id: u64,
+    task_name: &str,
+    outcome: &TaskState,
+    reason: Option<String>,
+) -> bool {
+    let task = match state.push_task_attempt(task_id, outcome.clone(), reason.clone()).await {
+        Ok(task) => task,
+        Err(e) => {
+            error!("Failed to record attempt of task '{}' (ID: {}): {}", task_name, task_id, e);
+            return false;
+        }
+    };
+    let queue = state.get_queue(&task.queue).await;
+    let Some(policy) = effective_retry_policy(&task, queue.as_ref()) else {
+        return false;
+    };
+    if !should_retry(&task, outcome, &policy) {
+        return false;
+    }
+    let attempts = task.attempts.len() as u32;
+
+    let next_log = attempt_log_path(&task.attempts[0].log_path, attempts + 1);
+    let retry_at = now_secs().saturating_add(policy.backoff_secs);
+    let retry_reason = format!(
+        "Attempt {}/{} failed: {}. Retrying in {}.",
+        attempts,
+        policy.max_attempts,
+        reason.as_deref().unwrap_or("unknown reason"),
+        format_duration_secs(policy.backoff_secs)
+    );
+    if let Err(e) = state.set_task_retry(task_id, retry_at, next_log).await {
+        error!("Failed to schedule retry of task '{}' (ID: {}): {}", task_name, task_id, e);
+        return false;
+    }
+    if let Err(e) =
+        state.update_task_state(task_id, TaskState::Retrying, None, Some(retry_reason)).await
+    {
+        error!("Failed to set task '{}' (ID: {}) to Retrying: {}", task_name, task_id, e);
+        return false;
+    }
+    true
+}
+
+// Whether a task gets another attempt under `policy` after a run that ended with `outcome`.
+// `task.attempts` already includes that run.
+fn should_retry(task: &TaskMeta, outcome: &TaskState, policy: &RetryPolicy) -> bool {
+    if *outcome != TaskState::Failed || task.kill_requested {
+        return false;
+    }
+    let attempts = task.attempts.len() as u32;
+    if attempts >= policy.max_attempts {
+        info!(
+            "Task '{}' (ID: {}) failed on attempt {}/{}, not retrying.",
+            task.name, task.id, attempts, policy.max_attempts
+        );
+        return false;
+    }
+    match &task.exit_info {
+        Some(exit_info) if !policy.matches(exit_info) => {
+            info!(
+                "Task '{}' (ID: {}) failed with {:?}/{:?}, which its retry policy does not cover.",
+                task.name, task.id, exit_info.exit_code, exit_info.signal
+            );
+            false
+        }
+        // Without an exit status (e.g. the shim died, or the task was re-adopted after a
+        // restart) only a policy without exit code and signal filters applies
+        None if !policy.exit_codes.is_empty() || !policy.signals.is_empty() => {
+            info!(
+                "Task '{}' (ID: {}) has no exit status to check against its retry policy.",
+                task.name, task.id
+            );
+            false
+        }
+        _ => true,
+    }
+}
+
+/// Puts Retrying tasks whose backoff has passed back into their queue's waiting list
+pub async fn release_due_retries(state: &DaemonState) -> Result<()> {
+    let now = now_secs();
+    for task in state.get_all_tasks().await {
+        if task.state == TaskState::Retrying && task.retry_at.is_none_or(|at| at <= now) {
+            info!(
+                "Task '{}' (ID: {}) is back in queue '{}' for attempt {}.",
+                task.name,
+                task.id,
+                task.queue,
+                task.attempts.len() + 1
+            );
+            state.update_task_state(task.id, TaskState::Waiting, None, None).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gavel_core::utils::models::{ExitInfo, TaskAttempt};
+
+    // A task that has run `attempts` times, the last run ending with `exit_info`
+    fn failed_task(attempts: u32, exit_info: Option<ExitInfo>) -> TaskMeta {
+        TaskMeta {
+            id: 1,
+            name: "train".to_string(),
+            attempts: (1..=attempts)
+                .map(|attempt| TaskAttempt { attempt, ..Default::default() })
+                .collect(),
+            exit_info,
+            ..Default::default()
+        }
+    }
+
+    fn exited(exit_code: i32) -> Option<ExitInfo> {
+        Some(ExitInfo { exit_code: Some(exit_code), ..Default::default() })
+    }
+
+    fn signaled(signal: i32) -> Option<ExitInfo> {
+        Some(ExitInfo { signal: Some(signal), ..Default::default() })
+    }
+
+    fn policy(max_attempts: u32, exit_codes: &[i32], signals: &[i32]) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            backoff_secs: 0,
+            exit_codes: exit_codes.to_vec(),
+            signals: signals.to_vec(),
+        }
+    }
+
+    #[test]
+    fn retries_until_max_attempts() {
+        let policy = policy(3, &[], &[]);
+        assert!(should_retry(&failed_task(1, exited(1)), &TaskState::Failed, &policy));
+        assert!(should_retry(&failed_task(2, exited(1)), &TaskState::Failed, &policy));
+        assert!(!should_retry(&failed_task(3, exited(1)), &TaskState::Failed, &policy));
+        let single = RetryPolicy { max_attempts: 1, ..policy };
+        assert!(!should_retry(&failed_task(1, exited(1)), &TaskState::Failed, &single));
+    }
+
+    #[test]
+    fn only_failures_are_retried() {
+        let policy = policy(3, &[], &[]);
+        let task = failed_task(1, exited(0));
+        assert!(!should_retry(&task, &TaskState::Finished, &policy));
+        assert!(!should_retry(&task, &TaskState::TimedOut, &policy));
+        assert!(!should_retry(&task, &TaskState::Cancelled, &policy));
+
+        let mut killed = failed_task(1, signaled(15));
+        killed.kill_requested = true;
+        assert!(!should_retry(&killed, &TaskState::Failed, &policy));
+    }
+
+    #[test]
+    fn exit_code_and_signal_filters() {
+        let policy = policy(3, &[137, 2], &[9]);
+        assert!(should_retry(&failed_task(1, exited(137)), &TaskState::Failed, &policy));
+        assert!(should_retry(&failed_task(1, exited(2)), &TaskState::Failed, &policy));
+        assert!(should_retry(&failed_task(1, signaled(9)), &TaskState::Failed, &policy));
+        assert!(!should_retry(&failed_task(1, exited(1)), &TaskState::Failed, &policy));
+        assert!(!should_retry(&failed_task(1, signaled(15)), &TaskState::Failed, &policy));
+    }
+
+    #[test]
+    fn runs_without_exit_status() {
+        // E.g. a task re-adopted after a daemon restart: only unfiltered policies apply
+        let task = failed_task(1, None);
+        assert!(should_retry(&task, &TaskState::Failed, &policy(2, &[], &[])));
+        assert!(!should_retry(&task, &TaskState::Failed, &policy(2, &[1], &[])));
+        assert!(!should_retry(&task, &TaskState::Failed, &policy(2, &[], &[9])));
+        assert!(!should_retry(&failed_task(2, None), &TaskState::Failed, &policy(2, &[], &[])));
+    }
+
+    #[test]
+    fn attempt_log_paths() {
+        assert_eq!(attempt_log_path("/tmp/gavel_logs/7.log", 1), "/tmp/gavel_logs/7.log");
+        assert_eq!(attempt_log_path("/tmp/gavel_logs/7.log", 3), "/tmp/gavel_logs/7.attempt3.log");
+        assert_eq!(attempt_log_path("/tmp/out", 2), "/tmp/out.attempt2.log");
+    }
+}
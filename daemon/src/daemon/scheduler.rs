@@ -1,106 +1,412 @@
+use crate::daemon::dependencies::{self, DependencyStatus};
+use crate::daemon::process;
+use crate::daemon::retry;
 use crate::daemon::state::DaemonState;
-use anyhow::Result;
+use crate::daemon::walltime;
+use anyhow::{Context, Result};
 use gavel_core::gpu::monitor::GpuStats; // Assuming GpuStats is here
-use gavel_core::utils::models::{MemoryRequirementType, ResourceLimit, TaskMeta, TaskState}; // Import necessary models, ResourceLimit, MemoryRequirementType
+use gavel_core::rpc::message::{GpuCheck, GpuProblem, TaskExplanation};
+use gavel_core::utils::fair_share;
+use gavel_core::utils::models::{
+    GpuMode, GpuRequirement, GpuShare, MemoryRequirementType, PendingReason, PlacementStrategy,
+    QueueMeta, ResourceLimit, TaskMeta, TaskState,
+}; // Import necessary models, ResourceLimit, MemoryRequirementType
+use gavel_core::utils::spool::{self, ShimPidInfo};
+use gavel_core::utils::time::now_secs;
 use gavel_core::utils::DEFAULT_WAITING_QUEUE_NAME; // Import the constant
-use log::{error, info, warn};
+use log::{debug, error, info, warn};
 use std::collections::{HashMap, HashSet}; // Add import for HashSet and HashMap
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::time::Duration;
 use tokio::fs::File; // For file operations
 use tokio::process::Command; // For launching processes
 use tokio::time::sleep; // For redirecting output
 
+// Supervisor binary that runs every task, see shim/src/main.rs
+const SHIM_BINARY: &str = "gavel-shim";
+
+/// Scheduler settings, built from the daemon config
+#[derive(Debug, Clone)]
+pub struct SchedulerConfig {
+    /// Grace period between SIGTERM and SIGKILL for preempted tasks. None disables preemption.
+    pub preemption_grace: Option<Duration>,
+    /// Grace period between SIGTERM and SIGKILL for tasks that exceed their max runtime
+    pub timeout_grace: Duration,
+    /// Serve queues by fair share (GPU time used against their weight) instead of priority.
+    /// Tasks within a queue keep their priority and submission order.
+    pub fair_share: bool,
+    /// Half-life of the GPU time queues are charged for
+    pub fair_share_half_life_secs: u64,
+    /// Longest time between two scheduling cycles when nothing wakes the scheduler
+    pub interval: Duration,
+}
+
+// How long GPUs freed by preemption stay reserved after the grace period has passed
+const RESERVATION_SLACK: Duration = Duration::from_secs(30);
+
 // 调度器的主函数，在一个单独的 Tokio 任务中运行
-pub async fn run_scheduler(state: DaemonState) {
-    info!("Scheduler started.");
+pub async fn run_scheduler(state: DaemonState, config: SchedulerConfig) {
+    info!("Scheduler started (interval: {:?}).", config.interval);
     loop {
-        // 1. 更新 GPU 状态 (Now using the method from DaemonState)
-        match state.update_all_gpu_stats().await {
-            Ok(_) => { /* GPU stats updated successfully */ }
-            Err(e) => error!("Failed to update GPU stats: {}", e),
-        }
-
-        // 2. 尝试调度任务
-        match schedule_tasks(&state).await {
+        // 1. 尝试调度任务 (GPU 状态由 monitor::run_gpu_monitor 更新)
+        match schedule_tasks(&state, &config).await {
             Ok(_) => { /* Scheduling cycle completed */ }
             Err(e) => error!("Error during scheduling cycle: {}", e),
         }
 
-        // 3. 尝试更新任务
+        // 2. 尝试更新任务
         match update_tasks(&state).await {
             Ok(_) => { /* Updating cycle completed */ }
             Err(e) => error!("Error during updating cycle: {}", e),
         }
 
-        // 等待一段时间再进行下一轮调度
-        sleep(Duration::from_secs(3)).await; // Adjust interval as needed
+        // 3. 停止超过最长运行时间的任务
+        if let Err(e) = walltime::enforce_max_runtime(&state, config.timeout_grace).await {
+            error!("Error while enforcing max runtimes: {}", e);
+        }
+
+        // 等待状态变化唤醒调度器, 最多等待一个调度间隔
+        tokio::select! {
+            _ = state.wait_for_wakeup() => {}
+            _ = sleep(config.interval) => {}
+        }
     }
 }
 
-// 新增辅助函数：检查 GPU 是否满足队列的资源限制
-fn is_gpu_qualifying_for_queue(gpu_id: u32, gpu_stat: &GpuStats, limit: &ResourceLimit) -> bool {
+// 新增辅助函数：检查 GPU 是否满足队列的资源限制, 返回不满足的条件 (为空表示符合)
+fn queue_limit_problems(
+    gpu_id: u32,
+    gpu_stat: &GpuStats,
+    limit: &ResourceLimit,
+) -> Vec<GpuProblem> {
+    let mut problems = Vec::new();
+
     // 1. 检查显存要求
     let free_memory_mb = gpu_stat.memory_usage.free / (1024 * 1024); // Convert bytes to MB
     let total_memory_mb = gpu_stat.memory_usage.total / (1024 * 1024); // Convert bytes to MB
 
-    let memory_ok = match limit.memory_requirement_type {
-        MemoryRequirementType::Ignore => true,
-        MemoryRequirementType::AbsoluteMb => free_memory_mb >= limit.memory_requirement_value,
+    let required_mb = match limit.memory_requirement_type {
+        MemoryRequirementType::Ignore => 0,
+        MemoryRequirementType::AbsoluteMb => limit.memory_requirement_value,
         MemoryRequirementType::Percentage => {
             if total_memory_mb == 0 {
                 warn!(
                     "GPU {} has 0 total memory reported (after MB conversion), failing percentage requirement for a queue.",
                     gpu_id
                 );
-                false
+                u64::MAX
             } else {
-                let required_free_mb_float =
-                    (limit.memory_requirement_value as f64 / 100.0) * total_memory_mb as f64;
-                (free_memory_mb as f64) >= required_free_mb_float
+                ((limit.memory_requirement_value as f64 / 100.0) * total_memory_mb as f64).ceil()
+                    as u64
             }
         }
     };
+    if free_memory_mb < required_mb {
+        problems.push(GpuProblem::QueueMemoryLimit { free_mb: free_memory_mb, required_mb });
+    }
 
-    if !memory_ok {
-        // info!(
-        //     "GPU {} failed memory requirement. Limit type: {:?}, value: {}, Stat: Free {}MB, Total {}MB",
-        //     gpu_id,
-        //     limit.memory_requirement_type,
-        //     limit.memory_requirement_value,
-        //     free_memory_mb,
-        //     total_memory_mb
-        // );
-        return false;
+    // 2. 检查 GPU 利用率要求 (超出 0-100 的限制表示忽略)
+    if (0.0..=100.0).contains(&limit.max_gpu_utilization)
+        && (gpu_stat.core_usage as f32) > limit.max_gpu_utilization
+    {
+        problems.push(GpuProblem::QueueUtilizationLimit {
+            util_percent: gpu_stat.core_usage,
+            max_percent: limit.max_gpu_utilization,
+        });
     }
 
-    // 2. 检查 GPU 利用率要求
-    let utilization_ok = if limit.max_gpu_utilization < 0.0 || limit.max_gpu_utilization > 100.0 {
-        true // 忽略限制
-    } else {
-        // Assuming GpuStats has utilization_gpu_percent directly, or it needs to be core_usage
-        // Based on core/src/gpu/monitor.rs, it should be gpu_stat.core_usage
-        (gpu_stat.core_usage as f32) <= limit.max_gpu_utilization
+    problems
+}
+
+// What the tasks running on a GPU (or started on it earlier in the cycle) hold
+#[derive(Debug, Clone, Default)]
+struct GpuLoad {
+    exclusive: bool,    // Held by a task that does not share it
+    task_ids: Vec<u64>, // Tasks holding or sharing it
+    memory_mb: u64,     // Memory declared by the tasks sharing it
+    util_percent: u32,  // Utilization share declared by the tasks sharing it
+}
+
+impl GpuLoad {
+    fn add(&mut self, task_id: u64, share: Option<&GpuShare>) {
+        self.task_ids.push(task_id);
+        match share {
+            Some(share) => {
+                self.memory_mb += share.memory_mb;
+                self.util_percent += share.util_percent.unwrap_or(0) as u32;
+            }
+            None => self.exclusive = true,
+        }
+    }
+
+    fn remove(&mut self, task_id: u64, share: Option<&GpuShare>) {
+        self.task_ids.retain(|id| *id != task_id);
+        match share {
+            Some(share) => {
+                self.memory_mb = self.memory_mb.saturating_sub(share.memory_mb);
+                self.util_percent =
+                    self.util_percent.saturating_sub(share.util_percent.unwrap_or(0) as u32);
+            }
+            None => self.exclusive = false,
+        }
+    }
+
+    /// Memory (MB) left on the GPU for a task needing `share` of it (None: the whole GPU).
+    /// For sharing tasks it is the smaller of what the GPU reports and what the declarations
+    /// leave, so that tasks which have not allocated their memory yet are counted too.
+    fn free_mb(&self, share: Option<&GpuShare>, gpu_stat: &GpuStats) -> u64 {
+        let reported_free_mb = gpu_stat.memory_usage.free / (1024 * 1024);
+        if share.is_none() {
+            return reported_free_mb;
+        }
+        let declared_free_mb =
+            (gpu_stat.memory_usage.total / (1024 * 1024)).saturating_sub(self.memory_mb);
+        reported_free_mb.min(declared_free_mb)
+    }
+
+    /// What keeps a task needing `share` of the GPU (None: the whole GPU) from fitting next
+    /// to this load; empty if it fits
+    fn problems(&self, share: Option<&GpuShare>, gpu_stat: &GpuStats) -> Vec<GpuProblem> {
+        let in_use = || vec![GpuProblem::InUse { task_ids: self.task_ids.clone() }];
+        let Some(share) = share else {
+            return if self.task_ids.is_empty() { Vec::new() } else { in_use() };
+        };
+        if self.exclusive {
+            return in_use();
+        }
+        let mut problems = Vec::new();
+        let free_mb = self.free_mb(Some(share), gpu_stat);
+        if free_mb < share.memory_mb {
+            problems.push(GpuProblem::SharedMemory { free_mb, required_mb: share.memory_mb });
+        }
+        let util_percent = share.util_percent.unwrap_or(0) as u32;
+        if self.util_percent + util_percent > 100 {
+            problems.push(GpuProblem::SharedUtilization {
+                used_percent: self.util_percent,
+                required_percent: util_percent,
+            });
+        }
+        problems
+    }
+}
+
+// GPUs held by running tasks, across all queues
+fn running_gpu_loads(
+    all_tasks: &HashMap<u64, TaskMeta>,
+    queue_modes: &HashMap<String, GpuMode>,
+) -> HashMap<u32, GpuLoad> {
+    let mut gpu_loads: HashMap<u32, GpuLoad> = HashMap::new();
+    for task in all_tasks.values().filter(|t| t.state == TaskState::Running) {
+        let mode = queue_modes.get(&task.queue).copied().unwrap_or_default();
+        for gpu_id in &task.gpu_ids {
+            gpu_loads.entry(*gpu_id as u32).or_default().add(task.id, gpu_share_in(task, mode));
+        }
+    }
+    gpu_loads
+}
+
+// What a GPU lacks for the requirement: the model, the total memory and, given the memory
+// `free_mb` left on it for the task, the free memory. Without `free_mb` only what the GPU can
+// never offer is checked, which includes enough total memory for an absolute free memory
+// requirement.
+fn requirement_problems(
+    requirement: &GpuRequirement,
+    gpu_stat: &GpuStats,
+    free_mb: Option<u64>,
+) -> Vec<GpuProblem> {
+    let mut problems = Vec::new();
+    if let Some(model) = &requirement.model {
+        if !gpu_stat.name.to_lowercase().contains(&model.to_lowercase()) {
+            let gpu_model = gpu_stat.name.clone();
+            problems.push(GpuProblem::Model { model: gpu_model, required: model.clone() });
+        }
+    }
+
+    let total_mb = gpu_stat.memory_usage.total / (1024 * 1024);
+    let mut required_total_mb = requirement.min_total_memory_mb.unwrap_or(0);
+    if requirement.memory_requirement_type == MemoryRequirementType::AbsoluteMb {
+        required_total_mb = required_total_mb.max(requirement.memory_requirement_value);
+    }
+    if total_mb < required_total_mb {
+        problems.push(GpuProblem::TotalMemory { total_mb, required_mb: required_total_mb });
+    } else if let Some(free_mb) = free_mb {
+        let required_free_mb = match requirement.memory_requirement_type {
+            MemoryRequirementType::Ignore => 0,
+            MemoryRequirementType::AbsoluteMb => requirement.memory_requirement_value,
+            MemoryRequirementType::Percentage => {
+                ((requirement.memory_requirement_value as f64 / 100.0) * total_mb as f64).ceil()
+                    as u64
+            }
+        };
+        if free_mb < required_free_mb {
+            problems.push(GpuProblem::FreeMemory { free_mb, required_mb: required_free_mb });
+        }
+    }
+    problems
+}
+
+// Whether a GPU could ever meet the requirement
+fn gpu_can_meet(requirement: &GpuRequirement, gpu_stat: &GpuStats) -> bool {
+    requirement_problems(requirement, gpu_stat, None).is_empty()
+}
+
+// Why a GPU that passes the queue limits cannot take the task right now: the tasks holding
+// it (`load`), the task it is kept for by preemption or backfilling, and the task's
+// requirement. Empty if it can.
+fn task_gpu_problems(
+    task: &TaskMeta,
+    queue_meta: &QueueMeta,
+    gpu_stat: &GpuStats,
+    load: &GpuLoad,
+    reserved_for: Option<u64>,
+    backfill: Option<&BackfillReservation>,
+    now: u64,
+) -> Vec<GpuProblem> {
+    let mut problems = Vec::new();
+    if let Some(owner) = reserved_for.filter(|owner| *owner != task.id) {
+        problems.push(GpuProblem::Preempting { task_id: owner });
+    }
+    let runtime = walltime::effective_max_runtime(task, Some(queue_meta));
+    if let Some(reservation) = backfill.filter(|r| !r.admits(task.id, runtime, now)) {
+        problems.push(GpuProblem::Reserved {
+            task_id: reservation.task_id,
+            start_at: reservation.start_at,
+        });
+    }
+    let share = gpu_share_in(task, queue_meta.gpu_mode);
+    problems.extend(load.problems(share, gpu_stat));
+    if let Some(requirement) = &task.gpu_requirement {
+        let free_mb = load.free_mb(share, gpu_stat);
+        problems.extend(requirement_problems(requirement, gpu_stat, Some(free_mb)));
+    }
+    problems
+}
+
+// Orders the GPUs a task can take by preference. Best fit leaves the GPUs with the most free
+// memory to the tasks that need it, most free spreads the tasks. Ties go to the lower GPU ID.
+fn sort_for_placement(
+    gpus: &mut [(u32, GpuStats)],
+    gpu_loads: &HashMap<u32, GpuLoad>,
+    share: Option<&GpuShare>,
+    placement: PlacementStrategy,
+) {
+    let idle = GpuLoad::default();
+    gpus.sort_by_cached_key(|(gpu_id, gpu_stat)| {
+        let free_mb = gpu_loads.get(gpu_id).unwrap_or(&idle).free_mb(share, gpu_stat);
+        match placement {
+            PlacementStrategy::BestFit => (free_mb, *gpu_id),
+            PlacementStrategy::MostFree => (u64::MAX - free_mb, *gpu_id),
+        }
+    });
+}
+
+// GPUs a running task is charged for: tasks sharing GPUs pay for the part of the memory they
+// declared
+fn gpus_held(task: &TaskMeta, mode: GpuMode, gpu_stats: &HashMap<u32, GpuStats>) -> f64 {
+    let Some(share) = gpu_share_in(task, mode) else {
+        return task.gpu_ids.len() as f64;
     };
+    task.gpu_ids
+        .iter()
+        .map(|gpu_id| {
+            let total_mb = gpu_stats
+                .get(&(*gpu_id as u32))
+                .map_or(0, |gpu_stat| gpu_stat.memory_usage.total / (1024 * 1024));
+            if total_mb == 0 {
+                return 1.0;
+            }
+            (share.memory_mb as f64 / total_mb as f64).min(1.0)
+        })
+        .sum()
+}
 
-    if !utilization_ok {
-        // info!(
-        //     "GPU {} failed utilization requirement. Limit: {}%, Stat: {}% (core_usage)",
-        //     gpu_id,
-        //     limit.max_gpu_utilization,
-        //     gpu_stat.core_usage
-        // );
-        return false;
+// Per-GPU demand of a task: its declared share in shared queues, None (whole GPUs) otherwise
+fn gpu_share_in(task: &TaskMeta, mode: GpuMode) -> Option<&GpuShare> {
+    task.gpu_share.as_ref().filter(|_| mode == GpuMode::Shared)
+}
+
+// GPUs kept for the first blocked task of a queue while it waits for them
+#[derive(Debug, Clone, Copy)]
+struct BackfillReservation {
+    task_id: u64,
+    start_at: u64, // When the GPUs are expected to be free
+}
+
+impl BackfillReservation {
+    // Whether a task declaring `runtime` may use the GPU without delaying the reservation
+    fn admits(&self, task_id: u64, runtime: Option<u64>, now: u64) -> bool {
+        task_id == self.task_id
+            || runtime.is_some_and(|runtime| now.saturating_add(runtime) <= self.start_at)
+    }
+}
+
+// When each GPU held by running tasks is expected to be free: the latest end of its tasks,
+// using max runtimes as declared runtimes. None if one of them has no max runtime, or one so
+// large that its end is out of range.
+fn expected_gpu_release(
+    all_tasks: &HashMap<u64, TaskMeta>,
+    queues: &HashMap<String, QueueMeta>,
+) -> HashMap<u32, Option<u64>> {
+    let mut free_at: HashMap<u32, Option<u64>> = HashMap::new();
+    for task in all_tasks.values().filter(|t| t.state == TaskState::Running) {
+        let runtime = walltime::effective_max_runtime(task, queues.get(&task.queue));
+        let end =
+            task.start_time.zip(runtime).and_then(|(start, runtime)| start.checked_add(runtime));
+        for gpu_id in &task.gpu_ids {
+            let entry = free_at.entry(*gpu_id as u32).or_insert(Some(0));
+            *entry = entry.zip(end).map(|(a, b)| a.max(b));
+        }
     }
+    free_at
+}
 
-    true // GPU 符合所有条件
+// Picks the `count` GPUs among `candidates` expected to be free the soonest. Returns them with
+// the time all of them should be free, or None if there are too few or that time is unknown:
+// a reservation without an end would keep free GPUs from every other task indefinitely.
+fn plan_reservation(
+    count: usize,
+    candidates: &[u32],
+    free_now: &HashSet<u32>,
+    expected_free_at: &HashMap<u32, Option<u64>>,
+    now: u64,
+) -> Option<(Vec<u32>, u64)> {
+    if count == 0 || candidates.len() < count {
+        return None;
+    }
+    let mut by_free_at: Vec<(u32, Option<u64>)> = candidates
+        .iter()
+        .map(|gpu_id| {
+            let free_at = if free_now.contains(gpu_id) {
+                Some(now)
+            } else {
+                // A busy GPU without gavel tasks is used by something else, no telling when
+                expected_free_at.get(gpu_id).copied().flatten().map(|at| at.max(now))
+            };
+            (*gpu_id, free_at)
+        })
+        .collect();
+    by_free_at.sort_by_key(|(gpu_id, free_at)| (free_at.is_none(), *free_at, *gpu_id));
+    by_free_at.truncate(count);
+
+    let start_at =
+        by_free_at.iter().try_fold(now, |latest, (_, at)| at.map(|at| latest.max(at)))?;
+    Some((by_free_at.into_iter().map(|(gpu_id, _)| gpu_id).collect(), start_at))
 }
 
 // 辅助函数：执行调度逻辑 (Refactored to use DaemonState public methods)
-async fn schedule_tasks(state: &DaemonState) -> Result<()> {
-    // 1. 获取所有队列并按优先级排序
+async fn schedule_tasks(state: &DaemonState, config: &SchedulerConfig) -> Result<()> {
+    // 0. 取消依赖已无法满足的任务
+    dependencies::cancel_unsatisfiable_tasks(state).await?;
+    // 将退避结束的重试任务放回队列
+    retry::release_due_retries(state).await?;
+
+    // 1. 获取所有队列
     let mut queues = state.get_all_queues().await;
-    queues.sort_by(|a, b| b.priority.cmp(&a.priority)); // Descending priority
+    let queue_priorities: HashMap<String, u8> =
+        queues.iter().map(|q| (q.name.clone(), q.priority)).collect();
+    let queue_modes: HashMap<String, GpuMode> =
+        queues.iter().map(|q| (q.name.clone(), q.gpu_mode)).collect();
 
     // 2. 获取 GPU 分配和状态信息
     let ignored_gpus = state.get_ignored_gpus().await; // Ignored GPUs
@@ -110,11 +416,58 @@ async fn schedule_tasks(state: &DaemonState) -> Result<()> {
     let all_tasks: HashMap<u64, TaskMeta> =
         state.get_all_tasks().await.into_iter().map(|t| (t.id, t)).collect();
 
+    // GPUs held by running tasks, across all queues; tasks started below are added
+    let mut gpu_loads = running_gpu_loads(&all_tasks, &queue_modes);
+
+    // Charge every queue for the GPU time of its running tasks
+    let mut gpus_in_use: HashMap<String, f64> = HashMap::new();
+    for task in all_tasks.values().filter(|t| t.state == TaskState::Running) {
+        let mode = queue_modes.get(&task.queue).copied().unwrap_or_default();
+        *gpus_in_use.entry(task.queue.clone()).or_default() +=
+            gpus_held(task, mode, &all_current_gpu_stats);
+    }
+    state.accrue_queue_usage(&gpus_in_use, config.fair_share_half_life_secs).await;
+
+    // 按公平共享 (或优先级) 排序队列
+    if config.fair_share {
+        // Queues that got the least GPU time for their weight first, then by priority
+        queues = state.get_all_queues().await;
+        let shares = fair_share::share_status(&queues);
+        let ratio = |q: &QueueMeta| shares.get(&q.name).map_or(f64::INFINITY, |s| s.ratio());
+        queues.sort_by(|a, b| {
+            ratio(a).total_cmp(&ratio(b)).then_with(|| b.priority.cmp(&a.priority))
+        });
+    } else {
+        queues.sort_by_key(|q| std::cmp::Reverse(q.priority)); // Descending priority
+    }
+
     let mut started_tasks_this_cycle = 0;
+    // GPUs being freed by preemption, kept for the task they are freed for while it waits
+    let mut reserved_gpus: HashMap<u32, u64> = state
+        .get_gpu_reservations()
+        .into_iter()
+        .filter(|(_, task_id)| {
+            all_tasks.get(task_id).is_some_and(|t| t.state == TaskState::Waiting)
+        })
+        .collect();
+
+    // Backfilling: when a queue's first blocked GPU task has to wait, GPUs are kept for it and
+    // later tasks may only use them if they will be done by the time it is expected to start
+    let now = now_secs();
+    let queues_by_name: HashMap<String, QueueMeta> =
+        queues.iter().map(|q| (q.name.clone(), q.clone())).collect();
+    let mut expected_free_at = expected_gpu_release(&all_tasks, &queues_by_name);
+    let mut backfill: HashMap<u32, BackfillReservation> = HashMap::new();
+
+    // Why each waiting task that is not started this cycle has to wait
+    let mut pending: HashMap<u64, PendingReason> = HashMap::new();
 
     'queue_loop: for queue_meta in queues {
         // If this is the default waiting queue, skip it, as tasks here require explicit action to run.
         if queue_meta.name == DEFAULT_WAITING_QUEUE_NAME {
+            for task_id in &queue_meta.waiting_task_ids {
+                pending.insert(*task_id, PendingReason::NotRunnable);
+            }
             continue 'queue_loop;
         }
 
@@ -127,7 +480,8 @@ async fn schedule_tasks(state: &DaemonState) -> Result<()> {
 
             let gpu_id_u32 = *gpu_id as u32;
             if let Some(gpu_stat) = all_current_gpu_stats.get(&gpu_id_u32) {
-                if is_gpu_qualifying_for_queue(gpu_id_u32, gpu_stat, &queue_meta.resource_limit) {
+                let limit = &queue_meta.resource_limit;
+                if queue_limit_problems(gpu_id_u32, gpu_stat, limit).is_empty() {
                     available_gpus_for_queue.push((gpu_id_u32, gpu_stat.clone()));
                 }
             }
@@ -137,8 +491,15 @@ async fn schedule_tasks(state: &DaemonState) -> Result<()> {
         let mut tasks_in_queue_to_process = Vec::new();
         for task_id in &queue_meta.waiting_task_ids {
             if let Some(task) = all_tasks.get(task_id) {
-                if task.state == TaskState::Waiting {
+                // Tasks wait until all their dependencies are satisfied
+                if task.state != TaskState::Waiting {
+                    continue;
+                }
+                let status = dependencies::dependency_status(task, &all_tasks);
+                if status == DependencyStatus::Satisfied {
                     tasks_in_queue_to_process.push(task.clone());
+                } else {
+                    pending.insert(task.id, PendingReason::Dependencies);
                 }
             }
         }
@@ -147,13 +508,22 @@ async fn schedule_tasks(state: &DaemonState) -> Result<()> {
         });
 
         let mut assigned_gpus_in_cycle: HashSet<u32> = HashSet::new();
+        let mut queue_has_backfill_reservation = false;
+        // Tasks started from this queue earlier in the cycle are not in `running_task_ids` yet
+        let mut started_in_queue = 0;
 
-        for task in tasks_in_queue_to_process {
-            if queue_meta.running_task_ids.len() >= queue_meta.max_concurrent as usize {
+        for (position, task) in tasks_in_queue_to_process.iter().enumerate() {
+            if queue_meta.running_task_ids.len() + started_in_queue
+                >= queue_meta.max_concurrent as usize
+            {
                 info!(
                     "Queue {} reached max concurrent tasks ({}). Task {} ({}) will wait.",
                     queue_meta.name, queue_meta.max_concurrent, task.name, task.id
                 );
+                let reason = PendingReason::QueueFull { max_concurrent: queue_meta.max_concurrent };
+                for waiting in &tasks_in_queue_to_process[position..] {
+                    pending.insert(waiting.id, reason.clone());
+                }
                 continue 'queue_loop; 
             }
 
@@ -165,6 +535,7 @@ async fn schedule_tasks(state: &DaemonState) -> Result<()> {
                              match launch_task_process(state, updated_task_meta).await {
                                 Ok(_) => {
                                     started_tasks_this_cycle += 1;
+                                    started_in_queue += 1;
                                     info!("CPU-only task {} (ID: {}) launched successfully.", task.name, task.id);
                                 }
                                 Err(e) => {
@@ -189,9 +560,24 @@ async fn schedule_tasks(state: &DaemonState) -> Result<()> {
                 continue; 
             }
 
+            let share = gpu_share_in(task, queue_meta.gpu_mode);
+            let runtime = walltime::effective_max_runtime(task, Some(&queue_meta));
             let mut selected_gpu_ids_for_task: Vec<u8> = Vec::new();
             let mut temp_available_gpus = available_gpus_for_queue.clone();
-            temp_available_gpus.retain(|(gpu_id, _)| !assigned_gpus_in_cycle.contains(gpu_id));
+            let idle = GpuLoad::default();
+            temp_available_gpus.retain(|(gpu_id, gpu_stat)| {
+                task_gpu_problems(
+                    task,
+                    &queue_meta,
+                    gpu_stat,
+                    gpu_loads.get(gpu_id).unwrap_or(&idle),
+                    reserved_gpus.get(gpu_id).copied(),
+                    backfill.get(gpu_id),
+                    now,
+                )
+                .is_empty()
+            });
+            sort_for_placement(&mut temp_available_gpus, &gpu_loads, share, queue_meta.placement);
 
             if temp_available_gpus.len() >= task.gpu_require as usize {
                 for (gpu_id, _gpu_stat) in temp_available_gpus.iter().take(task.gpu_require as usize) {
@@ -206,6 +592,10 @@ async fn schedule_tasks(state: &DaemonState) -> Result<()> {
                 );
                 for gpu_id in &selected_gpu_ids_for_task {
                     assigned_gpus_in_cycle.insert(*gpu_id as u32);
+                    gpu_loads.entry(*gpu_id as u32).or_default().add(task.id, share);
+                    let free_at = expected_free_at.entry(*gpu_id as u32).or_insert(Some(now));
+                    let end = runtime.and_then(|r| now.checked_add(r));
+                    *free_at = free_at.zip(end).map(|(a, b)| a.max(b));
                 }
 
                 match state.update_task_state(task.id, TaskState::Running, Some(selected_gpu_ids_for_task.clone()), None).await {
@@ -214,6 +604,7 @@ async fn schedule_tasks(state: &DaemonState) -> Result<()> {
                             match launch_task_process(state, updated_task_meta).await {
                                 Ok(_) => {
                                     started_tasks_this_cycle += 1;
+                                    started_in_queue += 1;
                                     info!("GPU Task {} (ID: {}) launched successfully with GPUs {:?}.", task.name, task.id, selected_gpu_ids_for_task);
                                 }
                                 Err(e) => {
@@ -235,25 +626,285 @@ async fn schedule_tasks(state: &DaemonState) -> Result<()> {
                         error!("Failed to update state to Running for task {} (ID: {}): {}", task.name, task.id, e);
                         for gpu_id in &selected_gpu_ids_for_task {
                             assigned_gpus_in_cycle.remove(&(*gpu_id as u32));
+                            gpu_loads.entry(*gpu_id as u32).or_default().remove(task.id, share);
                         }
                     }
                 }
-            } 
+            } else {
+                // GPUs of the queue that could ever take the task
+                let usable_gpus: Vec<u32> = queue_meta
+                    .allocated_gpus
+                    .iter()
+                    .map(|gpu_id| *gpu_id as u32)
+                    .filter(|gpu_id| !ignored_gpus.contains(gpu_id))
+                    .filter(|gpu_id| all_current_gpu_stats.contains_key(gpu_id))
+                    .collect();
+                let eligible_gpus: Vec<u32> = usable_gpus
+                    .iter()
+                    .copied()
+                    .filter(|gpu_id| {
+                        task.gpu_requirement.as_ref().is_none_or(|requirement| {
+                            gpu_can_meet(requirement, &all_current_gpu_stats[gpu_id])
+                        })
+                    })
+                    .collect();
+                let mut reason = if usable_gpus.is_empty() {
+                    PendingReason::NoGpus
+                } else if eligible_gpus.len() < task.gpu_require as usize {
+                    PendingReason::NeverFits {
+                        required: task.gpu_require,
+                        eligible: eligible_gpus.len() as u8,
+                    }
+                } else {
+                    PendingReason::NotEnoughGpus {
+                        required: task.gpu_require,
+                        free: temp_available_gpus.len() as u8,
+                    }
+                };
+
+                let mut preempting = false;
+                if let Some(grace) = config.preemption_grace {
+                    // Not enough qualifying GPUs: take contested ones back from lower-priority queues
+                    let mut excluded_gpus: HashSet<u32> = ignored_gpus.clone();
+                    excluded_gpus.extend(temp_available_gpus.iter().map(|(gpu_id, _)| *gpu_id));
+                    excluded_gpus.extend(assigned_gpus_in_cycle.iter().copied());
+                    excluded_gpus.extend(
+                        reserved_gpus
+                            .iter()
+                            .filter(|(_, owner)| **owner != task.id)
+                            .map(|(gpu_id, _)| *gpu_id),
+                    );
+                    // Freeing a GPU the task can never run on would not help it
+                    if let Some(requirement) = &task.gpu_requirement {
+                        excluded_gpus.extend(
+                            all_current_gpu_stats
+                                .iter()
+                                .filter(|(_, gpu_stat)| !gpu_can_meet(requirement, gpu_stat))
+                                .map(|(gpu_id, _)| *gpu_id),
+                        );
+                    }
+                    let missing = task.gpu_require as usize - temp_available_gpus.len();
+
+                    if let Some((gpus, victims)) = select_preemption_victims(
+                        &queue_meta,
+                        missing,
+                        &all_tasks,
+                        &queue_priorities,
+                        &excluded_gpus,
+                    ) {
+                        // Keep the GPUs (and the ones already being freed) for this task
+                        state.reserve_gpus(&gpus, task.id, grace + RESERVATION_SLACK);
+                        reserved_gpus.extend(gpus.iter().map(|gpu_id| (*gpu_id, task.id)));
+                        preempting = true;
+                        for victim_id in victims {
+                            let victim = &all_tasks[&victim_id];
+                            info!(
+                                "Preempting task {} (ID: {}) from queue {} for task {} (ID: {}) of queue {}",
+                                victim.name, victim.id, victim.queue, task.name, task.id, queue_meta.name
+                            );
+                            if let Err(e) = preempt_task(state, victim, grace).await {
+                                error!(
+                                    "Failed to preempt task {} (ID: {}): {}",
+                                    victim.name, victim.id, e
+                                );
+                            }
+                        }
+                    }
+                }
+                // Otherwise keep GPUs for the first blocked task of the queue (EASY backfill)
+                if !preempting && !queue_has_backfill_reservation {
+                    let fits_now: HashSet<u32> =
+                        temp_available_gpus.iter().map(|(gpu_id, _)| *gpu_id).collect();
+                    let candidates: Vec<u32> = eligible_gpus
+                        .iter()
+                        .copied()
+                        .filter(|gpu_id| {
+                            reserved_gpus.get(gpu_id).is_none_or(|owner| *owner == task.id)
+                        })
+                        .filter(|gpu_id| backfill.get(gpu_id).is_none_or(|r| r.task_id == task.id))
+                        .collect();
+                    if let Some((gpus, start_at)) = plan_reservation(
+                        task.gpu_require as usize,
+                        &candidates,
+                        &fits_now,
+                        &expected_free_at,
+                        now,
+                    ) {
+                        debug!(
+                            "Keeping GPUs {:?} for task {} (ID: {}) of queue {}, expected to start in {}s",
+                            gpus, task.name, task.id, queue_meta.name, start_at - now
+                        );
+                        let reservation = BackfillReservation { task_id: task.id, start_at };
+                        backfill.extend(gpus.iter().map(|gpu_id| (*gpu_id, reservation)));
+                        queue_has_backfill_reservation = true;
+                        reason = PendingReason::Reserved { start_at };
+                    }
+                }
+                if reserved_gpus.values().any(|owner| *owner == task.id) {
+                    reason = PendingReason::Preempting;
+                }
+                pending.insert(task.id, reason);
+            }
         } 
     } 
 
+    state.set_pending_reasons(pending).await;
+    state.set_backfill_reservations(
+        backfill.into_iter().map(|(gpu_id, r)| (gpu_id, (r.task_id, r.start_at))).collect(),
+    );
+
     if started_tasks_this_cycle > 0 {
         info!("Scheduler cycle finished. Started {} tasks.", started_tasks_this_cycle);
     }
     Ok(())
 }
 
+/// Why a task is still waiting: the reason recorded by the last scheduling cycle and, for a
+/// waiting GPU task, what each GPU of its queue lacks for it as of now. None if the task
+/// does not exist.
+pub async fn explain_task(state: &DaemonState, task_id: u64) -> Option<TaskExplanation> {
+    let task = state.get_task(task_id).await?;
+    let mut gpus = Vec::new();
+    let waiting_for_gpus = task.state == TaskState::Waiting && task.gpu_require > 0;
+    let queue = state.get_queue(&task.queue).await.filter(|_| waiting_for_gpus);
+    if let Some(queue_meta) = queue {
+        let queues = state.get_all_queues().await;
+        let queue_modes: HashMap<String, GpuMode> =
+            queues.iter().map(|q| (q.name.clone(), q.gpu_mode)).collect();
+        let all_tasks: HashMap<u64, TaskMeta> =
+            state.get_all_tasks().await.into_iter().map(|t| (t.id, t)).collect();
+        let gpu_loads = running_gpu_loads(&all_tasks, &queue_modes);
+        let ignored_gpus = state.get_ignored_gpus().await;
+        let all_current_gpu_stats = state.get_all_gpu_stats().await;
+        let reserved_gpus = state.get_gpu_reservations();
+        let backfill = state.get_backfill_reservations();
+        let now = now_secs();
+
+        let idle = GpuLoad::default();
+        for gpu_id in queue_meta.allocated_gpus.iter().map(|gpu_id| *gpu_id as u32) {
+            let mut problems = Vec::new();
+            if ignored_gpus.contains(&gpu_id) {
+                problems.push(GpuProblem::Ignored);
+            }
+            let gpu_stat = all_current_gpu_stats.get(&gpu_id);
+            match gpu_stat {
+                Some(gpu_stat) => {
+                    let limit = &queue_meta.resource_limit;
+                    problems.extend(queue_limit_problems(gpu_id, gpu_stat, limit));
+                    let reservation = backfill.get(&gpu_id).map(|(task_id, start_at)| {
+                        BackfillReservation { task_id: *task_id, start_at: *start_at }
+                    });
+                    problems.extend(task_gpu_problems(
+                        &task,
+                        &queue_meta,
+                        gpu_stat,
+                        gpu_loads.get(&gpu_id).unwrap_or(&idle),
+                        reserved_gpus.get(&gpu_id).copied(),
+                        reservation.as_ref(),
+                        now,
+                    ));
+                }
+                None => problems.push(GpuProblem::Unavailable),
+            }
+            let name = gpu_stat.map(|gpu_stat| gpu_stat.name.clone()).unwrap_or_default();
+            gpus.push(GpuCheck { gpu_id, name, problems });
+        }
+    }
+    Some(TaskExplanation { task_id, state: task.state, reason: task.pending_reason, gpus })
+}
+
+/// Chooses contested GPUs for a waiting task of `queue_meta` that is `missing` GPUs short.
+/// A GPU is contested if it belongs to the queue and every running task on it comes from a
+/// lower-priority queue. GPUs already being freed come first, then those whose tasks have the
+/// lowest queue priority, then those with the fewest tasks. Returns the chosen GPUs and the
+/// tasks that still have to be preempted, or None if preemption cannot free enough GPUs.
+fn select_preemption_victims(
+    queue_meta: &QueueMeta,
+    missing: usize,
+    all_tasks: &HashMap<u64, TaskMeta>,
+    queue_priorities: &HashMap<String, u8>,
+    excluded_gpus: &HashSet<u32>,
+) -> Option<(Vec<u32>, Vec<u64>)> {
+    let mut holders: HashMap<u32, Vec<&TaskMeta>> = HashMap::new();
+    for task in all_tasks.values().filter(|t| t.state == TaskState::Running) {
+        for gpu_id in &task.gpu_ids {
+            holders.entry(*gpu_id as u32).or_default().push(task);
+        }
+    }
+
+    // (gpu_id, max queue priority of its tasks, tasks not yet being preempted)
+    let mut candidates: Vec<(u32, u8, Vec<u64>)> = Vec::new();
+    for gpu_id in queue_meta.allocated_gpus.iter().map(|id| *id as u32) {
+        if excluded_gpus.contains(&gpu_id) {
+            continue;
+        }
+        let Some(tasks) = holders.get(&gpu_id) else {
+            continue; // Nothing to preempt, the GPU fails the queue limits on its own
+        };
+        let priorities: Vec<u8> =
+            tasks.iter().map(|t| queue_priorities.get(&t.queue).copied().unwrap_or(0)).collect();
+        if priorities.iter().any(|p| *p >= queue_meta.priority) {
+            continue;
+        }
+        let victims = tasks.iter().filter(|t| !t.preempted).map(|t| t.id).collect();
+        candidates.push((gpu_id, priorities.into_iter().max().unwrap_or(0), victims));
+    }
+
+    if candidates.len() < missing {
+        return None;
+    }
+    candidates.sort_by_key(|(_, max_priority, victims)| {
+        (!victims.is_empty(), *max_priority, victims.len())
+    });
+    candidates.truncate(missing);
+
+    let gpus = candidates.iter().map(|(gpu_id, _, _)| *gpu_id).collect();
+    let mut victims: Vec<u64> = candidates.into_iter().flat_map(|(_, _, v)| v).collect();
+    victims.sort_unstable();
+    victims.dedup();
+    Some((gpus, victims))
+}
+
+/// Stops a running task so its GPUs can go to a higher-priority queue.
+/// The task gets SIGTERM, then SIGKILL if it is still alive after `grace`. Once it has
+/// exited, its monitor puts it back into its queue as Waiting (see `record_task_exit`).
+async fn preempt_task(state: &DaemonState, task: &TaskMeta, grace: Duration) -> Result<()> {
+    if task.pid.is_none() {
+        return Err(anyhow::anyhow!("Task {} has no PID", task.id));
+    }
+    state.set_task_preempted(task.id, true).await?;
+    if let Err(e) = process::terminate_task(state, task, grace) {
+        state.set_task_preempted(task.id, false).await?;
+        return Err(e);
+    }
+    Ok(())
+}
+
+// A preempted task that has exited goes back to its queue instead of finishing.
+// Returns true if the task was requeued.
+async fn requeue_if_preempted(state: &DaemonState, task_id: u64, task_name: &str) -> bool {
+    let preempted =
+        state.get_task(task_id).await.is_some_and(|t| t.state == TaskState::Running && t.preempted);
+    if !preempted {
+        return false;
+    }
+    match state.requeue_preempted_task(task_id).await {
+        Ok(_) => info!("Preempted task '{}' (ID: {}) exited and was requeued.", task_name, task_id),
+        Err(e) => {
+            error!("Failed to requeue preempted task '{}' (ID: {}): {}", task_name, task_id, e)
+        }
+    }
+    true
+}
+
 async fn launch_task_process(state: &DaemonState, task: TaskMeta) -> Result<()> {
     info!(
         "Launching process for task '{}' (ID: {}), CMD: '{}', LOG: '{}', GPUS: {:?}",
         task.name, task.id, task.cmd, task.log_path, task.gpu_ids
     );
 
+    let shim = shim_path()?;
+
     let log_file = match File::create(&task.log_path).await {
         Ok(f) => f,
         Err(e) => {
@@ -261,16 +912,55 @@ async fn launch_task_process(state: &DaemonState, task: TaskMeta) -> Result<()>
         }
     };
 
-    let args = match shlex::split(&task.cmd) {
-        Some(a) if !a.is_empty() => a,
-        _ => {
-            return Err(anyhow::anyhow!("Failed to parse command for task {}: '{}'", task.id, task.cmd));
-        }
+    // In shell mode the whole command line goes to the shell, so pipes and `&&` work
+    let args = match &task.exec.shell {
+        Some(shell) => vec![shell.clone(), "-c".to_string(), task.cmd.clone()],
+        None => match shlex::split(&task.cmd) {
+            Some(a) if !a.is_empty() => a,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Failed to parse command for task {}: '{}'",
+                    task.id,
+                    task.cmd
+                ));
+            }
+        },
     };
 
-    let mut command = Command::new(&args[0]);
-    if args.len() > 1 {
-        command.args(&args[1..]);
+    // Files from an earlier run of the same task must not be mistaken for this one
+    let spool_dir = state.task_spool_dir(task.id);
+    tokio::fs::create_dir_all(&spool_dir)
+        .await
+        .with_context(|| format!("Failed to create spool directory {}", spool_dir.display()))?;
+    for file in [spool::PID_FILE, spool::STATUS_FILE] {
+        let _ = tokio::fs::remove_file(spool_dir.join(file)).await;
+    }
+
+    // gavel-shim --kill-grace <secs> <spool_dir> <program> [args...]
+    let mut command = Command::new(&shim);
+    command.arg("--kill-grace").arg(state.kill_grace().as_secs().to_string());
+    command.arg(&spool_dir).args(&args);
+
+    // Run the task where and with the environment it was submitted from
+    if let Some(cwd) = &task.exec.cwd {
+        if !Path::new(cwd).is_dir() {
+            return Err(anyhow::anyhow!(
+                "Working directory {} of task {} does not exist",
+                cwd,
+                task.id
+            ));
+        }
+        command.current_dir(cwd);
+    }
+    if task.exec.full_env {
+        command.env_clear();
+    }
+    command.envs(task.exec.env.iter().map(|(k, v)| (k, v)));
+    // Tasks of an array learn their place in it
+    if let Some(array) = &task.array {
+        command.env("GAVEL_ARRAY_ID", array.array_id.to_string());
+        command.env("GAVEL_ARRAY_INDEX", array.index.to_string());
+        command.env("GAVEL_ARRAY_SIZE", array.size.to_string());
     }
 
     if !task.gpu_ids.is_empty() {
@@ -288,78 +978,191 @@ async fn launch_task_process(state: &DaemonState, task: TaskMeta) -> Result<()>
     command.stdout(log_file_stdout);
     command.stderr(log_file_stderr);
 
-    let mut child = match command.spawn() {
-        Ok(c) => c,
+    // The shim returns as soon as the task is running; its supervisor keeps running detached
+    let status = match command.status().await {
+        Ok(s) => s,
         Err(e) => {
-            return Err(anyhow::anyhow!("Failed to spawn command '{}' for task {}: {}", args[0], task.id, e));
+            return Err(anyhow::anyhow!(
+                "Failed to spawn {} for task {}: {}",
+                shim.display(),
+                task.id,
+                e
+            ));
         }
     };
+    if !status.success() {
+        return Err(anyhow::anyhow!(
+            "gavel-shim failed to start task {} ({}). See {} for details.",
+            task.id,
+            status,
+            task.log_path
+        ));
+    }
 
-    let pid = child.id().map(|u_pid| u_pid as i32);
-    info!("Task {} (ID: {}) spawned with PID: {:?}", task.name, task.id, pid);
-    if let Err(e) = state.set_task_pid(task.id, pid).await {
+    let pid_info = spool::read_pid_info(&spool_dir)?.ok_or_else(|| {
+        anyhow::anyhow!("gavel-shim started task {} but did not write {}", task.id, spool::PID_FILE)
+    })?;
+    info!(
+        "Task {} (ID: {}) spawned with PID: {} (supervisor PID: {})",
+        task.name, task.id, pid_info.pid, pid_info.shim_pid
+    );
+    if let Err(e) = state
+        .set_task_pid(task.id, Some(pid_info.pid), pid_info.pid_start_time, pid_info.pgid)
+        .await
+    {
         error!(
-            "CRITICAL: Task {} (ID: {}) spawned (PID: {:?}), but FAILED to set PID in state: {}. Manual intervention may be needed.",
-            task.name, task.id, pid, e
+            "CRITICAL: Task {} (ID: {}) spawned (PID: {}), but FAILED to set PID in state: {}. Manual intervention may be needed.",
+            task.name, task.id, pid_info.pid, e
         );
     }
 
-    let state_clone_for_monitor = state.clone();
-    let task_id_for_monitor = task.id;
-    let task_name_for_monitor = task.name.clone();
+    monitor_shim(state, &task, pid_info);
+    info!("Task '{}' (ID: {}) process launched and monitoring started.", task.name, task.id);
+    Ok(())
+}
 
-    tokio::spawn(async move {
-        info!("Monitoring process for task '{}' (ID: {}) PID: {:?}", task_name_for_monitor, task_id_for_monitor, pid);
-        match child.wait().await {
-            Ok(status) => {
-                info!(
-                    "Task '{}' (ID: {}) (PID: {:?}) exited with status: {}",
-                    task_name_for_monitor, task_id_for_monitor, pid, status
-                );
-                let final_state = if status.success() {
-                    TaskState::Finished
-                } else {
-                    TaskState::Failed
-                };
-                let reason = if status.success() {
-                    None
-                } else {
-                    Some(format!("Process exited with status: {}", status))
-                };
+// gavel-shim is installed next to gavel-daemon
+fn shim_path() -> Result<PathBuf> {
+    let exe = std::env::current_exe().context("Failed to locate the gavel-daemon executable")?;
+    let shim = exe.with_file_name(SHIM_BINARY);
+    if !shim.is_file() {
+        return Err(anyhow::anyhow!(
+            "{} not found at {}. Install it next to gavel-daemon.",
+            SHIM_BINARY,
+            shim.display()
+        ));
+    }
+    Ok(shim)
+}
 
-                if let Err(e) = state_clone_for_monitor.update_task_state(task_id_for_monitor, final_state.clone(), None, reason.clone()).await {
-                    error!(
-                        "Failed to update task '{}' (ID: {}) state to {:?} after process exit: {}",
-                        task_name_for_monitor, task_id_for_monitor, final_state, e
-                    );
-                } else {
-                    info!("Task '{}' (ID: {}) state updated to {:?} (Reason: {:?}) after process exit.", task_name_for_monitor, task_id_for_monitor, final_state, reason.as_deref().unwrap_or("None"));
-                }
+/// Watches the gavel-shim supervising a task and records the outcome it leaves in the
+/// spool directory. Used for new tasks and for tasks re-adopted after a daemon restart.
+pub fn monitor_shim(state: &DaemonState, task: &TaskMeta, pid_info: ShimPidInfo) {
+    state.watch_task(task.id);
+
+    let state = state.clone();
+    let task_id = task.id;
+    let task_name = task.name.clone();
+    tokio::spawn(async move {
+        info!(
+            "Monitoring task '{}' (ID: {}) PID: {} via supervisor PID {}",
+            task_name, task_id, pid_info.pid, pid_info.shim_pid
+        );
+        // The supervisor exits right after writing the status file
+        match pid_info.shim_start_time {
+            Some(shim_start_time) => {
+                process::wait_for_exit(pid_info.shim_pid, shim_start_time).await
             }
-            Err(e) => { // e is std::io::Error
-                let io_error_string = e.to_string(); // Convert std::io::Error to String immediately.
+            None => process::wait_for_pid_exit(pid_info.shim_pid).await,
+        }
+        // The supervisor empties the task's process group before it exits; if it died early,
+        // the GPUs must still not be freed while processes of the task are alive
+        if let Some(pgid) = pid_info.pgid {
+            process::wait_for_group_exit(pgid, state.kill_grace()).await;
+        }
+        record_task_exit(&state, task_id, &task_name).await;
+        state.unwatch_task(task_id);
+    });
+}
 
+/// Sets the final state of a task from the status file gavel-shim wrote for it
+pub async fn record_task_exit(state: &DaemonState, task_id: u64, task_name: &str) {
+    let spool_dir = state.task_spool_dir(task_id);
+    let (mut final_state, mut reason) = match spool::read_exit_info(&spool_dir) {
+        Ok(Some(exit_info)) => {
+            info!("Task '{}' (ID: {}) exited: {:?}", task_name, task_id, exit_info);
+            let outcome = if exit_info.success() {
+                (TaskState::Finished, None)
+            } else {
+                (TaskState::Failed, Some(exit_info.describe()))
+            };
+            if let Err(e) = state.set_task_exit_info(task_id, exit_info).await {
                 error!(
-                    "Error waiting for task '{}' (ID: {}) (PID: {:?}) process. IO Error: {}",
-                    task_name_for_monitor, task_id_for_monitor, pid, io_error_string
+                    "Failed to record exit info of task '{}' (ID: {}): {}",
+                    task_name, task_id, e
                 );
+            }
+            outcome
+        }
+        Ok(None) => (
+            TaskState::Failed,
+            Some("Task supervisor exited without writing an exit status.".to_string()),
+        ),
+        Err(e) => (TaskState::Failed, Some(format!("Failed to read task exit status: {:#}", e))),
+    };
 
-                let reason_for_state = format!("Process monitoring failed: {}", io_error_string);
+    if let Some(timeout_reason) = walltime::timeout_reason(state, task_id).await {
+        final_state = TaskState::TimedOut;
+        reason = Some(timeout_reason);
+    } else if final_state == TaskState::Failed
+        && state.get_task(task_id).await.is_some_and(|t| t.kill_requested)
+    {
+        reason = reason.map(|r| format!("Killed by the user ({})", r));
+    } else if requeue_if_preempted(state, task_id, task_name).await {
+        return;
+    }
 
-                if let Err(update_err) = state_clone_for_monitor.update_task_state(task_id_for_monitor, TaskState::Failed, None, Some(reason_for_state.clone())).await {
-                    error!(
-                        "Additionally, failed to update task '{}' (ID: {}) state to Failed: {}",
-                        task_name_for_monitor, task_id_for_monitor, update_err
-                    );
-                } else {
-                     info!("Task '{}' (ID: {}) state updated to Failed. Reason: {}", task_name_for_monitor, task_id_for_monitor, reason_for_state);
-                }
+    if retry::record_attempt(state, task_id, task_name, &final_state, reason.clone()).await {
+        return;
+    }
+
+    if let Err(e) =
+        state.update_task_state(task_id, final_state.clone(), None, reason.clone()).await
+    {
+        error!(
+            "Failed to update task '{}' (ID: {}) state to {:?} after process exit: {}",
+            task_name, task_id, final_state, e
+        );
+    } else {
+        info!(
+            "Task '{}' (ID: {}) state updated to {:?} (Reason: {:?}) after process exit.",
+            task_name,
+            task_id,
+            final_state,
+            reason.as_deref().unwrap_or("None")
+        );
+    }
+}
+
+/// Re-attaches a monitor to a task process that survived a daemon restart but has no
+/// gavel-shim status to read (e.g. it was started by an older daemon).
+/// The daemon is not the parent of such a process, so only its exit can be observed,
+/// not its exit status, and the task is marked Failed when it exits.
+pub fn adopt_task_process(state: &DaemonState, task: TaskMeta, pid: i32, start_time: u64) {
+    info!("Re-adopting task '{}' (ID: {}) with PID {}", task.name, task.id, pid);
+    state.watch_task(task.id);
+
+    let state = state.clone();
+    tokio::spawn(async move {
+        process::wait_for_exit(pid, start_time).await;
+        info!(
+            "Re-adopted task '{}' (ID: {}) (PID: {}) exited. Its exit status is not available to the daemon.",
+            task.name, task.id, pid
+        );
+        let (final_state, reason) = match walltime::timeout_reason(&state, task.id).await {
+            Some(reason) => (TaskState::TimedOut, Some(reason)),
+            None if requeue_if_preempted(&state, task.id, &task.name).await => {
+                state.unwatch_task(task.id);
+                return;
+            }
+            // Without an exit status the task cannot be assumed to have succeeded
+            None => (
+                TaskState::Failed,
+                Some("Exit status unknown after daemon restart.".to_string()),
+            ),
+        };
+        let retrying =
+            retry::record_attempt(&state, task.id, &task.name, &final_state, reason.clone()).await;
+        if !retrying {
+            if let Err(e) = state.update_task_state(task.id, final_state, None, reason).await {
+                error!(
+                    "Failed to update re-adopted task '{}' (ID: {}) after exit: {}",
+                    task.name, task.id, e
+                );
             }
         }
+        state.unwatch_task(task.id);
     });
-
-    info!("Task '{}' (ID: {}) process launched and monitoring started.", task.name, task.id);
-    Ok(())
 }
 
 async fn update_tasks(state: &DaemonState) -> Result<()> {
@@ -367,7 +1170,8 @@ async fn update_tasks(state: &DaemonState) -> Result<()> {
     let mut tasks_to_update: Vec<(u64, TaskState, Option<String>)> = Vec::new();
 
     for task in tasks {
-        if task.state == TaskState::Running {
+        // Tasks with an active monitor get their final state from it
+        if task.state == TaskState::Running && !state.is_task_watched(task.id) {
             if let Some(pid_val) = task.pid {
                 let pid_exists = match tokio::process::Command::new("kill")
                     .arg("-0")
@@ -408,3 +1212,439 @@ async fn update_tasks(state: &DaemonState) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gavel_core::gpu::monitor::MemoryInfo;
+    use gavel_core::gpu::simulated::{SimulatedBackend, SimulatedFixture};
+    use gavel_core::utils::models::{ExitInfo, QueueUsage};
+    use std::sync::Arc;
+
+    const MB: u64 = 1024 * 1024;
+
+    fn gpu(name: &str, total_mb: u64, used_mb: u64) -> GpuStats {
+        GpuStats {
+            gpu_index: 0,
+            temperature: 40,
+            core_usage: 0,
+            memory_usage: MemoryInfo {
+                total: total_mb * MB,
+                used: used_mb * MB,
+                free: (total_mb - used_mb) * MB,
+            },
+            power_usage: 0,
+            power_limit: 0,
+            name: name.to_string(),
+        }
+    }
+
+    fn share(memory_mb: u64, util_percent: Option<u8>) -> GpuShare {
+        GpuShare { memory_mb, util_percent }
+    }
+
+    #[test]
+    fn gpu_load_sums_shared_declarations() {
+        let stat = gpu("A100", 80_000, 0);
+        let mut load = GpuLoad::default();
+        load.add(1, Some(&share(30_000, Some(40))));
+        load.add(2, Some(&share(20_000, None)));
+        assert_eq!((load.memory_mb, load.util_percent, load.exclusive), (50_000, 40, false));
+        assert_eq!(load.task_ids, vec![1, 2]);
+        assert_eq!(load.free_mb(Some(&share(1, None)), &stat), 30_000);
+
+        load.remove(1, Some(&share(30_000, Some(40))));
+        assert_eq!((load.memory_mb, load.util_percent), (20_000, 0));
+        assert_eq!(load.task_ids, vec![2]);
+    }
+
+    #[test]
+    fn gpu_load_free_memory() {
+        let mut load = GpuLoad::default();
+        load.add(1, Some(&share(10_000, None)));
+        // Sharing tasks get the smaller of the reported and the undeclared memory
+        assert_eq!(load.free_mb(Some(&share(1, None)), &gpu("A100", 80_000, 5_000)), 70_000);
+        assert_eq!(load.free_mb(Some(&share(1, None)), &gpu("A100", 80_000, 30_000)), 50_000);
+        // Whole-GPU tasks only see what the GPU reports
+        assert_eq!(load.free_mb(None, &gpu("A100", 80_000, 5_000)), 75_000);
+        load.add(2, Some(&share(100_000, None)));
+        assert_eq!(load.free_mb(Some(&share(1, None)), &gpu("A100", 80_000, 0)), 0);
+    }
+
+    #[test]
+    fn gpu_load_packs_shared_tasks() {
+        let stat = gpu("A100", 80_000, 0);
+        let mut load = GpuLoad::default();
+        assert!(load.problems(None, &stat).is_empty());
+        assert!(load.problems(Some(&share(80_000, Some(100))), &stat).is_empty());
+
+        load.add(1, Some(&share(50_000, Some(60))));
+        assert!(load.problems(Some(&share(30_000, Some(40))), &stat).is_empty());
+        assert_eq!(
+            load.problems(Some(&share(30_001, None)), &stat),
+            vec![GpuProblem::SharedMemory { free_mb: 30_000, required_mb: 30_001 }]
+        );
+        // Declared utilization is capped at 100%
+        assert_eq!(
+            load.problems(Some(&share(1_000, Some(41))), &stat),
+            vec![GpuProblem::SharedUtilization { used_percent: 60, required_percent: 41 }]
+        );
+        // A whole-GPU task cannot join sharing tasks
+        assert_eq!(load.problems(None, &stat), vec![GpuProblem::InUse { task_ids: vec![1] }]);
+    }
+
+    #[test]
+    fn gpu_load_exclusive_task_blocks_sharing() {
+        let stat = gpu("A100", 80_000, 0);
+        let mut load = GpuLoad::default();
+        load.add(1, None);
+        let in_use = vec![GpuProblem::InUse { task_ids: vec![1] }];
+        assert_eq!(load.problems(Some(&share(1, None)), &stat), in_use);
+        assert_eq!(load.problems(None, &stat), in_use);
+        load.remove(1, None);
+        assert!(load.problems(Some(&share(1, None)), &stat).is_empty());
+    }
+
+    fn requiring(requirement: GpuRequirement) -> TaskMeta {
+        TaskMeta { id: 1, gpu_require: 1, gpu_requirement: Some(requirement), ..Default::default() }
+    }
+
+    fn problems_on(task: &TaskMeta, stat: &GpuStats) -> Vec<GpuProblem> {
+        let queue = queue("default", 1, vec![0]);
+        task_gpu_problems(task, &queue, stat, &GpuLoad::default(), None, None, 0)
+    }
+
+    #[test]
+    fn requirement_min_free_memory() {
+        let stat = gpu("A100", 80_000, 60_000);
+        let absolute = |value| {
+            requiring(GpuRequirement {
+                memory_requirement_type: MemoryRequirementType::AbsoluteMb,
+                memory_requirement_value: value,
+                ..Default::default()
+            })
+        };
+        assert_eq!(problems_on(&absolute(20_000), &stat), vec![]);
+        assert_eq!(
+            problems_on(&absolute(30_000), &stat),
+            vec![GpuProblem::FreeMemory { free_mb: 20_000, required_mb: 30_000 }]
+        );
+        // More than the GPU has at all: it can never meet it
+        assert_eq!(
+            problems_on(&absolute(90_000), &stat),
+            vec![GpuProblem::TotalMemory { total_mb: 80_000, required_mb: 90_000 }]
+        );
+        assert!(!gpu_can_meet(absolute(90_000).gpu_requirement.as_ref().unwrap(), &stat));
+        assert!(gpu_can_meet(absolute(30_000).gpu_requirement.as_ref().unwrap(), &stat));
+
+        let percentage = |value| {
+            requiring(GpuRequirement {
+                memory_requirement_type: MemoryRequirementType::Percentage,
+                memory_requirement_value: value,
+                ..Default::default()
+            })
+        };
+        assert_eq!(problems_on(&percentage(25), &stat), vec![]);
+        assert_eq!(
+            problems_on(&percentage(50), &stat),
+            vec![GpuProblem::FreeMemory { free_mb: 20_000, required_mb: 40_000 }]
+        );
+    }
+
+    #[test]
+    fn requirement_min_total_memory() {
+        let task =
+            requiring(GpuRequirement { min_total_memory_mb: Some(40_000), ..Default::default() });
+        assert_eq!(problems_on(&task, &gpu("A100", 80_000, 79_000)), vec![]);
+        assert_eq!(
+            problems_on(&task, &gpu("RTX 4090", 24_000, 0)),
+            vec![GpuProblem::TotalMemory { total_mb: 24_000, required_mb: 40_000 }]
+        );
+    }
+
+    #[test]
+    fn requirement_model_is_a_case_insensitive_substring() {
+        let task =
+            requiring(GpuRequirement { model: Some("a100".to_string()), ..Default::default() });
+        assert_eq!(problems_on(&task, &gpu("NVIDIA A100-SXM4-80GB", 80_000, 0)), vec![]);
+        assert_eq!(
+            problems_on(&task, &gpu("NVIDIA H100 80GB HBM3", 80_000, 0)),
+            vec![GpuProblem::Model {
+                model: "NVIDIA H100 80GB HBM3".to_string(),
+                required: "a100".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn requirement_free_memory_counts_shared_declarations() {
+        let mut shared = queue("shared", 1, vec![0]);
+        shared.gpu_mode = GpuMode::Shared;
+        let task = TaskMeta {
+            gpu_share: Some(share(10_000, None)),
+            ..requiring(GpuRequirement {
+                memory_requirement_type: MemoryRequirementType::AbsoluteMb,
+                memory_requirement_value: 30_000,
+                ..Default::default()
+            })
+        };
+        let stat = gpu("A100", 80_000, 0);
+        let mut load = GpuLoad::default();
+        load.add(2, Some(&share(60_000, None)));
+        assert_eq!(
+            task_gpu_problems(&task, &shared, &stat, &load, None, None, 0),
+            vec![GpuProblem::FreeMemory { free_mb: 20_000, required_mb: 30_000 }]
+        );
+    }
+
+    #[test]
+    fn reserved_gpus_are_kept_for_their_task() {
+        let queue = queue("default", 1, vec![0]);
+        let stat = gpu("A100", 80_000, 0);
+        let idle = GpuLoad::default();
+        let task = TaskMeta { id: 1, gpu_require: 1, ..Default::default() };
+        assert_eq!(
+            task_gpu_problems(&task, &queue, &stat, &idle, Some(2), None, 0),
+            vec![GpuProblem::Preempting { task_id: 2 }]
+        );
+        assert_eq!(task_gpu_problems(&task, &queue, &stat, &idle, Some(1), None, 0), vec![]);
+
+        let reservation = BackfillReservation { task_id: 2, start_at: 1_000 };
+        assert_eq!(
+            task_gpu_problems(&task, &queue, &stat, &idle, None, Some(&reservation), 900),
+            vec![GpuProblem::Reserved { task_id: 2, start_at: 1_000 }]
+        );
+        let short = TaskMeta { max_runtime: Some(100), ..task };
+        assert_eq!(
+            task_gpu_problems(&short, &queue, &stat, &idle, None, Some(&reservation), 900),
+            vec![]
+        );
+    }
+
+    fn placement_order(
+        placement: PlacementStrategy,
+        gpu_loads: &HashMap<u32, GpuLoad>,
+        share: Option<&GpuShare>,
+    ) -> Vec<u32> {
+        let mut gpus = vec![
+            (0, gpu("A100", 80_000, 40_000)),
+            (1, gpu("A100", 80_000, 70_000)),
+            (2, gpu("A100", 80_000, 0)),
+            (3, gpu("A100", 80_000, 70_000)),
+        ];
+        sort_for_placement(&mut gpus, gpu_loads, share, placement);
+        gpus.into_iter().map(|(gpu_id, _)| gpu_id).collect()
+    }
+
+    #[test]
+    fn best_fit_prefers_the_least_free_memory() {
+        let order = placement_order(PlacementStrategy::BestFit, &HashMap::new(), None);
+        assert_eq!(order, vec![1, 3, 0, 2]);
+    }
+
+    #[test]
+    fn most_free_prefers_the_most_free_memory() {
+        let order = placement_order(PlacementStrategy::MostFree, &HashMap::new(), None);
+        assert_eq!(order, vec![2, 0, 1, 3]);
+    }
+
+    #[test]
+    fn placement_counts_shared_declarations() {
+        // GPU 2 reports all its memory free, but tasks sharing it declared 75 GB of it
+        let mut load = GpuLoad::default();
+        load.add(1, Some(&share(75_000, None)));
+        let gpu_loads = HashMap::from([(2, load)]);
+        let task_share = share(1_000, None);
+        let order = placement_order(PlacementStrategy::BestFit, &gpu_loads, Some(&task_share));
+        assert_eq!(order, vec![2, 1, 3, 0]);
+        let order = placement_order(PlacementStrategy::MostFree, &gpu_loads, Some(&task_share));
+        assert_eq!(order, vec![0, 1, 3, 2]);
+    }
+
+    #[test]
+    fn reservation_admits_its_task_and_tasks_done_in_time() {
+        let reservation = BackfillReservation { task_id: 1, start_at: 1_000 };
+        assert!(reservation.admits(1, None, 900));
+        assert!(reservation.admits(2, Some(100), 900));
+        assert!(!reservation.admits(2, Some(101), 900));
+        assert!(!reservation.admits(2, None, 900)); // Unknown runtime could run past the start
+        assert!(!reservation.admits(2, Some(0), 1_001));
+    }
+
+    #[test]
+    fn plan_picks_the_gpus_free_the_soonest() {
+        let free_now = HashSet::from([3]);
+        let expected = HashMap::from([(0, Some(500)), (1, Some(200)), (2, None)]);
+        let plan = plan_reservation(2, &[0, 1, 2, 3], &free_now, &expected, 100);
+        assert_eq!(plan, Some((vec![3, 1], 200)));
+        let plan = plan_reservation(3, &[0, 1, 2, 3], &free_now, &expected, 100);
+        assert_eq!(plan, Some((vec![3, 1, 0], 500)));
+        // Tasks past their expected end may still hold the GPU, it is expected to be free now
+        let plan = plan_reservation(1, &[0], &HashSet::new(), &expected, 600);
+        assert_eq!(plan, Some((vec![0], 600)));
+    }
+
+    #[test]
+    fn plan_reserves_nothing_when_the_start_is_unknown() {
+        let free_now = HashSet::from([3]);
+        let expected = HashMap::from([(0, Some(500)), (2, None)]);
+        // GPU 2 is held by a task without a max runtime, GPU 1 by something outside gavel
+        assert_eq!(plan_reservation(3, &[0, 2, 3], &free_now, &expected, 100), None);
+        assert_eq!(plan_reservation(2, &[1, 3], &free_now, &expected, 100), None);
+    }
+
+    #[test]
+    fn huge_runtimes_do_not_wrap() {
+        let reservation = BackfillReservation { task_id: 1, start_at: 1_000 };
+        assert!(!reservation.admits(2, Some(u64::MAX), 900));
+        assert!(!reservation.admits(2, Some(u64::MAX - 500), 900));
+
+        // A task ending out of range holds its GPU for an unknown time
+        let queues = HashMap::new();
+        let task = |id: u64, gpu_id: u8, max_runtime: u64| TaskMeta {
+            id,
+            state: TaskState::Running,
+            start_time: Some(1_000),
+            max_runtime: Some(max_runtime),
+            gpu_ids: vec![gpu_id],
+            ..Default::default()
+        };
+        let all_tasks = HashMap::from([
+            (1, task(1, 0, 500)),
+            (2, task(2, 1, u64::MAX)),
+            (3, task(3, 2, u64::MAX - 1_000)),
+        ]);
+        let expected = expected_gpu_release(&all_tasks, &queues);
+        assert_eq!(expected, HashMap::from([(0, Some(1_500)), (1, None), (2, Some(u64::MAX))]));
+        let free_now = HashSet::new();
+        assert_eq!(plan_reservation(1, &[1], &free_now, &expected, 1_200), None);
+        assert_eq!(plan_reservation(2, &[0, 1], &free_now, &expected, 1_200), None);
+        let plan = plan_reservation(1, &[0, 1], &free_now, &expected, 1_200);
+        assert_eq!(plan, Some((vec![0], 1_500)));
+    }
+
+    #[test]
+    fn plan_needs_enough_candidates() {
+        let free_now = HashSet::from([0, 1]);
+        assert_eq!(plan_reservation(3, &[0, 1], &free_now, &HashMap::new(), 100), None);
+        assert_eq!(plan_reservation(0, &[0, 1], &free_now, &HashMap::new(), 100), None);
+    }
+
+    fn queue(name: &str, priority: u8, allocated_gpus: Vec<u8>) -> QueueMeta {
+        QueueMeta {
+            name: name.to_string(),
+            max_concurrent: 4,
+            priority,
+            waiting_task_ids: vec![],
+            running_task_ids: vec![],
+            allocated_gpus,
+            resource_limit: ResourceLimit::default(),
+            default_max_runtime: None,
+            default_retry_policy: None,
+            gpu_mode: GpuMode::default(),
+            placement: PlacementStrategy::default(),
+            fair_share: 1,
+            usage: QueueUsage::default(),
+        }
+    }
+
+    fn running(id: u64, queue: &str, gpu_ids: Vec<u8>) -> TaskMeta {
+        TaskMeta {
+            id,
+            queue: queue.to_string(),
+            state: TaskState::Running,
+            gpu_ids,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn preemption_takes_gpus_from_lower_priority_queues() {
+        let high = queue("high", 10, vec![0, 1, 2, 3]);
+        let priorities = HashMap::from([
+            ("high".to_string(), 10),
+            ("mid".to_string(), 5),
+            ("low".to_string(), 1),
+        ]);
+        // GPU 3 is idle: it fails the queue limits on its own, preempting would not help
+        let all_tasks = HashMap::from([
+            (1, running(1, "mid", vec![0])),
+            (2, running(2, "low", vec![1])),
+            (3, running(3, "high", vec![2])),
+        ]);
+        let none = HashSet::new();
+
+        let select =
+            |missing| select_preemption_victims(&high, missing, &all_tasks, &priorities, &none);
+        assert_eq!(select(1), Some((vec![1], vec![2])));
+        assert_eq!(select(2), Some((vec![1, 0], vec![1, 2])));
+        // GPU 2 runs a task of the same priority, which is never preempted
+        assert_eq!(select(3), None);
+
+        let excluded = HashSet::from([1]);
+        let plan = select_preemption_victims(&high, 1, &all_tasks, &priorities, &excluded);
+        assert_eq!(plan, Some((vec![0], vec![1])));
+        assert_eq!(select_preemption_victims(&high, 2, &all_tasks, &priorities, &excluded), None);
+
+        // Lower-priority queues cannot preempt anything
+        let low = queue("low", 1, vec![0, 1]);
+        assert_eq!(select_preemption_victims(&low, 1, &all_tasks, &priorities, &none), None);
+    }
+
+    #[test]
+    fn preemption_prefers_gpus_already_being_freed() {
+        let high = queue("high", 10, vec![0, 1]);
+        let priorities = HashMap::from([("high".to_string(), 10), ("low".to_string(), 1)]);
+        let mut being_freed = running(2, "low", vec![1]);
+        being_freed.preempted = true;
+        let all_tasks = HashMap::from([(1, running(1, "low", vec![0])), (2, being_freed)]);
+        let none = HashSet::new();
+
+        // Task 2 already got SIGTERM, it is not preempted again
+        let plan = select_preemption_victims(&high, 1, &all_tasks, &priorities, &none);
+        assert_eq!(plan, Some((vec![1], vec![])));
+        let plan = select_preemption_victims(&high, 2, &all_tasks, &priorities, &none);
+        assert_eq!(plan, Some((vec![1, 0], vec![1])));
+    }
+
+    #[tokio::test]
+    async fn preempted_task_is_requeued_when_it_exits() {
+        let spool_dir = tempfile::tempdir().unwrap();
+        let backend = SimulatedBackend::new(SimulatedFixture {
+            devices: vec![],
+            timeline: vec![],
+            loop_secs: None,
+        });
+        let state = DaemonState::new(
+            Arc::new(backend),
+            None,
+            Duration::from_secs(1),
+            spool_dir.path().to_path_buf(),
+        );
+        let task = TaskMeta {
+            id: 1,
+            name: "train".to_string(),
+            queue: "low".to_string(),
+            ..Default::default()
+        };
+        state.add_task(task).await.unwrap();
+        state.update_task_state(1, TaskState::Running, Some(vec![0]), None).await.unwrap();
+        state.set_task_preempted(1, true).await.unwrap();
+
+        // The preempted task was terminated by SIGTERM
+        let task_spool = state.task_spool_dir(1);
+        std::fs::create_dir_all(&task_spool).unwrap();
+        let exit_info = ExitInfo { signal: Some(15), ..Default::default() };
+        spool::write_json_atomic(&task_spool.join(spool::STATUS_FILE), &exit_info).unwrap();
+        record_task_exit(&state, 1, "train").await;
+
+        let task = state.get_task(1).await.unwrap();
+        assert_eq!(task.state, TaskState::Waiting);
+        assert!(task.preempted); // Cleared when it starts again
+        assert!(task.gpu_ids.is_empty());
+        assert!(task.attempts.is_empty()); // Preemption is not a failed attempt
+        let queue = state.get_queue("low").await.unwrap();
+        assert_eq!(queue.waiting_task_ids, vec![1]);
+        assert!(queue.running_task_ids.is_empty());
+    }
+}
@@ -1,25 +1,68 @@
 // src/daemon/state.rs
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use bincode::{self, Decode, Encode};
-use gavel_core::gpu::monitor::GpuMonitor;
+use gavel_core::gpu::backend::GpuBackend;
 use log::{error, info, warn}; // Import log macros
 use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
-use tokio::sync::RwLock; // Import GpuMonitor
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{broadcast, Notify, RwLock}; // Import GpuMonitor
 
 // 从 core crate 引入共享的数据模型
 use gavel_core::gpu::monitor::GpuStats;
-use gavel_core::utils::models::{QueueMeta, ResourceLimit, TaskMeta, TaskState}; // TaskState will now include Failed
+use gavel_core::rpc::message::{Event, EventKind, RpcError};
+use gavel_core::utils::models::{
+    ExitInfo, GpuMode, PendingReason, PlacementStrategy, QueueMeta, QueueUsage, ResourceLimit,
+    RetryPolicy, TaskAttempt, TaskMeta, TaskState, DEFAULT_FAIR_SHARE,
+};
+use gavel_core::utils::spool;
+use gavel_core::utils::time::now_secs; // TaskState will now include Failed
 
 // 定义守护进程的共享状态
 #[derive(Debug, Clone)]
 pub struct DaemonState {
     // 使用 Arc<RwLock<...>> 允许多个线程安全地读写状态
     inner: Arc<RwLock<InnerDaemonState>>,
-    // 持久化文件的路径
+    // GPU 数据来源 (NVML 或模拟器)
+    gpu_backend: Arc<dyn GpuBackend>,
+    // 持久化文件的路径 (None 表示不持久化)
+    persist_path: Option<Arc<PathBuf>>,
+    // 状态被修改时通知持久化任务
+    persist_notify: Arc<Notify>,
+    // 可能让任务得以启动的变化 (提交、任务退出、GPU 分配和队列变化) 发生时唤醒调度器
+    scheduler_notify: Arc<Notify>,
+    // 当前有监控协程负责其退出状态的任务 ID (运行时信息, 不持久化)
+    watched_tasks: Arc<Mutex<HashSet<u64>>>,
+    // 抢占腾出的 GPU -> (等待使用它的任务 ID, 过期时间) (运行时信息, 不持久化)
+    gpu_reservations: Arc<Mutex<HashMap<u32, (u64, Instant)>>>,
+    // 回填预留的 GPU -> (等待使用它的任务 ID, 预计开始时间), 每轮调度更新 (运行时信息, 不持久化)
+    backfill_reservations: Arc<Mutex<BackfillReservations>>,
+    // task kill 时 SIGTERM 与 SIGKILL 之间的等待时间
+    kill_grace: Duration,
+    // 各任务 spool 目录的根目录, gavel-shim 在其中记录任务的 PID 和退出状态
+    spool_dir: Arc<PathBuf>,
+    // 推送给订阅连接的事件
+    events: broadcast::Sender<Event>,
+    // 最近一次查询失败或消失的 GPU (运行时信息, 不持久化)
+    unhealthy_gpus: Arc<Mutex<HashSet<u32>>>,
+    // 守护进程启动时间 (Unix 时间戳, 秒)
+    started_at: u64,
 }
 
+/// GPU ID -> (task ID, expected start) of the GPUs kept for blocked tasks by backfilling
+pub type BackfillReservations = HashMap<u32, (u64, u64)>;
+
+// Events a subscriber may fall behind by before it misses some
+const EVENT_BUFFER: usize = 1024;
+
+/// Largest task ID a submitter may give (batch JSON). The IDs above it are left for the ones
+/// handed out after it, so that they never run out.
+pub const MAX_SUBMITTED_TASK_ID: u64 = u64::MAX / 2;
+
 // 内部状态结构，由 RwLock 保护
 #[derive(Debug, Default, serde::Serialize, serde::Deserialize, Encode, Decode)]
 struct InnerDaemonState {
@@ -28,26 +71,201 @@ struct InnerDaemonState {
     gpu_stats: HashMap<u32, GpuStats>,  // 存储每个 GPU 的最新状态统计
     gpu_allocations: HashMap<u32, Option<String>>, // GPU ID -> 分配到的队列名称 (None 表示未分配或空闲)
     ignored_gpus: HashSet<u32>,                    // 被用户设置为忽略的 GPU ID 集合
+    #[serde(default)]
+    last_task_id: u64, // 已分配过的最大任务/数组 ID, 新 ID 总比它大 (删除的任务 ID 也不会复用)
 }
 
 // 为 DaemonState 实现方法
 impl DaemonState {
     // 创建一个新的 DaemonState 实例
-    pub fn new() -> Self {
-        DaemonState { inner: Arc::new(RwLock::new(InnerDaemonState::default())) }
+    pub fn new(
+        gpu_backend: Arc<dyn GpuBackend>,
+        persist_path: Option<PathBuf>,
+        kill_grace: Duration,
+        spool_dir: PathBuf,
+    ) -> Self {
+        DaemonState {
+            inner: Arc::new(RwLock::new(InnerDaemonState::default())),
+            gpu_backend,
+            persist_path: persist_path.map(Arc::new),
+            persist_notify: Arc::new(Notify::new()),
+            scheduler_notify: Arc::new(Notify::new()),
+            watched_tasks: Arc::new(Mutex::new(HashSet::new())),
+            gpu_reservations: Arc::new(Mutex::new(HashMap::new())),
+            backfill_reservations: Arc::new(Mutex::new(HashMap::new())),
+            kill_grace,
+            spool_dir: Arc::new(spool_dir),
+            events: broadcast::channel(EVENT_BUFFER).0,
+            unhealthy_gpus: Arc::new(Mutex::new(HashSet::new())),
+            started_at: now_secs(),
+        }
+    }
+
+    /// Time between SIGTERM and SIGKILL when a task is killed
+    pub fn kill_grace(&self) -> Duration {
+        self.kill_grace
+    }
+
+    /// Spool directory of a task, where its gavel-shim writes the pid and status files
+    pub fn task_spool_dir(&self, task_id: u64) -> PathBuf {
+        spool::task_spool_dir(&self.spool_dir, task_id)
+    }
+
+    pub fn started_at(&self) -> u64 {
+        self.started_at
+    }
+
+    // --- Event subscription ---
+
+    /// Sends an event to the current subscribers, if any
+    pub fn publish(&self, kind: EventKind) {
+        let _ = self.events.send(Event { time: now_secs(), kind });
+    }
+
+    pub fn subscribe_events(&self) -> broadcast::Receiver<Event> {
+        self.events.subscribe()
+    }
+
+    // --- Persistence related methods ---
+
+    // Wakes the persister so the mutation is written to disk soon
+    fn mark_dirty(&self) {
+        if self.persist_path.is_some() {
+            self.persist_notify.notify_one();
+        }
+    }
+
+    /// Waits until some mutation has marked the state dirty
+    pub async fn wait_for_mutation(&self) {
+        self.persist_notify.notified().await;
+    }
+
+    // --- Scheduler wakeups ---
+
+    /// Runs the next scheduling cycle right away instead of at the next tick
+    pub fn wake_scheduler(&self) {
+        self.scheduler_notify.notify_one();
+    }
+
+    /// Waits until something has woken the scheduler since its last wait
+    pub async fn wait_for_wakeup(&self) {
+        self.scheduler_notify.notified().await;
+    }
+
+    /// Loads the snapshot from the state file. Returns false if persistence is
+    /// disabled or no snapshot exists yet. A state file that cannot be parsed is
+    /// moved aside to `<path>.corrupt-<timestamp>` and the daemon starts empty.
+    pub async fn load_snapshot(&self) -> Result<bool> {
+        let Some(path) = self.persist_path.as_deref() else {
+            return Ok(false);
+        };
+        if !path.exists() {
+            info!("No state file found at {}, starting with empty state.", path.display());
+            return Ok(false);
+        }
+
+        let content = tokio::fs::read(path)
+            .await
+            .with_context(|| format!("Failed to read state file: {}", path.display()))?;
+        let mut loaded: InnerDaemonState = match serde_json::from_slice(&content) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                let mut corrupt_name = path.as_os_str().to_os_string();
+                corrupt_name.push(format!(".corrupt-{}", now_secs()));
+                let corrupt_path = PathBuf::from(corrupt_name);
+                // Never start empty while the old file is still in place: the next save
+                // would overwrite it
+                tokio::fs::rename(path, &corrupt_path).await.with_context(|| {
+                    format!("Failed to move corrupt state file aside: {}", path.display())
+                })?;
+                error!(
+                    "Failed to parse state file {}: {}. Moved it to {}, starting with empty state.",
+                    path.display(),
+                    e,
+                    corrupt_path.display()
+                );
+                return Ok(false);
+            }
+        };
+        loaded.gpu_stats.clear(); // Live stats always come from the GPU backend
+
+        // Snapshots from before `last_task_id` was saved: continue above every known ID
+        let max_known_id = loaded
+            .tasks
+            .values()
+            .flat_map(|t| [Some(t.id), t.array.as_ref().map(|a| a.array_id)])
+            .flatten()
+            .max()
+            .unwrap_or(0);
+        loaded.last_task_id = loaded.last_task_id.max(max_known_id);
+
+        info!(
+            "Loaded state from {}: {} tasks, {} queues.",
+            path.display(),
+            loaded.tasks.len(),
+            loaded.queues.len()
+        );
+        *self.inner.write().await = loaded;
+        Ok(true)
+    }
+
+    /// Writes a snapshot of the state to the state file atomically
+    /// (write to a temporary file, fsync, then rename over the old file).
+    pub async fn save_snapshot(&self) -> Result<()> {
+        let Some(path) = self.persist_path.as_deref() else {
+            return Ok(());
+        };
+        // Serialize under the read lock, write without holding it
+        let bytes = {
+            let state = self.inner.read().await;
+            serde_json::to_vec_pretty(&*state).context("Failed to serialize daemon state")?
+        };
+        write_atomic(path, &bytes).await
     }
 
     // --- Task related methods ---
 
+    /// Hands out a new task (or array) ID. IDs are derived from the current time for
+    /// readability, but always grow, so none is ever reused, even across restarts.
+    pub async fn next_task_id(&self) -> Result<u64> {
+        let mut state = self.inner.write().await;
+        // Seconds modulo 1,000,000 followed by two digits for IDs within the same second
+        let candidate = now_secs() % 1_000_000 * 100;
+        let next = state
+            .last_task_id
+            .checked_add(1)
+            .ok_or_else(|| RpcError::Internal("No task IDs are left".to_string()))?;
+        state.last_task_id = candidate.max(next);
+        self.mark_dirty();
+        Ok(state.last_task_id)
+    }
+
     pub async fn add_task(&self, task: TaskMeta) -> Result<()> {
         let mut state = self.inner.write().await;
         let task_id = task.id;
         let queue_name = task.queue.clone();
 
         // Add task to the main task map
-        if state.tasks.insert(task_id, task.clone()).is_some() {
-            warn!("Task with ID {} already existed and was overwritten.", task_id);
+        if state.tasks.contains_key(&task_id) {
+            return Err(
+                RpcError::Conflict(format!("Task with ID {} already exists", task_id)).into()
+            );
+        }
+        if task_id > MAX_SUBMITTED_TASK_ID {
+            return Err(RpcError::InvalidArgument(format!(
+                "Task ID {} is too large, IDs go up to {}",
+                task_id, MAX_SUBMITTED_TASK_ID
+            ))
+            .into());
         }
+        // IDs given by the submitter (batch JSON) must not be handed out again either
+        state.last_task_id = state.last_task_id.max(task_id);
+        state.tasks.insert(task_id, task.clone());
+        self.publish(EventKind::TaskSubmitted {
+            task_id,
+            name: task.name.clone(),
+            queue: queue_name.clone(),
+        });
 
         // Add task ID to the corresponding queue's waiting list
         if let Some(queue) = state.queues.get_mut(&queue_name) {
@@ -68,11 +286,23 @@ impl DaemonState {
                 running_task_ids: Vec::new(),
                 allocated_gpus: Vec::new(), // Default: no allocated GPUs
                 resource_limit: ResourceLimit::default(), // Corrected field name
+                default_max_runtime: None,
+                default_retry_policy: None,
+                gpu_mode: GpuMode::default(),
+                placement: PlacementStrategy::default(),
+                fair_share: DEFAULT_FAIR_SHARE,
+                usage: QueueUsage::default(),
             };
+            self.publish(EventKind::QueueCreated {
+                name: queue_name.clone(),
+                priority: new_queue.priority,
+            });
             state.queues.insert(queue_name, new_queue);
             // Task ID is already added to waiting_task_ids during creation above
         }
 
+        self.mark_dirty();
+        self.wake_scheduler();
         Ok(())
     }
 
@@ -80,21 +310,151 @@ impl DaemonState {
         self.inner.read().await.tasks.get(&task_id).cloned()
     }
 
+    /// Records why each waiting task is not running (`reasons`, by task ID); waiting tasks not
+    /// in `reasons` get none. Like queue usage, the reasons are saved with the next mutation.
+    pub async fn set_pending_reasons(&self, mut reasons: HashMap<u64, PendingReason>) {
+        let mut state = self.inner.write().await;
+        for task in state.tasks.values_mut().filter(|t| t.state == TaskState::Waiting) {
+            task.pending_reason = reasons.remove(&task.id);
+        }
+    }
+
     pub async fn get_all_tasks(&self) -> Vec<TaskMeta> {
         self.inner.read().await.tasks.values().cloned().collect()
     }
 
-    // New method to set task PID
-    pub async fn set_task_pid(&self, task_id: u64, pid: Option<i32>) -> Result<()> {
+    // New method to set task PID and the process start time that identifies it
+    pub async fn set_task_pid(
+        &self,
+        task_id: u64,
+        pid: Option<i32>,
+        pid_start_time: Option<u64>,
+        pgid: Option<i32>,
+    ) -> Result<()> {
         let mut state = self.inner.write().await;
         if let Some(task) = state.tasks.get_mut(&task_id) {
             task.pid = pid;
+            task.pid_start_time = pid_start_time;
+            task.pgid = pgid;
+            self.mark_dirty();
             Ok(())
         } else {
             Err(anyhow::anyhow!("Task with ID {} not found when trying to set PID", task_id))
         }
     }
 
+    // Sets or clears the preemption marker of a task
+    pub async fn set_task_preempted(&self, task_id: u64, preempted: bool) -> Result<()> {
+        let mut state = self.inner.write().await;
+        if let Some(task) = state.tasks.get_mut(&task_id) {
+            task.preempted = preempted;
+            self.mark_dirty();
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "Task with ID {} not found when trying to mark it preempted",
+                task_id
+            ))
+        }
+    }
+
+    // Puts a preempted task back into its queue; the marker stays set until it runs again
+    pub async fn requeue_preempted_task(&self, task_id: u64) -> Result<()> {
+        self.update_task_state(task_id, TaskState::Waiting, Some(Vec::new()), None).await?;
+        let mut state = self.inner.write().await;
+        if let Some(task) = state.tasks.get_mut(&task_id) {
+            task.preempted = true;
+            task.pid = None;
+            task.pid_start_time = None;
+            self.mark_dirty();
+        }
+        Ok(())
+    }
+
+    // Records when a task was sent SIGTERM for exceeding its max runtime
+    pub async fn set_task_timed_out(&self, task_id: u64, at: u64) -> Result<()> {
+        let mut state = self.inner.write().await;
+        if let Some(task) = state.tasks.get_mut(&task_id) {
+            task.timed_out_at = Some(at);
+            self.mark_dirty();
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "Task with ID {} not found when trying to mark it timed out",
+                task_id
+            ))
+        }
+    }
+
+    // Adds the run that just ended to the task's attempt history and returns the updated task
+    pub async fn push_task_attempt(
+        &self,
+        task_id: u64,
+        outcome: TaskState,
+        reason: Option<String>,
+    ) -> Result<TaskMeta> {
+        let mut state = self.inner.write().await;
+        let task = state.tasks.get_mut(&task_id).ok_or_else(|| {
+            anyhow::anyhow!("Task with ID {} not found when trying to record an attempt", task_id)
+        })?;
+        let attempt = TaskAttempt {
+            attempt: task.attempts.len() as u32 + 1,
+            log_path: task.log_path.clone(),
+            start_time: task.start_time,
+            exit_info: task.exit_info.clone(),
+            state: outcome,
+            reason,
+        };
+        task.attempts.push(attempt);
+        self.mark_dirty();
+        Ok(task.clone())
+    }
+
+    // Sets when a Retrying task goes back to its queue and the log file of its next attempt
+    pub async fn set_task_retry(
+        &self,
+        task_id: u64,
+        retry_at: u64,
+        log_path: String,
+    ) -> Result<()> {
+        let mut state = self.inner.write().await;
+        if let Some(task) = state.tasks.get_mut(&task_id) {
+            task.retry_at = Some(retry_at);
+            task.log_path = log_path;
+            self.mark_dirty();
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "Task with ID {} not found when trying to schedule a retry",
+                task_id
+            ))
+        }
+    }
+
+    // Marks a task as killed by the user, so its failure is not retried
+    pub async fn set_task_kill_requested(&self, task_id: u64) -> Result<()> {
+        let mut state = self.inner.write().await;
+        if let Some(task) = state.tasks.get_mut(&task_id) {
+            task.kill_requested = true;
+            self.mark_dirty();
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Task with ID {} not found when trying to mark it killed", task_id))
+        }
+    }
+
+    // Records the exit status reported by gavel-shim
+    pub async fn set_task_exit_info(&self, task_id: u64, exit_info: ExitInfo) -> Result<()> {
+        let mut state = self.inner.write().await;
+        if let Some(task) = state.tasks.get_mut(&task_id) {
+            task.exit_info = Some(exit_info);
+            self.mark_dirty();
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Task with ID {} not found when trying to set exit info", task_id))
+        }
+    }
+
     // Modified to accept optional assigned GPU IDs and failure_reason
     pub async fn update_task_state(
         &self,
@@ -110,9 +470,21 @@ impl DaemonState {
 
             task.state = new_state_val.clone();
 
-            if new_state_val == TaskState::Failed {
+            if matches!(
+                new_state_val,
+                TaskState::Failed
+                    | TaskState::Cancelled
+                    | TaskState::TimedOut
+                    | TaskState::Retrying
+            ) {
                 task.failure_reason = failure_reason;
-                info!("Task {} ({}) set to Failed. Reason: {}", task_id, task.name, task.failure_reason.as_deref().unwrap_or("None"));
+                info!(
+                    "Task {} ({}) set to {:?}. Reason: {}",
+                    task_id,
+                    task.name,
+                    new_state_val,
+                    task.failure_reason.as_deref().unwrap_or("None")
+                );
             } else {
                 // Clear failure reason if task is moving to a non-failed state
                 task.failure_reason = None;
@@ -121,9 +493,32 @@ impl DaemonState {
             if let Some(gpus) = assigned_gpu_ids {
                 task.gpu_ids = gpus;
             }
+            if new_state_val != TaskState::Waiting {
+                task.pending_reason = None;
+            }
+            if new_state_val == TaskState::Running {
+                task.exit_info = None; // Left over from a previous run
+                task.preempted = false;
+                task.start_time = Some(now_secs());
+                task.timed_out_at = None;
+                task.retry_at = None;
+                task.kill_requested = false;
+            }
 
             // If state changes, update queue lists
             if old_state != new_state_val {
+                // GPUs were released or the task is waiting again
+                if old_state == TaskState::Running || new_state_val == TaskState::Waiting {
+                    self.wake_scheduler();
+                }
+                self.publish(EventKind::TaskStateChanged {
+                    task_id,
+                    name: task.name.clone(),
+                    queue: queue_name.clone(),
+                    from: old_state.clone(),
+                    to: new_state_val.clone(),
+                    reason: task.failure_reason.clone(),
+                });
                 if let Some(queue) = state.queues.get_mut(&queue_name) {
                     // Remove from old state's list in the queue
                     match old_state {
@@ -148,7 +543,11 @@ impl DaemonState {
                                 queue.running_task_ids.push(task_id);
                             }
                         }
-                        TaskState::Failed | TaskState::Finished => {
+                        TaskState::Failed
+                        | TaskState::Finished
+                        | TaskState::Cancelled
+                        | TaskState::TimedOut
+                        | TaskState::Retrying => {
                             // For Failed or Finished, ensure it's removed from active lists
                             // (already done by removing from old_state list if it was Waiting/Running)
                             // Log the transition.
@@ -159,6 +558,7 @@ impl DaemonState {
                     warn!("Task {}'s queue {} not found while updating task state from {:?} to {:?}.", task_id, queue_name, old_state, new_state_val);
                 }
             }
+            self.mark_dirty();
             Ok(())
         } else {
             error!("Task {} not found when trying to update state to {:?}.", task_id, new_state_val);
@@ -207,7 +607,11 @@ impl DaemonState {
                         old_queue.running_task_ids.retain(|&id| id != task_id);
                         info!("Task {} removed from running list of old queue {}.", task_id, old_queue_name);
                     }
-                    TaskState::Finished | TaskState::Failed => {
+                    TaskState::Finished
+                    | TaskState::Failed
+                    | TaskState::Cancelled
+                    | TaskState::TimedOut
+                    | TaskState::Retrying => {
                         let mut found_in_waiting = false;
                         old_queue.waiting_task_ids.retain(|&id| if id == task_id { found_in_waiting = true; false } else { true });
                         let mut found_in_running = false;
@@ -239,6 +643,8 @@ impl DaemonState {
                 info!("Task {} already present in lists of new queue {} or was not added to waiting list (e.g. same queue move).", task_id, new_queue_name);
             }
         }
+        self.mark_dirty();
+        self.wake_scheduler();
         Ok(())
     }
 
@@ -246,6 +652,8 @@ impl DaemonState {
         let mut state = self.inner.write().await;
         if let Some(task) = state.tasks.get_mut(&task_id) {
             task.priority = new_priority;
+            self.mark_dirty();
+            self.wake_scheduler();
             Ok(())
         } else {
             Err(anyhow::anyhow!("Task with ID {} not found", task_id))
@@ -271,7 +679,11 @@ impl DaemonState {
                         queue.running_task_ids.retain(|&id| id != task_id);
                         info!("Task {} (Running) removed from queue {} running list.", task_id, queue_name);
                     }
-                    TaskState::Finished | TaskState::Failed => {
+                    TaskState::Finished
+                    | TaskState::Failed
+                    | TaskState::Cancelled
+                    | TaskState::TimedOut
+                    | TaskState::Retrying => {
                         // Tasks in Finished or Failed state should ideally already be out of
                         // waiting_task_ids and running_task_ids due to state updates.
                         // This is a safeguard.
@@ -296,20 +708,66 @@ impl DaemonState {
                 );
             }
             info!("Task {} (ID: {}) metadata removed from state.", task.name, task_id);
+            self.mark_dirty();
+            self.wake_scheduler();
         } else {
             warn!("Attempted to remove non-existent task with ID: {}", task_id);
         }
         Ok(removed_task)
     }
 
+    // Marks a task as having a monitor that will record its exit
+    pub fn watch_task(&self, task_id: u64) {
+        self.watched_tasks.lock().unwrap().insert(task_id);
+    }
+
+    pub fn unwatch_task(&self, task_id: u64) {
+        self.watched_tasks.lock().unwrap().remove(&task_id);
+    }
+
+    pub fn is_task_watched(&self, task_id: u64) -> bool {
+        self.watched_tasks.lock().unwrap().contains(&task_id)
+    }
+
+    // Keeps GPUs freed by preemption for the task they were freed for, until `ttl` has passed
+    pub fn reserve_gpus(&self, gpu_ids: &[u32], task_id: u64, ttl: Duration) {
+        let expires_at = Instant::now() + ttl;
+        let mut reservations = self.gpu_reservations.lock().unwrap();
+        for gpu_id in gpu_ids {
+            reservations.insert(*gpu_id, (task_id, expires_at));
+        }
+    }
+
+    // GPU ID -> task ID of the reservations that have not expired
+    pub fn get_gpu_reservations(&self) -> HashMap<u32, u64> {
+        let now = Instant::now();
+        let mut reservations = self.gpu_reservations.lock().unwrap();
+        reservations.retain(|_, (_, expires_at)| *expires_at > now);
+        reservations.iter().map(|(gpu_id, (task_id, _))| (*gpu_id, *task_id)).collect()
+    }
+
+    // Replaces the GPUs kept for blocked tasks by backfilling
+    pub fn set_backfill_reservations(&self, reservations: BackfillReservations) {
+        *self.backfill_reservations.lock().unwrap() = reservations;
+    }
+
+    pub fn get_backfill_reservations(&self) -> BackfillReservations {
+        self.backfill_reservations.lock().unwrap().clone()
+    }
+
     // --- Queue related methods ---
 
     pub async fn add_queue(&self, queue: QueueMeta) -> Result<()> {
         let mut state = self.inner.write().await;
         let queue_name = queue.name.clone();
+        let priority = queue.priority;
         if state.queues.insert(queue_name.clone(), queue).is_some() {
             warn!("Queue with name '{}' already existed and was overwritten.", queue_name);
+        } else {
+            self.publish(EventKind::QueueCreated { name: queue_name, priority });
         }
+        self.mark_dirty();
+        self.wake_scheduler();
         Ok(())
     }
 
@@ -329,6 +787,111 @@ impl DaemonState {
         let mut state = self.inner.write().await;
         if let Some(queue) = state.queues.get_mut(&queue_name) {
             queue.resource_limit = new_limit;
+            self.mark_dirty();
+            self.wake_scheduler();
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Queue '{}' not found", queue_name))
+        }
+    }
+
+    pub async fn update_queue_max_runtime(
+        &self,
+        queue_name: String,
+        max_runtime: Option<u64>,
+    ) -> Result<()> {
+        let mut state = self.inner.write().await;
+        if let Some(queue) = state.queues.get_mut(&queue_name) {
+            queue.default_max_runtime = max_runtime;
+            self.mark_dirty();
+            self.wake_scheduler();
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Queue '{}' not found", queue_name))
+        }
+    }
+
+    pub async fn update_queue_retry_policy(
+        &self,
+        queue_name: String,
+        policy: Option<RetryPolicy>,
+    ) -> Result<()> {
+        let mut state = self.inner.write().await;
+        if let Some(queue) = state.queues.get_mut(&queue_name) {
+            queue.default_retry_policy = policy;
+            self.mark_dirty();
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Queue '{}' not found", queue_name))
+        }
+    }
+
+    pub async fn update_queue_gpu_mode(&self, queue_name: String, mode: GpuMode) -> Result<()> {
+        let mut state = self.inner.write().await;
+        if let Some(queue) = state.queues.get_mut(&queue_name) {
+            queue.gpu_mode = mode;
+            self.mark_dirty();
+            self.wake_scheduler();
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Queue '{}' not found", queue_name))
+        }
+    }
+
+    pub async fn update_queue_placement(
+        &self,
+        queue_name: String,
+        placement: PlacementStrategy,
+    ) -> Result<()> {
+        let mut state = self.inner.write().await;
+        if let Some(queue) = state.queues.get_mut(&queue_name) {
+            queue.placement = placement;
+            self.mark_dirty();
+            self.wake_scheduler();
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Queue '{}' not found", queue_name))
+        }
+    }
+
+    pub async fn update_queue_fair_share(&self, queue_name: String, weight: u32) -> Result<()> {
+        let mut state = self.inner.write().await;
+        if let Some(queue) = state.queues.get_mut(&queue_name) {
+            queue.fair_share = weight;
+            self.mark_dirty();
+            self.wake_scheduler();
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Queue '{}' not found", queue_name))
+        }
+    }
+
+    /// Charges every queue for the GPUs its running tasks hold (`gpus_in_use`, by queue name)
+    /// since the last call, decaying older usage. Not worth a snapshot on its own: the usage
+    /// is saved with the next mutation.
+    pub async fn accrue_queue_usage(
+        &self,
+        gpus_in_use: &HashMap<String, f64>,
+        half_life_secs: u64,
+    ) {
+        let now = now_secs();
+        let mut state = self.inner.write().await;
+        for queue in state.queues.values_mut() {
+            let gpus = gpus_in_use.get(&queue.name).copied().unwrap_or(0.0);
+            queue.usage.accrue(gpus, now, half_life_secs);
+        }
+    }
+
+    pub async fn update_queue_max_concurrent(
+        &self,
+        queue_name: String,
+        max_concurrent: u8,
+    ) -> Result<()> {
+        let mut state = self.inner.write().await;
+        if let Some(queue) = state.queues.get_mut(&queue_name) {
+            queue.max_concurrent = max_concurrent;
+            self.mark_dirty();
+            self.wake_scheduler();
             Ok(())
         } else {
             Err(anyhow::anyhow!("Queue '{}' not found", queue_name))
@@ -339,23 +902,21 @@ impl DaemonState {
 
     // --- GPU related methods ---}
 
-    // New method to update stats for ALL GPUs
+    // New method to update stats for ALL GPUs. Sampling blocks (NVML calls), so it runs on the
+    // blocking thread pool and the state lock is only taken once the stats are in.
     pub async fn update_all_gpu_stats(&self) -> Result<()> {
-        // Note: Creating GpuMonitor here assumes NVML can be initialized repeatedly.
-        // Consider creating it once if performance is critical and NVML allows it.
-        let monitor = match GpuMonitor::new() {
-            Ok(m) => m,
-            Err(e) => {
-                error!("Failed to initialize GpuMonitor: {}. Skipping GPU stats update.", e);
-                // Decide if this should return an error or just log and continue
-                return Err(e); // Propagate the error for now
-            }
-        };
-
-        let stats_results = match monitor.get_all_stats() {
+        let backend = self.gpu_backend.clone();
+        let sampled = tokio::task::spawn_blocking(move || backend.get_all_stats())
+            .await
+            .context("GPU sampling task panicked")?;
+        let stats_results = match sampled {
             Ok(sr) => sr,
             Err(e) => {
-                error!("Failed to get all GPU stats: {}. Skipping GPU stats update.", e);
+                error!(
+                    "Failed to get all GPU stats from {} backend: {}. Skipping GPU stats update.",
+                    self.gpu_backend.name(),
+                    e
+                );
                 return Err(e); // Propagate the error
             }
         };
@@ -379,17 +940,25 @@ impl DaemonState {
             match stats_result {
                 Ok(stats) => {
                     state.gpu_stats.insert(gpu_id, stats);
+                    self.set_gpu_health(&state, gpu_id, None);
+                }
+                Err(e) => {
+                    warn!("Failed to get stats for GPU {}: {}", gpu_id, e);
+                    self.set_gpu_health(&state, gpu_id, Some(format!("Stats query failed: {}", e)));
                 }
-                Err(e) => warn!("Failed to get stats for GPU {}: {}", gpu_id, e),
             }
         }
 
         // Remove stats for GPUs that are no longer detected or became ignored
         let existing_ids: Vec<u32> = state.gpu_stats.keys().cloned().collect();
         for gpu_id in existing_ids {
-            if !current_gpu_ids.contains(&gpu_id) || state.ignored_gpus.contains(&gpu_id) {
-                if state.gpu_stats.remove(&gpu_id).is_some() {
-                    info!("Removed stale/ignored stats for GPU {}", gpu_id);
+            if (!current_gpu_ids.contains(&gpu_id) || state.ignored_gpus.contains(&gpu_id))
+                && state.gpu_stats.remove(&gpu_id).is_some()
+            {
+                info!("Removed stale/ignored stats for GPU {}", gpu_id);
+                if !current_gpu_ids.contains(&gpu_id) {
+                    let reason = "GPU is no longer detected".to_string();
+                    self.set_gpu_health(&state, gpu_id, Some(reason));
                 }
             }
         }
@@ -397,6 +966,29 @@ impl DaemonState {
         Ok(())
     }
 
+    /// GPUs whose last stats query failed or that disappeared
+    pub fn get_unhealthy_gpus(&self) -> HashSet<u32> {
+        self.unhealthy_gpus.lock().unwrap().clone()
+    }
+
+    // Records whether the last stats query of a GPU succeeded (None) or why it failed, and
+    // publishes the changes
+    fn set_gpu_health(&self, state: &InnerDaemonState, gpu_id: u32, problem: Option<String>) {
+        let mut unhealthy = self.unhealthy_gpus.lock().unwrap();
+        let changed = match &problem {
+            Some(_) => unhealthy.insert(gpu_id),
+            None => unhealthy.remove(&gpu_id),
+        };
+        if changed {
+            self.publish(EventKind::GpuHealthChanged {
+                gpu_id,
+                healthy: problem.is_none(),
+                queue: state.gpu_allocations.get(&gpu_id).cloned().flatten(),
+                reason: problem,
+            });
+        }
+    }
+
     pub async fn get_gpu_stats(&self, gpu_id: u32) -> Option<GpuStats> {
         self.inner.read().await.gpu_stats.get(&gpu_id).cloned()
     }
@@ -408,12 +1000,16 @@ impl DaemonState {
     pub async fn set_gpu_ignore(&self, gpu_id: u32) -> Result<()> {
         let mut state = self.inner.write().await;
         state.ignored_gpus.insert(gpu_id);
+        self.mark_dirty();
+        self.wake_scheduler();
         Ok(())
     }
 
     pub async fn unset_gpu_ignore(&self, gpu_id: u32) -> Result<()> {
         let mut state = self.inner.write().await;
         state.ignored_gpus.remove(&gpu_id);
+        self.mark_dirty();
+        self.wake_scheduler();
         Ok(())
     }
 
@@ -421,15 +1017,36 @@ impl DaemonState {
         self.inner.read().await.ignored_gpus.clone()
     }
 
+    // Also keeps the allocated_gpus list of the old and the new queue in sync, since the
+    // scheduler places tasks by that list
     pub async fn set_gpu_allocation(&self, gpu_id: u32, queue_name: Option<String>) -> Result<()> {
         let mut state = self.inner.write().await;
-        state.gpu_allocations.insert(gpu_id, queue_name.clone());
+        let previous = state.gpu_allocations.insert(gpu_id, queue_name.clone()).flatten();
+        if previous != queue_name {
+            self.publish(EventKind::GpuAllocationChanged {
+                gpu_id,
+                from: previous.clone(),
+                to: queue_name.clone(),
+            });
+        }
+        if let Some(queue) = previous.and_then(|name| state.queues.get_mut(&name)) {
+            queue.allocated_gpus.retain(|&id| id as u32 != gpu_id);
+        }
+        if let Some(queue) = queue_name.and_then(|name| state.queues.get_mut(&name)) {
+            if !queue.allocated_gpus.contains(&(gpu_id as u8)) {
+                queue.allocated_gpus.push(gpu_id as u8);
+            }
+        }
+        self.mark_dirty();
+        self.wake_scheduler();
         Ok(())
     }
 
     pub async fn remove_gpu_allocation(&self, gpu_id: u32) -> Result<()> {
         let mut state = self.inner.write().await;
         state.gpu_allocations.remove(&gpu_id);
+        self.mark_dirty();
+        self.wake_scheduler();
         Ok(())
     }
 
@@ -444,3 +1061,191 @@ impl DaemonState {
 
     // TODO: Add methods for scheduler interactions (e.g., find available GPU)
 }
+
+// Writes `bytes` to `path` via a temporary file in the same directory and a rename,
+// so a crash mid-write never leaves a truncated state file behind.
+async fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            tokio::fs::create_dir_all(parent).await.with_context(|| {
+                format!("Failed to create state directory: {}", parent.display())
+            })?;
+        }
+    }
+
+    let mut tmp_name = path.as_os_str().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+
+    // The state holds the environment captured at submission, keep it private to the owner
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp_path)
+        .await
+        .with_context(|| {
+            format!("Failed to create temporary state file: {}", tmp_path.display())
+        })?;
+    // `mode` only applies to new files, not to a temporary file left behind by a crash
+    file.set_permissions(std::fs::Permissions::from_mode(0o600)).await.with_context(|| {
+        format!("Failed to restrict temporary state file: {}", tmp_path.display())
+    })?;
+    file.write_all(bytes)
+        .await
+        .with_context(|| format!("Failed to write temporary state file: {}", tmp_path.display()))?;
+    file.sync_all()
+        .await
+        .with_context(|| format!("Failed to sync temporary state file: {}", tmp_path.display()))?;
+    drop(file);
+
+    tokio::fs::rename(&tmp_path, path)
+        .await
+        .with_context(|| format!("Failed to move state file into place: {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gavel_core::gpu::simulated::{SimulatedBackend, SimulatedFixture};
+
+    fn new_state(persist_path: Option<PathBuf>) -> DaemonState {
+        let backend = SimulatedBackend::new(SimulatedFixture {
+            devices: vec![],
+            timeline: vec![],
+            loop_secs: None,
+        });
+        let spool_dir = PathBuf::from(spool::DEFAULT_SPOOL_DIR);
+        DaemonState::new(Arc::new(backend), persist_path, Duration::from_secs(1), spool_dir)
+    }
+
+    fn task(id: u64) -> TaskMeta {
+        TaskMeta { id, name: format!("task_{}", id), queue: "q".to_string(), ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn snapshot_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let state = new_state(Some(path.clone()));
+        let id = state.next_task_id().await.unwrap();
+        state.add_task(task(id)).await.unwrap();
+        state.save_snapshot().await.unwrap();
+
+        // write_atomic renames its temporary file over the state file
+        let entries: Vec<_> =
+            std::fs::read_dir(dir.path()).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert_eq!(entries, vec![std::ffi::OsString::from("state.json")]);
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let restored = new_state(Some(path));
+        assert!(restored.load_snapshot().await.unwrap());
+        assert_eq!(restored.get_task(id).await, state.get_task(id).await);
+        assert!(restored.get_queue("q").await.is_some());
+        assert!(restored.next_task_id().await.unwrap() > id);
+    }
+
+    #[tokio::test]
+    async fn load_snapshot_without_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = new_state(Some(dir.path().join("state.json")));
+        assert!(!state.load_snapshot().await.unwrap());
+        assert!(!new_state(None).load_snapshot().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn load_snapshot_moves_corrupt_file_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        std::fs::write(&path, b"{\"tasks\": {").unwrap();
+
+        let state = new_state(Some(path.clone()));
+        assert!(!state.load_snapshot().await.unwrap());
+        assert!(state.get_all_tasks().await.is_empty());
+        assert!(!path.exists());
+        let entries: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].starts_with("state.json.corrupt-"));
+        assert_eq!(std::fs::read(dir.path().join(&entries[0])).unwrap(), b"{\"tasks\": {");
+    }
+
+    #[tokio::test]
+    async fn load_snapshot_continues_above_known_ids() {
+        // Snapshots written before `last_task_id` existed
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let old = serde_json::json!({
+            "tasks": { "99999999": task(99_999_999) },
+            "queues": {},
+            "gpu_stats": {},
+            "gpu_allocations": {},
+            "ignored_gpus": [],
+        });
+        std::fs::write(&path, serde_json::to_vec(&old).unwrap()).unwrap();
+
+        let state = new_state(Some(path));
+        assert!(state.load_snapshot().await.unwrap());
+        assert!(state.next_task_id().await.unwrap() > 99_999_999);
+    }
+
+    #[tokio::test]
+    async fn task_ids_always_grow() {
+        let state = new_state(None);
+        let first = state.next_task_id().await.unwrap();
+        let second = state.next_task_id().await.unwrap();
+        assert!(second > first);
+
+        // An ID given by the submitter is never handed out again
+        state.add_task(task(second + 1_000)).await.unwrap();
+        assert!(state.next_task_id().await.unwrap() > second + 1_000);
+    }
+
+    #[tokio::test]
+    async fn add_task_refuses_duplicate_ids() {
+        let state = new_state(None);
+        state.add_task(task(7)).await.unwrap();
+        let mut duplicate = task(7);
+        duplicate.name = "other".to_string();
+        let err = state.add_task(duplicate).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<RpcError>(), Some(RpcError::Conflict(_))));
+        assert_eq!(state.get_task(7).await.unwrap().name, "task_7");
+    }
+
+    #[tokio::test]
+    async fn add_task_refuses_ids_at_the_end_of_the_range() {
+        let state = new_state(None);
+        for id in [MAX_SUBMITTED_TASK_ID + 1, u64::MAX] {
+            let err = state.add_task(task(id)).await.unwrap_err();
+            assert!(matches!(err.downcast_ref::<RpcError>(), Some(RpcError::InvalidArgument(_))));
+            assert!(state.get_task(id).await.is_none());
+        }
+        state.add_task(task(MAX_SUBMITTED_TASK_ID)).await.unwrap();
+        assert_eq!(state.next_task_id().await.unwrap(), MAX_SUBMITTED_TASK_ID + 1);
+    }
+
+    #[tokio::test]
+    async fn next_task_id_fails_when_ids_run_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let full = serde_json::json!({
+            "tasks": {},
+            "queues": {},
+            "gpu_stats": {},
+            "gpu_allocations": {},
+            "ignored_gpus": [],
+            "last_task_id": u64::MAX,
+        });
+        std::fs::write(&path, serde_json::to_vec(&full).unwrap()).unwrap();
+
+        let state = new_state(Some(path));
+        assert!(state.load_snapshot().await.unwrap());
+        let err = state.next_task_id().await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<RpcError>(), Some(RpcError::Internal(_))));
+    }
+}
@@ -0,0 +1,144 @@
+// src/daemon/walltime.rs
+// Max runtime (walltime) limits of running tasks
+use crate::daemon::process;
+use crate::daemon::state::DaemonState;
+use anyhow::Result;
+use gavel_core::utils::models::{QueueMeta, TaskMeta, TaskState};
+use gavel_core::utils::procfs;
+use gavel_core::utils::time::{format_duration_secs, now_secs};
+use log::{error, warn};
+use nix::errno::Errno;
+use nix::sys::signal::Signal;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Max runtime of a task in seconds: its own limit, otherwise the default of its queue
+pub fn effective_max_runtime(task: &TaskMeta, queue: Option<&QueueMeta>) -> Option<u64> {
+    task.max_runtime.or_else(|| queue.and_then(|q| q.default_max_runtime))
+}
+
+// What the walltime check does to a running task
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WalltimeAction {
+    Nothing,
+    Terminate, // Over its max runtime: SIGTERM
+    Kill,      // Still running `grace` after the SIGTERM: SIGKILL
+}
+
+// Limits come from user-supplied durations and may be as large as u64::MAX, so the
+// deadlines saturate instead of wrapping around to the past
+fn walltime_action(
+    start_time: u64,
+    limit: u64,
+    timed_out_at: Option<u64>,
+    grace: Duration,
+    now: u64,
+) -> WalltimeAction {
+    match timed_out_at {
+        None if now >= start_time.saturating_add(limit) => WalltimeAction::Terminate,
+        Some(at) if now >= at.saturating_add(grace.as_secs()) => WalltimeAction::Kill,
+        _ => WalltimeAction::Nothing,
+    }
+}
+
+/// Sends SIGTERM to the processes of running tasks that have exceeded their max runtime,
+/// and SIGKILL to those still alive `grace` after that. The deadline is stored in the task,
+/// so it is enforced across daemon restarts; the task's monitor records the TimedOut outcome.
+pub async fn enforce_max_runtime(state: &DaemonState, grace: Duration) -> Result<()> {
+    let queues: HashMap<String, QueueMeta> =
+        state.get_all_queues().await.into_iter().map(|q| (q.name.clone(), q)).collect();
+    let now = now_secs();
+
+    for task in state.get_all_tasks().await {
+        if task.state != TaskState::Running {
+            continue;
+        }
+        let (Some(pid_val), Some(start_time)) = (task.pid, task.start_time) else {
+            continue;
+        };
+        let Some(limit) = effective_max_runtime(&task, queues.get(&task.queue)) else {
+            continue;
+        };
+        let same_process = task.pid_start_time.is_none_or(|t| procfs::is_same_process(pid_val, t));
+        if !same_process {
+            continue; // Already exited, its monitor will notice
+        }
+
+        match walltime_action(start_time, limit, task.timed_out_at, grace, now) {
+            WalltimeAction::Terminate => {
+                warn!(
+                    "Task '{}' (ID: {}) exceeded its max runtime of {}, sending SIGTERM to PID {}.",
+                    task.name,
+                    task.id,
+                    format_duration_secs(limit),
+                    pid_val
+                );
+                state.set_task_timed_out(task.id, now).await?;
+                match process::signal_task(&task, Signal::SIGTERM) {
+                    Ok(_) | Err(Errno::ESRCH) => {}
+                    Err(e) => error!("Failed to send SIGTERM to PID {}: {}", pid_val, e),
+                }
+            }
+            WalltimeAction::Kill => {
+                warn!(
+                    "Timed out task '{}' (ID: {}) is still running after {:?}, sending SIGKILL to PID {}.",
+                    task.name, task.id, grace, pid_val
+                );
+                match process::signal_task(&task, Signal::SIGKILL) {
+                    Ok(_) | Err(Errno::ESRCH) => {}
+                    Err(e) => error!("Failed to send SIGKILL to PID {}: {}", pid_val, e),
+                }
+            }
+            WalltimeAction::Nothing => {}
+        }
+    }
+    Ok(())
+}
+
+/// Failure reason for a task that was stopped for exceeding its max runtime, None if it
+/// was not
+pub async fn timeout_reason(state: &DaemonState, task_id: u64) -> Option<String> {
+    let task = state.get_task(task_id).await?;
+    task.timed_out_at?;
+    let queue = state.get_queue(&task.queue).await;
+    let limit = effective_max_runtime(&task, queue.as_ref())?;
+    Some(format!("Task exceeded its max runtime of {}.", format_duration_secs(limit)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRACE: Duration = Duration::from_secs(30);
+
+    #[test]
+    fn terminates_tasks_over_their_max_runtime() {
+        assert_eq!(walltime_action(100, 60, None, GRACE, 159), WalltimeAction::Nothing);
+        assert_eq!(walltime_action(100, 60, None, GRACE, 160), WalltimeAction::Terminate);
+        assert_eq!(walltime_action(100, 0, None, GRACE, 100), WalltimeAction::Terminate);
+    }
+
+    #[test]
+    fn kills_timed_out_tasks_after_the_grace_period() {
+        assert_eq!(walltime_action(100, 60, Some(160), GRACE, 160), WalltimeAction::Nothing);
+        assert_eq!(walltime_action(100, 60, Some(160), GRACE, 189), WalltimeAction::Nothing);
+        assert_eq!(walltime_action(100, 60, Some(160), GRACE, 190), WalltimeAction::Kill);
+        let no_grace = Duration::ZERO;
+        assert_eq!(walltime_action(100, 60, Some(160), no_grace, 160), WalltimeAction::Kill);
+    }
+
+    #[test]
+    fn huge_limits_never_expire() {
+        let now = 1_700_000_000;
+        assert_eq!(walltime_action(now - 10, u64::MAX, None, GRACE, now), WalltimeAction::Nothing);
+        assert_eq!(
+            walltime_action(now - 10, u64::MAX - 5, None, GRACE, now),
+            WalltimeAction::Nothing
+        );
+        let huge_grace = Duration::from_secs(u64::MAX);
+        assert_eq!(
+            walltime_action(now - 10, 5, Some(now - 5), huge_grace, now),
+            WalltimeAction::Nothing
+        );
+    }
+}
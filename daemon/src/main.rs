@@ -1,9 +1,10 @@
 // src/main.rs
 use anyhow::{Context, Ok, Result}; // Import Result
-use gavel_core::utils::logging;
+use gavel_core::gpu::backend::{self, GpuBackendKind};
+use gavel_core::utils::{fair_share, logging, spool};
 use log::LevelFilter;
 use serde::Deserialize;
-use std::{env, fs, path::Path};
+use std::{env, fs, path::Path, time::Duration};
 
 mod daemon;
 
@@ -15,6 +16,66 @@ struct Config {
     log_path: String,
     #[serde(rename = "sock-path")] // Added sock-path
     sock_path: String,
+    #[serde(rename = "gpu-backend", default)] // "nvml" (default) or "simulated"
+    gpu_backend: GpuBackendKind,
+    #[serde(rename = "gpu-fixture", default)] // Fixture file for the simulated backend
+    gpu_fixture: Option<String>,
+    #[serde(rename = "state-path", default)] // State file; persistence is disabled if unset
+    state_path: Option<String>,
+    #[serde(rename = "spool-dir", default = "default_spool_dir")] // Task outcomes from gavel-shim
+    spool_dir: String,
+    #[serde(rename = "snapshot-interval-secs", default = "default_snapshot_interval_secs")]
+    snapshot_interval_secs: u64,
+    #[serde(rename = "scheduler-interval-secs", default = "default_scheduler_interval_secs")]
+    scheduler_interval_secs: u64, // Fallback tick; state changes wake the scheduler earlier
+    #[serde(rename = "gpu-monitor-interval-secs", default = "default_gpu_monitor_interval_secs")]
+    gpu_monitor_interval_secs: u64,
+    // Preempt lower-priority tasks for blocked GPU tasks. Only tasks still running on GPUs that
+    // were reallocated to the blocked task's queue are preempted, never other queues' GPUs.
+    #[serde(default)]
+    preemption: bool,
+    #[serde(rename = "preemption-grace-secs", default = "default_preemption_grace_secs")]
+    preemption_grace_secs: u64,
+    #[serde(rename = "fair-share", default)] // Serve queues by GPU time used against their weight
+    fair_share: bool,
+    #[serde(rename = "fair-share-half-life-secs", default = "default_fair_share_half_life_secs")]
+    fair_share_half_life_secs: u64,
+    #[serde(rename = "timeout-grace-secs", default = "default_timeout_grace_secs")]
+    timeout_grace_secs: u64, // SIGTERM -> SIGKILL delay for tasks over their max runtime
+    #[serde(rename = "kill-grace-secs", default = "default_kill_grace_secs")]
+    kill_grace_secs: u64, // SIGTERM -> SIGKILL delay for `task kill` and leftover processes
+}
+
+fn default_spool_dir() -> String {
+    spool::DEFAULT_SPOOL_DIR.to_string()
+}
+
+fn default_snapshot_interval_secs() -> u64 {
+    30
+}
+
+fn default_scheduler_interval_secs() -> u64 {
+    3
+}
+
+fn default_gpu_monitor_interval_secs() -> u64 {
+    3
+}
+
+fn default_preemption_grace_secs() -> u64 {
+    30
+}
+
+fn default_fair_share_half_life_secs() -> u64 {
+    fair_share::DEFAULT_HALF_LIFE_SECS
+}
+
+fn default_timeout_grace_secs() -> u64 {
+    30
+}
+
+fn default_kill_grace_secs() -> u64 {
+    10
 }
 
 // Use tokio::main for the async runtime
@@ -65,8 +126,32 @@ async fn main() -> Result<()> {
     // Ensure logger initialization happens before any potential logging in daemon::start
     logging::SimpleLogger::init(&log_path_str, log_level).context("Failed to initialize logger")?;
 
-    // Pass sock_path to daemon start function and await its completion
-    daemon::start(&sock_path_str).await?; // Pass state to daemon::start
+    // 选择 GPU 后端
+    let gpu_backend = backend::open_backend(&config.gpu_backend, config.gpu_fixture.as_deref())
+        .context("Failed to initialize GPU backend")?;
+    log::info!("Using {} GPU backend", gpu_backend.name());
+
+    let options = daemon::DaemonOptions {
+        sock_path: sock_path_str,
+        gpu_backend,
+        state_path: config.state_path.map(Into::into),
+        snapshot_interval: Duration::from_secs(config.snapshot_interval_secs.max(1)),
+        scheduler: daemon::scheduler::SchedulerConfig {
+            preemption_grace: config
+                .preemption
+                .then(|| Duration::from_secs(config.preemption_grace_secs)),
+            timeout_grace: Duration::from_secs(config.timeout_grace_secs),
+            fair_share: config.fair_share,
+            fair_share_half_life_secs: config.fair_share_half_life_secs,
+            interval: Duration::from_secs(config.scheduler_interval_secs.max(1)),
+        },
+        gpu_monitor_interval: Duration::from_secs(config.gpu_monitor_interval_secs.max(1)),
+        kill_grace: Duration::from_secs(config.kill_grace_secs),
+        spool_dir: config.spool_dir.into(),
+    };
+
+    // Pass options to daemon start function and await its completion
+    daemon::start(options).await?;
 
     Ok(())
 }
@@ -0,0 +1,212 @@
+// src/main.rs
+// gavel-shim: launched by gavel-daemon for every task.
+//
+//   gavel-daemon ──> gavel-shim (exits once the task is running)
+//                      └── gavel-shim supervisor (new session, outlives the daemon)
+//                            └── task command (own process group)
+//
+// The supervisor waits for the task and writes its exit code, signal and rusage to
+// the status file in the spool directory, so the outcome survives daemon restarts.
+// Processes the task leaves behind in its group are terminated first, so the status
+// file only appears once nothing of the task is running anymore.
+use std::env;
+use std::ffi::CString;
+use std::os::fd::OwnedFd;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread::sleep;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use gavel_core::utils::models::ExitInfo;
+use gavel_core::utils::procfs::process_start_time;
+use gavel_core::utils::spool::{self, ShimPidInfo};
+use nix::errno::Errno;
+use nix::sys::prctl;
+use nix::sys::resource::{getrusage, UsageWho};
+use nix::sys::signal::{killpg, Signal};
+use nix::sys::time::TimeVal;
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::unistd::{execvp, fork, getpid, pipe, read, setpgid, setsid, write, ForkResult, Pid};
+
+// Time left to leftover processes between SIGTERM and SIGKILL, unless --kill-grace is given
+const DEFAULT_KILL_GRACE: Duration = Duration::from_secs(10);
+const GROUP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let mut kill_grace = DEFAULT_KILL_GRACE;
+    if args.first().is_some_and(|a| a == "--kill-grace") && args.len() > 1 {
+        match args[1].parse::<u64>() {
+            Ok(secs) => kill_grace = Duration::from_secs(secs),
+            Err(_) => {
+                eprintln!("gavel-shim: invalid --kill-grace value '{}'", args[1]);
+                return ExitCode::from(2);
+            }
+        }
+        args.drain(..2);
+    }
+    if args.len() < 2 {
+        eprintln!("Usage: gavel-shim [--kill-grace <secs>] <spool_dir> <program> [args...]");
+        return ExitCode::from(2);
+    }
+    let spool_dir = PathBuf::from(&args[0]);
+    let command = &args[1..];
+
+    // The supervisor reports through this pipe once the pid file is written
+    let (ready_r, ready_w) = match pipe() {
+        Ok(fds) => fds,
+        Err(e) => {
+            eprintln!("gavel-shim: failed to create pipe: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // SAFETY: the shim is single-threaded at this point
+    match unsafe { fork() } {
+        Ok(ForkResult::Parent { .. }) => {
+            drop(ready_w);
+            let mut buf = [0u8; 1];
+            match read(&ready_r, &mut buf) {
+                Ok(1) => ExitCode::SUCCESS,
+                _ => ExitCode::FAILURE, // Supervisor died before the task started
+            }
+        }
+        Ok(ForkResult::Child) => {
+            drop(ready_r);
+            match supervise(&spool_dir, command, kill_grace, ready_w) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("gavel-shim: {:#}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("gavel-shim: fork failed: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn supervise(
+    spool_dir: &Path,
+    command: &[String],
+    kill_grace: Duration,
+    ready: OwnedFd,
+) -> Result<()> {
+    // Detach from the daemon's session so signals aimed at it do not reach the supervisor
+    setsid().context("setsid failed")?;
+    // Orphaned processes of the task are re-parented to the supervisor, which reaps them
+    if let Err(e) = prctl::set_child_subreaper(true) {
+        eprintln!("gavel-shim: failed to become a child subreaper: {}", e);
+    }
+
+    let c_args = command
+        .iter()
+        .map(|a| CString::new(a.as_str()))
+        .collect::<Result<Vec<_>, _>>()
+        .context("Command contains a NUL byte")?;
+
+    // SAFETY: the supervisor is single-threaded
+    let child = match unsafe { fork() }.context("fork failed")? {
+        ForkResult::Child => {
+            drop(ready);
+            // The daemon signals the whole group, which reaches everything the task starts
+            let _ = setpgid(Pid::from_raw(0), Pid::from_raw(0));
+            let err = execvp(&c_args[0], &c_args).unwrap_err();
+            eprintln!("gavel-shim: failed to execute '{}': {}", command[0], err);
+            std::process::exit(127);
+        }
+        ForkResult::Parent { child } => child,
+    };
+    // Also set in the parent, so the group exists before anyone signals it
+    let _ = setpgid(child, child);
+
+    let pid_info = ShimPidInfo {
+        shim_pid: getpid().as_raw(),
+        shim_start_time: process_start_time(getpid().as_raw()),
+        pid: child.as_raw(),
+        pid_start_time: process_start_time(child.as_raw()),
+        pgid: Some(child.as_raw()),
+    };
+    if let Err(e) = spool::write_json_atomic(&spool_dir.join(spool::PID_FILE), &pid_info) {
+        // The daemon could not track the task, so it must not keep running (and holding GPUs)
+        terminate_group(child, kill_grace);
+        return Err(e.context("Stopped the task, its pid file could not be written"));
+    }
+    let _ = write(&ready, &[1]);
+    drop(ready);
+
+    let (exit_code, signal) = wait_task(child)?;
+    terminate_group(child, kill_grace);
+
+    // All processes of the task are reaped now, so RUSAGE_CHILDREN covers exactly their usage
+    let usage = getrusage(UsageWho::RUSAGE_CHILDREN).context("getrusage failed")?;
+    let exit_info = ExitInfo {
+        exit_code,
+        signal,
+        user_time_ms: timeval_ms(usage.user_time()),
+        system_time_ms: timeval_ms(usage.system_time()),
+        max_rss_kb: usage.max_rss().max(0) as u64,
+        finished_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+    };
+    spool::write_json_atomic(&spool_dir.join(spool::STATUS_FILE), &exit_info)
+}
+
+// Waits for the task and returns its exit code or terminating signal
+fn wait_task(child: Pid) -> Result<(Option<i32>, Option<i32>)> {
+    loop {
+        match waitpid(child, None) {
+            Ok(WaitStatus::Exited(_, code)) => return Ok((Some(code), None)),
+            Ok(WaitStatus::Signaled(_, sig, _)) => return Ok((None, Some(sig as i32))),
+            Ok(_) => continue, // Stopped/continued, keep waiting
+            Err(Errno::EINTR) => continue,
+            Err(e) => return Err(anyhow!("waitpid for task {} failed: {}", child, e)),
+        }
+    }
+}
+
+// Terminates what is left of the task's process group once its main process has exited:
+// SIGTERM, then SIGKILL after `grace`. Returns when the group is empty.
+fn terminate_group(pgid: Pid, grace: Duration) {
+    reap_orphans();
+    if !group_alive(pgid) {
+        return;
+    }
+    eprintln!("gavel-shim: task left processes behind in group {}, sending SIGTERM", pgid);
+    let _ = killpg(pgid, Signal::SIGTERM);
+
+    let deadline = Instant::now() + grace;
+    let mut killed = false;
+    loop {
+        reap_orphans();
+        if !group_alive(pgid) {
+            return;
+        }
+        if !killed && Instant::now() >= deadline {
+            eprintln!("gavel-shim: processes in group {} survived SIGTERM, sending SIGKILL", pgid);
+            let _ = killpg(pgid, Signal::SIGKILL);
+            killed = true;
+        }
+        sleep(GROUP_POLL_INTERVAL);
+    }
+}
+
+// Processes still in the group; zombies count until they are reaped
+fn group_alive(pgid: Pid) -> bool {
+    killpg(pgid, None) != Err(Errno::ESRCH)
+}
+
+// Reaps exited orphans the supervisor inherited as a subreaper
+fn reap_orphans() {
+    while let Ok(status) = waitpid(Pid::from_raw(-1), Some(WaitPidFlag::WNOHANG)) {
+        if status == WaitStatus::StillAlive {
+            break;
+        }
+    }
+}
+
+fn timeval_ms(tv: TimeVal) -> u64 {
+    (tv.tv_sec().max(0) as u64) * 1000 + (tv.tv_usec().max(0) as u64) / 1000
+}
@@ -0,0 +1,54 @@
+// Runs gavel-shim on short commands and checks the status file its supervisor writes
+use std::path::Path;
+use std::process::Command;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use gavel_core::utils::models::ExitInfo;
+use gavel_core::utils::spool;
+
+// Starts `sh -c <script>` through the shim and waits for the task's status file
+fn run_shim(spool_dir: &Path, script: &str) -> ExitInfo {
+    let status = Command::new(env!("CARGO_BIN_EXE_gavel-shim"))
+        .args(["--kill-grace", "1"])
+        .arg(spool_dir)
+        .args(["sh", "-c", script])
+        .status()
+        .unwrap();
+    // The shim returns once the task runs and its pid file is written
+    assert!(status.success());
+    let pid_info = spool::read_pid_info(spool_dir).unwrap().expect("pid file");
+    assert_eq!(pid_info.pgid, Some(pid_info.pid));
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        if let Some(exit_info) = spool::read_exit_info(spool_dir).unwrap() {
+            return exit_info;
+        }
+        assert!(Instant::now() < deadline, "gavel-shim did not write {}", spool::STATUS_FILE);
+        sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn records_exit_code() {
+    let dir = tempfile::tempdir().unwrap();
+    let exit_info = run_shim(dir.path(), "exit 3");
+    assert_eq!(exit_info.exit_code, Some(3));
+    assert_eq!(exit_info.signal, None);
+    assert!(!exit_info.success());
+
+    // Each run gets a fresh spool directory, as the daemon clears it before every launch
+    let dir = tempfile::tempdir().unwrap();
+    let exit_info = run_shim(dir.path(), "true");
+    assert_eq!(exit_info.exit_code, Some(0));
+    assert!(exit_info.success());
+}
+
+#[test]
+fn records_terminating_signal() {
+    let dir = tempfile::tempdir().unwrap();
+    let exit_info = run_shim(dir.path(), "kill -TERM $$");
+    assert_eq!(exit_info.exit_code, None);
+    assert_eq!(exit_info.signal, Some(15));
+}